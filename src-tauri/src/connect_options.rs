@@ -0,0 +1,345 @@
+use crate::connection_import;
+use crate::connection_manager::{SavedConnection, SslMode};
+use sqlx::mysql::{MySqlConnectOptions, MySqlSslMode};
+use sqlx::postgres::{PgConnectOptions, PgSslMode};
+use std::path::Path;
+
+// Connect options are built field by field from the saved connection rather
+// than formatted into a URL, so credentials need no escaping. `host` and
+// `port` are the ones actually connected to, which differ from the saved
+// ones through an SSH tunnel. With a `socket`, the driver connects through
+// that relay to them instead and checks the certificate against the name
+// from `tls_server_name`.
+
+/// The name verify-full has to check the server's certificate against when
+/// it isn't `host`, the address actually connected to: the saved server name,
+/// or else the saved host, which differs from `host` behind an SSH tunnel.
+pub fn tls_server_name<'a>(config: &'a SavedConnection, host: &str) -> Option<&'a str> {
+    if config.ssl_mode != Some(SslMode::VerifyFull) {
+        return None;
+    }
+    let name = config
+        .ssl_server_name
+        .as_deref()
+        .filter(|name| !name.is_empty())
+        .unwrap_or(&config.host);
+    Some(name).filter(|name| !name.eq_ignore_ascii_case(host))
+}
+
+/// Postgres options for the connection. Without a saved password one is
+/// looked up in `.pgpass`, as libpq would. Extra parameters other than
+/// `application_name` are sent as server settings at startup, like
+/// `PGOPTIONS`.
+pub fn postgres(
+    config: &SavedConnection,
+    host: &str,
+    port: u16,
+    socket: Option<&Path>,
+) -> Result<PgConnectOptions, String> {
+    let mut options = PgConnectOptions::new_without_pgpass()
+        .host(host)
+        .port(port)
+        .username(&config.username);
+    let password = config
+        .password
+        .clone()
+        .filter(|p| !p.is_empty())
+        .or_else(|| connection_import::pgpass_password(config));
+    if let Some(password) = &password {
+        options = options.password(password);
+    }
+    if !config.database.is_empty() {
+        options = options.database(&config.database);
+    }
+
+    let mut settings = Vec::new();
+    for (key, value) in &config.extra_params {
+        match key.as_str() {
+            "application_name" => options = options.application_name(value),
+            _ => {
+                if key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == '=') {
+                    return Err(format!("Invalid parameter name: {:?}", key));
+                }
+                settings.push((key, escape_startup_option(value)));
+            }
+        }
+    }
+    if !settings.is_empty() {
+        options = options.options(settings);
+    }
+
+    // The socket is looked up as `.s.PGSQL.<port>` in the directory
+    let tls_host = match socket {
+        Some(dir) => {
+            let name = tls_server_name(config, host).unwrap_or(host);
+            options = options.host(name).socket(dir);
+            name
+        }
+        None => host,
+    };
+    postgres_tls(options, config, tls_host)
+}
+
+/// MySQL options for the connection, with the session variables to set on
+/// each new connection. Extra parameters other than `charset`, `collation`
+/// and `time_zone`, which the driver takes itself, become session variables.
+pub fn mysql(
+    config: &SavedConnection,
+    host: &str,
+    port: u16,
+    socket: Option<&Path>,
+) -> Result<(MySqlConnectOptions, Vec<(String, String)>), String> {
+    let mut options = MySqlConnectOptions::new()
+        .host(host)
+        .port(port)
+        .username(&config.username);
+    if let Some(password) = config.password.as_deref().filter(|p| !p.is_empty()) {
+        options = options.password(password);
+    }
+    if !config.database.is_empty() {
+        options = options.database(&config.database);
+    }
+
+    let mut session_variables = Vec::new();
+    for (key, value) in &config.extra_params {
+        options = match key.as_str() {
+            "charset" => options.charset(value),
+            "collation" => options.collation(value),
+            // The driver quotes the zone itself when setting it
+            "time_zone" if !value.contains(['\'', '\\']) => options.timezone(value.clone()),
+            "time_zone" => return Err(format!("Invalid time zone: {}", value)),
+            _ => {
+                // The name goes into the SET statement unquoted
+                if !is_variable_name(key) {
+                    return Err(format!("Invalid session variable name: {:?}", key));
+                }
+                session_variables.push((key.clone(), value.clone()));
+                options
+            }
+        };
+    }
+
+    let tls_host = match socket {
+        Some(path) => {
+            let name = tls_server_name(config, host).unwrap_or(host);
+            options = options.host(name).socket(path);
+            name
+        }
+        None => host,
+    };
+    Ok((mysql_tls(options, config, tls_host)?, session_variables))
+}
+
+fn is_variable_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The server splits startup options on whitespace, with a backslash
+/// escaping the next character.
+fn escape_startup_option(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\\' || c.is_whitespace() {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn postgres_tls(
+    options: PgConnectOptions,
+    config: &SavedConnection,
+    host: &str,
+) -> Result<PgConnectOptions, String> {
+    let tls = TlsSettings::from_config(config, host)?;
+    let mut options = options;
+    if let Some(mode) = tls.mode {
+        options = options.ssl_mode(match mode {
+            SslMode::Disable => PgSslMode::Disable,
+            SslMode::Allow => PgSslMode::Allow,
+            SslMode::Prefer => PgSslMode::Prefer,
+            SslMode::Require => PgSslMode::Require,
+            SslMode::VerifyCa => PgSslMode::VerifyCa,
+            SslMode::VerifyFull => PgSslMode::VerifyFull,
+        });
+    }
+    if let Some(root_cert) = tls.root_cert {
+        options = options.ssl_root_cert(root_cert);
+    }
+    if let Some((cert, key)) = tls.client_cert {
+        options = options.ssl_client_cert(cert).ssl_client_key(key);
+    }
+    Ok(options)
+}
+
+fn mysql_tls(
+    options: MySqlConnectOptions,
+    config: &SavedConnection,
+    host: &str,
+) -> Result<MySqlConnectOptions, String> {
+    let tls = TlsSettings::from_config(config, host)?;
+    let mut options = options;
+    if let Some(mode) = tls.mode {
+        options = options.ssl_mode(match mode {
+            SslMode::Disable => MySqlSslMode::Disabled,
+            SslMode::Allow | SslMode::Prefer => MySqlSslMode::Preferred,
+            SslMode::Require => MySqlSslMode::Required,
+            SslMode::VerifyCa => MySqlSslMode::VerifyCa,
+            SslMode::VerifyFull => MySqlSslMode::VerifyIdentity,
+        });
+    }
+    if let Some(root_cert) = tls.root_cert {
+        options = options.ssl_ca(root_cert);
+    }
+    if let Some((cert, key)) = tls.client_cert {
+        options = options.ssl_client_cert(cert).ssl_client_key(key);
+    }
+    Ok(options)
+}
+
+/// The saved TLS fields with empty ones dropped, checked for combinations
+/// the drivers can't honour.
+struct TlsSettings<'a> {
+    mode: Option<SslMode>,
+    root_cert: Option<&'a str>,
+    client_cert: Option<(&'a str, &'a str)>,
+}
+
+impl<'a> TlsSettings<'a> {
+    fn from_config(config: &'a SavedConnection, host: &str) -> Result<Self, String> {
+        let field = |value: &'a Option<String>| value.as_deref().filter(|v| !v.is_empty());
+        let client_cert = match (
+            field(&config.ssl_client_cert),
+            field(&config.ssl_client_key),
+        ) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            (None, None) => None,
+            _ => {
+                return Err("A client certificate and its key have to be given together".to_string())
+            }
+        };
+
+        // The drivers check the certificate against the host they are given,
+        // which has to be the expected name rather than, say, a tunnel's
+        if let Some(expected) = tls_server_name(config, host) {
+            return Err(format!(
+                "verify-full would check the certificate against {} rather than {}",
+                host, expected
+            ));
+        }
+
+        Ok(Self {
+            mode: config.ssl_mode,
+            root_cert: field(&config.ssl_root_cert),
+            client_cert,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(db_type: &str, extra_params: serde_json::Value) -> SavedConnection {
+        serde_json::from_value(serde_json::json!({
+            "id": "c1",
+            "name": "test",
+            "host": "db.example.com",
+            "username": "app",
+            "db_type": db_type,
+            "ssh_enabled": false,
+            "extra_params": extra_params,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn escapes_whitespace_and_backslashes_in_startup_options() {
+        assert_eq!(escape_startup_option("plain"), "plain");
+        assert_eq!(escape_startup_option("a b\tc"), "a\\ b\\\tc");
+        assert_eq!(escape_startup_option(r"C:\dir"), r"C:\\dir");
+    }
+
+    #[test]
+    fn sends_postgres_parameters_as_startup_options() {
+        let config = connection(
+            "postgres",
+            serde_json::json!({
+                "application_name": "tool",
+                "search_path": "app, public",
+                "statement_timeout": "5s",
+            }),
+        );
+        let options = postgres(&config, "db.example.com", 5432, None).unwrap();
+        assert_eq!(options.get_application_name(), Some("tool"));
+        assert_eq!(
+            options.get_options(),
+            Some("-c search_path=app,\\ public -c statement_timeout=5s")
+        );
+
+        for key in ["", "work mem", "a=b"] {
+            let config = connection("postgres", serde_json::json!({ key: "1" }));
+            assert!(postgres(&config, "db.example.com", 5432, None).is_err());
+        }
+    }
+
+    #[test]
+    fn turns_other_mysql_parameters_into_session_variables() {
+        let config = connection(
+            "mysql",
+            serde_json::json!({
+                "charset": "utf8mb4",
+                "max_execution_time": "1000",
+                "sql_mode": "ANSI_QUOTES",
+            }),
+        );
+        let (_, session_variables) = mysql(&config, "db.example.com", 3306, None).unwrap();
+        assert_eq!(
+            session_variables,
+            [
+                ("max_execution_time".to_string(), "1000".to_string()),
+                ("sql_mode".to_string(), "ANSI_QUOTES".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_verify_full_against_the_server_name() {
+        let mut config = connection("postgres", serde_json::json!({}));
+        config.ssl_mode = Some(SslMode::VerifyFull);
+        assert_eq!(tls_server_name(&config, "db.example.com"), None);
+        assert_eq!(
+            tls_server_name(&config, "127.0.0.1"),
+            Some("db.example.com")
+        );
+        config.ssl_server_name = Some("db.internal".to_string());
+        assert_eq!(
+            tls_server_name(&config, "db.example.com"),
+            Some("db.internal")
+        );
+
+        // Without a relay the driver would check the tunnel's address
+        assert!(postgres(&config, "127.0.0.1", 5432, None).is_err());
+        let options = postgres(&config, "127.0.0.1", 5432, Some(Path::new("/tmp/relay"))).unwrap();
+        assert_eq!(options.get_host(), "db.internal");
+
+        config.ssl_mode = Some(SslMode::VerifyCa);
+        assert_eq!(tls_server_name(&config, "127.0.0.1"), None);
+    }
+
+    #[test]
+    fn rejects_mysql_parameters_that_are_not_identifiers() {
+        for key in ["", "1st", "sql mode", "x = 1; DROP TABLE t", "`quoted`"] {
+            let config = connection("mysql", serde_json::json!({ key: "1" }));
+            assert!(
+                mysql(&config, "db.example.com", 3306, None).is_err(),
+                "{:?}",
+                key
+            );
+        }
+        let config = connection("mysql", serde_json::json!({ "time_zone": "'+00:00" }));
+        assert!(mysql(&config, "db.example.com", 3306, None).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::connection_manager::{self, SavedConnection};
+use crate::credentials::{self, CredentialStore, PasswordSealed, Secrets};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use tauri::{AppHandle, State};
+use zeroize::Zeroizing;
+
+// A bundle is a JSON file holding saved connections to hand to someone else.
+// Its connections are either in plain JSON or, when it has a password,
+// encrypted as a whole so nothing about them can be read without it.
+
+const BUNDLE_FORMAT: &str = "sql-tauri-connections";
+const BUNDLE_VERSION: u32 = 1;
+const BUNDLE_CONTEXT: &str = "connection-bundle";
+
+#[derive(Serialize, Deserialize)]
+struct Bundle {
+    format: String,
+    version: u32,
+    exported_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    connections: Option<Vec<SavedConnection>>,
+    /// The connections, encrypted, when the bundle has a password.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted: Option<PasswordSealed>,
+}
+
+/// What to do with a bundled connection whose id is already saved.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Keep the saved connection.
+    Skip,
+    /// Replace the saved connection. Its stored passwords are kept unless
+    /// the bundle has passwords for it.
+    Overwrite,
+    /// Import the bundled connection under a new id.
+    Duplicate,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BundleAction {
+    Create,
+    Overwrite,
+    Duplicate,
+    Skip,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BundleEntry {
+    /// The connection as it would be saved, without its passwords.
+    pub connection: SavedConnection,
+    pub has_password: bool,
+    /// Whether a connection with the bundled id is already saved, or comes
+    /// earlier in the bundle.
+    pub conflict: bool,
+    pub action: BundleAction,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BundleImport {
+    pub entries: Vec<BundleEntry>,
+    /// Connections saved, overwritten ones included; always 0 on a dry run.
+    pub imported: usize,
+}
+
+/// Writes the selected connections, or all of them, to a bundle. Passwords
+/// are only included on request, which needs the credential store unlocked
+/// and a bundle password so they aren't written out in plaintext.
+#[tauri::command]
+pub async fn export_connections(
+    app_handle: AppHandle,
+    credentials: State<'_, CredentialStore>,
+    path: String,
+    ids: Option<Vec<String>>,
+    include_secrets: bool,
+    password: Option<String>,
+) -> Result<usize, String> {
+    let password = password.filter(|p| !p.is_empty()).map(Zeroizing::new);
+    if include_secrets && password.is_none() {
+        return Err("Including passwords requires a bundle password".to_string());
+    }
+
+    let dir = connection_manager::data_dir(&app_handle);
+    let mut connections = connection_manager::read_connections(&dir)?;
+    if let Some(ids) = &ids {
+        if let Some(missing) = ids
+            .iter()
+            .find(|id| !connections.iter().any(|c| &c.id == *id))
+        {
+            return Err(format!("Connection {} does not exist", missing));
+        }
+        connections.retain(|c| ids.contains(&c.id));
+    }
+    for connection in &mut connections {
+        if include_secrets {
+            credentials.fill(&dir, connection)?;
+        } else {
+            Secrets::take(connection);
+        }
+    }
+
+    let mut bundle = Bundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        connections: None,
+        encrypted: None,
+    };
+    match &password {
+        Some(password) => {
+            let plaintext =
+                Zeroizing::new(serde_json::to_vec(&connections).map_err(|e| e.to_string())?);
+            bundle.encrypted = Some(PasswordSealed::seal(password, BUNDLE_CONTEXT, &plaintext)?);
+        }
+        None => bundle.connections = Some(connections.clone()),
+    }
+
+    let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(connections.len())
+}
+
+/// Imports a bundle's connections, resolving id conflicts as asked. With
+/// `dry_run` nothing is saved and the entries show what would happen.
+#[tauri::command]
+pub async fn import_connection_bundle(
+    app_handle: AppHandle,
+    credentials: State<'_, CredentialStore>,
+    path: String,
+    password: Option<String>,
+    on_conflict: ConflictResolution,
+    dry_run: bool,
+) -> Result<BundleImport, String> {
+    let password = password.filter(|p| !p.is_empty()).map(Zeroizing::new);
+    let bundled = read_bundle(&path, password.as_deref().map(String::as_str))?;
+
+    let dir = connection_manager::data_dir(&app_handle);
+    let mut connections = connection_manager::read_connections(&dir)?;
+    let mut saved_ids: HashSet<String> = connections.iter().map(|c| c.id.clone()).collect();
+
+    let mut entries = Vec::with_capacity(bundled.len());
+    let mut to_store: Vec<(String, Secrets)> = Vec::new();
+    for mut connection in bundled {
+        let conflict = saved_ids.contains(&connection.id);
+        let action = match (conflict, on_conflict) {
+            (false, _) => BundleAction::Create,
+            (true, ConflictResolution::Skip) => BundleAction::Skip,
+            (true, ConflictResolution::Overwrite) => BundleAction::Overwrite,
+            (true, ConflictResolution::Duplicate) => BundleAction::Duplicate,
+        };
+        if action == BundleAction::Duplicate {
+            connection.id = uuid::Uuid::new_v4().to_string();
+        }
+        let secrets = Secrets::take(&mut connection);
+        let has_password = !secrets.is_empty();
+
+        match action {
+            BundleAction::Skip => {}
+            BundleAction::Overwrite => {
+                if let Some(saved) = connections.iter_mut().find(|c| c.id == connection.id) {
+                    // Plaintext passwords live on the saved record itself
+                    let kept = Secrets::take(saved);
+                    *saved = connection.clone();
+                    if !has_password {
+                        kept.apply_to(saved);
+                    }
+                }
+                if has_password {
+                    to_store.retain(|(id, _)| *id != connection.id);
+                }
+            }
+            BundleAction::Create | BundleAction::Duplicate => {
+                saved_ids.insert(connection.id.clone());
+                connections.push(connection.clone());
+            }
+        }
+        if action != BundleAction::Skip && has_password {
+            to_store.push((connection.id.clone(), secrets));
+        }
+        entries.push(BundleEntry {
+            connection,
+            has_password,
+            conflict,
+            action,
+        });
+    }
+
+    let imported = entries
+        .iter()
+        .filter(|e| e.action != BundleAction::Skip)
+        .count();
+    if dry_run || imported == 0 {
+        return Ok(BundleImport {
+            entries,
+            imported: 0,
+        });
+    }
+
+    if !credentials::is_initialized(&dir) {
+        // Without a master password they are saved with the connections
+        for (id, secrets) in to_store {
+            if let Some(saved) = connections.iter_mut().find(|c| c.id == id) {
+                secrets.apply_to(saved);
+            }
+        }
+    } else if !to_store.is_empty() && !credentials.is_unlocked() {
+        return Err("Unlock the credential store to import passwords".to_string());
+    } else {
+        for (id, secrets) in &to_store {
+            credentials.set(&dir, id, secrets)?;
+        }
+    }
+    connection_manager::write_connections(&dir, &connections)?;
+    Ok(BundleImport { entries, imported })
+}
+
+fn read_bundle(path: &str, password: Option<&str>) -> Result<Vec<SavedConnection>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let bundle: Bundle =
+        serde_json::from_str(&content).map_err(|_| "Not a connection bundle".to_string())?;
+    if bundle.format != BUNDLE_FORMAT {
+        return Err("Not a connection bundle".to_string());
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(format!(
+            "Bundle version {} is newer than this app supports",
+            bundle.version
+        ));
+    }
+
+    match (bundle.encrypted, bundle.connections) {
+        (Some(sealed), _) => {
+            let password = password.ok_or("This bundle is password-protected")?;
+            let plaintext = sealed
+                .open(password, BUNDLE_CONTEXT)?
+                .ok_or("Incorrect bundle password")?;
+            serde_json::from_slice(&plaintext).map_err(|e| format!("Failed to parse bundle: {}", e))
+        }
+        (None, Some(connections)) => Ok(connections),
+        (None, None) => Err("The bundle holds no connections".to_string()),
+    }
+}
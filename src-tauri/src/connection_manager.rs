@@ -1,46 +1,142 @@
+use crate::credentials::{self, CredentialStore, Secrets};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
-use tauri::AppHandle;
+use std::path::{Path, PathBuf};
 use tauri::Manager;
+use tauri::{AppHandle, State};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SavedConnection {
     pub id: String,
     pub name: String,
+    #[serde(default)]
     pub host: String,
+    #[serde(default)]
     pub port: u16,
+    #[serde(default)]
     pub username: String,
     pub password: Option<String>,
+    #[serde(default)]
     pub database: String,
     #[serde(default = "default_db_type")]
     pub db_type: String,
+    /// Path to the database file, used instead of host/port for SQLite.
+    #[serde(default)]
+    pub file_path: Option<String>,
     pub ssh_enabled: bool,
     pub ssh_host: Option<String>,
     pub ssh_port: Option<u16>,
     pub ssh_user: Option<String>,
     pub ssh_password: Option<String>,
     pub ssh_key_path: Option<String>,
+    /// Left to the driver's default (prefer) when unset.
+    #[serde(default)]
+    pub ssl_mode: Option<SslMode>,
+    /// CA certificate file (PEM) the server's certificate must chain to.
+    #[serde(default)]
+    pub ssl_root_cert: Option<String>,
+    #[serde(default)]
+    pub ssl_client_cert: Option<String>,
+    #[serde(default)]
+    pub ssl_client_key: Option<String>,
+    /// The name verify-full checks the certificate against, when it isn't
+    /// `host`. Behind an SSH tunnel `host` is checked rather than the
+    /// tunnel's local address.
+    #[serde(default)]
+    pub ssl_server_name: Option<String>,
+    /// Driver and session settings such as `application_name`,
+    /// `statement_timeout` or MySQL's `charset` and `max_execution_time`.
+    #[serde(default)]
+    pub extra_params: BTreeMap<String, String>,
+}
+
+/// libpq's sslmode values. MySQL's modes map onto them, with `verify-full`
+/// standing for VERIFY_IDENTITY and `allow` behaving like `prefer`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SslMode {
+    Disable,
+    Allow,
+    Prefer,
+    Require,
+    VerifyCa,
+    VerifyFull,
+}
+
+impl SslMode {
+    /// Parses either a libpq sslmode or a MySQL ssl-mode value.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().replace('_', "-").as_str() {
+            "disable" | "disabled" => Some(Self::Disable),
+            "allow" => Some(Self::Allow),
+            "prefer" | "preferred" => Some(Self::Prefer),
+            "require" | "required" => Some(Self::Require),
+            "verify-ca" => Some(Self::VerifyCa),
+            "verify-full" | "verify-identity" => Some(Self::VerifyFull),
+            _ => None,
+        }
+    }
 }
 
 fn default_db_type() -> String {
     "postgres".to_string()
 }
 
-fn get_connections_file_path(app_handle: &AppHandle) -> PathBuf {
-    let mut path = app_handle
+/// The app data directory, where connections, credentials and app state are kept.
+pub fn data_dir(app_handle: &AppHandle) -> PathBuf {
+    let path = app_handle
         .path()
         .app_data_dir()
         .expect("failed to get app data dir");
     fs::create_dir_all(&path).expect("failed to create app data dir");
-    path.push("connections.json");
     path
 }
 
+fn connections_file_path(dir: &Path) -> PathBuf {
+    dir.join("connections.json")
+}
+
+pub fn read_connections(dir: &Path) -> Result<Vec<SavedConnection>, String> {
+    let path = connections_file_path(dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let connections: Vec<SavedConnection> =
+        serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    Ok(connections)
+}
+
+pub fn write_connections(dir: &Path, connections: &[SavedConnection]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(connections).map_err(|e| e.to_string())?;
+    fs::write(connections_file_path(dir), json).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Saves the connection with its passwords in the credential store, which
+/// has to be unlocked for that. Saving while it is locked leaves any stored
+/// passwords as they are. Without a master password set, passwords are
+/// saved with the connection.
 #[tauri::command]
-pub fn save_connection(app_handle: AppHandle, connection: SavedConnection) -> Result<(), String> {
-    let path = get_connections_file_path(&app_handle);
-    let mut connections = load_connections(app_handle.clone()).unwrap_or_default();
+pub fn save_connection(
+    app_handle: AppHandle,
+    credentials: State<'_, CredentialStore>,
+    connection: SavedConnection,
+) -> Result<(), String> {
+    let dir = data_dir(&app_handle);
+    let mut connection = connection;
+    if credentials::is_initialized(&dir) {
+        let secrets = Secrets::take(&mut connection);
+        if credentials.is_unlocked() {
+            credentials.set(&dir, &connection.id, &secrets)?;
+        } else if !secrets.is_empty() {
+            return Err("Unlock the credential store to save passwords".to_string());
+        }
+    }
+
+    let mut connections = read_connections(&dir).unwrap_or_default();
 
     // Update existing or add new
     if let Some(idx) = connections.iter().position(|c| c.id == connection.id) {
@@ -49,32 +145,87 @@ pub fn save_connection(app_handle: AppHandle, connection: SavedConnection) -> Re
         connections.push(connection);
     }
 
-    let json = serde_json::to_string_pretty(&connections).map_err(|e| e.to_string())?;
-    fs::write(path, json).map_err(|e| e.to_string())?;
-    Ok(())
+    write_connections(&dir, &connections)
 }
 
+/// The saved connections, with their passwords filled in while the
+/// credential store is unlocked.
 #[tauri::command]
-pub fn load_connections(app_handle: AppHandle) -> Result<Vec<SavedConnection>, String> {
-    let path = get_connections_file_path(&app_handle);
-    if !path.exists() {
-        return Ok(Vec::new());
+pub fn load_connections(
+    app_handle: AppHandle,
+    credentials: State<'_, CredentialStore>,
+) -> Result<Vec<SavedConnection>, String> {
+    let dir = data_dir(&app_handle);
+    let mut connections = read_connections(&dir)?;
+    if credentials.is_unlocked() {
+        for connection in &mut connections {
+            credentials.fill(&dir, connection)?;
+        }
     }
-
-    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
-    let connections: Vec<SavedConnection> =
-        serde_json::from_str(&content).map_err(|e| e.to_string())?;
     Ok(connections)
 }
 
 #[tauri::command]
-pub fn delete_connection(app_handle: AppHandle, id: String) -> Result<(), String> {
-    let path = get_connections_file_path(&app_handle);
-    let mut connections = load_connections(app_handle.clone()).unwrap_or_default();
+pub fn delete_connection(
+    app_handle: AppHandle,
+    credentials: State<'_, CredentialStore>,
+    id: String,
+) -> Result<(), String> {
+    let dir = data_dir(&app_handle);
+    let mut connections = read_connections(&dir).unwrap_or_default();
 
     connections.retain(|c| c.id != id);
 
-    let json = serde_json::to_string_pretty(&connections).map_err(|e| e.to_string())?;
-    fs::write(path, json).map_err(|e| e.to_string())?;
-    Ok(())
+    write_connections(&dir, &connections)?;
+    credentials.remove(&dir, &id)
+}
+
+/// Appends the connections, storing their passwords in the credential store
+/// once a master password is set.
+pub fn add_connections(
+    dir: &Path,
+    credentials: &CredentialStore,
+    new_connections: Vec<SavedConnection>,
+) -> Result<(), String> {
+    let mut connections = read_connections(dir)?;
+    let use_store = credentials::is_initialized(dir);
+    for mut connection in new_connections {
+        if use_store {
+            let secrets = Secrets::take(&mut connection);
+            credentials.set(dir, &connection.id, &secrets)?;
+        }
+        connections.push(connection);
+    }
+    write_connections(dir, &connections)
+}
+
+/// Connections saved before the credential store existed, whose passwords
+/// are still in connections.json.
+pub fn plaintext_connection_count(dir: &Path) -> Result<usize, String> {
+    Ok(read_connections(dir)?
+        .iter()
+        .filter(|c| has_secrets(c))
+        .count())
+}
+
+/// Moves passwords still in connections.json into the unlocked credential
+/// store. They are written to the store before being removed from the file.
+pub fn migrate_plaintext_secrets(dir: &Path, credentials: &CredentialStore) -> Result<(), String> {
+    let mut connections = read_connections(dir)?;
+    if !connections.iter().any(has_secrets) {
+        return Ok(());
+    }
+    for connection in &mut connections {
+        if has_secrets(connection) {
+            let secrets = Secrets::take(connection);
+            credentials.set(dir, &connection.id, &secrets)?;
+        }
+    }
+    write_connections(dir, &connections)
+}
+
+/// Whether the connection carries a non-empty password or SSH password.
+pub fn has_secrets(connection: &SavedConnection) -> bool {
+    let present = |secret: &Option<String>| secret.as_deref().is_some_and(|s| !s.is_empty());
+    present(&connection.password) || present(&connection.ssh_password)
 }
@@ -0,0 +1,418 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+use zeroize::Zeroizing;
+
+use crate::connection_manager::{self, SavedConnection};
+use crate::state;
+
+// Passwords live in credentials.json next to connections.json, one
+// XChaCha20-Poly1305 entry per connection. The key is derived from the master
+// password with Argon2id and only held in memory while the store is unlocked.
+
+const VAULT_VERSION: u32 = 1;
+const KEY_LENGTH: usize = 32;
+const SALT_LENGTH: usize = 16;
+/// Encrypted with the key when the store is created, to tell a wrong master
+/// password from a corrupt entry.
+const CHECK_PLAINTEXT: &[u8] = b"credentials";
+const CHECK_CONTEXT: &str = "check";
+
+type Key = Zeroizing<[u8; KEY_LENGTH]>;
+
+/// Argon2id cost settings, stored with the salt so they can be raised later
+/// without breaking existing stores.
+#[derive(Serialize, Deserialize)]
+struct KdfParams {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+}
+
+impl KdfParams {
+    fn generate() -> Self {
+        let mut salt = [0u8; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+            salt: BASE64.encode(salt),
+        }
+    }
+
+    fn derive_key(&self, master_password: &str) -> Result<Key, String> {
+        let salt = BASE64
+            .decode(&self.salt)
+            .map_err(|e| format!("Invalid credential store salt: {}", e))?;
+        let params = Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+            Some(KEY_LENGTH),
+        )
+        .map_err(|e| format!("Invalid key derivation settings: {}", e))?;
+        let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(master_password.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| format!("Failed to derive the key: {}", e))?;
+        Ok(key)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SealedEntry {
+    nonce: String,
+    ciphertext: String,
+}
+
+impl SealedEntry {
+    /// Encrypts `plaintext`, binding it to `context` so an entry copied to
+    /// another connection id fails to decrypt.
+    fn seal(key: &Key, context: &str, plaintext: &[u8]) -> Result<Self, String> {
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: context.as_bytes(),
+                },
+            )
+            .map_err(|_| "Failed to encrypt credentials".to_string())?;
+        Ok(Self {
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    /// None if the key is wrong or the entry was tampered with.
+    fn open(&self, key: &Key, context: &str) -> Option<Zeroizing<Vec<u8>>> {
+        let nonce = BASE64.decode(&self.nonce).ok()?;
+        if nonce.len() != 24 {
+            return None;
+        }
+        let ciphertext = BASE64.decode(&self.ciphertext).ok()?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        cipher
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: context.as_bytes(),
+                },
+            )
+            .ok()
+            .map(Zeroizing::new)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    kdf: KdfParams,
+    check: SealedEntry,
+    /// Keyed by connection id.
+    #[serde(default)]
+    entries: BTreeMap<String, SealedEntry>,
+}
+
+impl VaultFile {
+    fn read(path: &Path) -> Result<Option<Self>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read credential store: {}", e))?;
+        let vault: VaultFile = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse credential store: {}", e))?;
+        if vault.version > VAULT_VERSION {
+            return Err(format!(
+                "Credential store version {} is newer than this app supports",
+                vault.version
+            ));
+        }
+        Ok(Some(vault))
+    }
+
+    /// Writes through a temporary file so a failed write can't leave the
+    /// store half written.
+    fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, json).map_err(|e| format!("Failed to write credential store: {}", e))?;
+        fs::rename(&temp, path).map_err(|e| format!("Failed to write credential store: {}", e))
+    }
+}
+
+/// Data encrypted with a key derived from a password of its own rather than
+/// the master password, e.g. to share it.
+#[derive(Serialize, Deserialize)]
+pub struct PasswordSealed {
+    kdf: KdfParams,
+    #[serde(flatten)]
+    entry: SealedEntry,
+}
+
+impl PasswordSealed {
+    pub fn seal(password: &str, context: &str, plaintext: &[u8]) -> Result<Self, String> {
+        let kdf = KdfParams::generate();
+        let key = kdf.derive_key(password)?;
+        Ok(Self {
+            entry: SealedEntry::seal(&key, context, plaintext)?,
+            kdf,
+        })
+    }
+
+    /// None if the password is wrong.
+    pub fn open(
+        &self,
+        password: &str,
+        context: &str,
+    ) -> Result<Option<Zeroizing<Vec<u8>>>, String> {
+        let key = self.kdf.derive_key(password)?;
+        Ok(self.entry.open(&key, context))
+    }
+}
+
+/// The secret fields of a saved connection.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Secrets {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_password: Option<String>,
+}
+
+impl Secrets {
+    /// Removes the secrets from the connection, ignoring empty ones.
+    pub fn take(connection: &mut SavedConnection) -> Self {
+        Self {
+            password: connection.password.take().filter(|s| !s.is_empty()),
+            ssh_password: connection.ssh_password.take().filter(|s| !s.is_empty()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.password.is_none() && self.ssh_password.is_none()
+    }
+
+    /// Fills in the fields the connection doesn't already have.
+    pub fn apply_to(self, connection: &mut SavedConnection) {
+        if connection.password.as_deref().unwrap_or("").is_empty() {
+            connection.password = self.password;
+        }
+        if connection.ssh_password.as_deref().unwrap_or("").is_empty() {
+            connection.ssh_password = self.ssh_password;
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct CredentialStoreStatus {
+    /// Whether a master password has been set.
+    pub initialized: bool,
+    pub unlocked: bool,
+    /// Saved connections whose passwords are still stored in plaintext, and
+    /// will be moved into the store when it is next unlocked.
+    pub plaintext_connections: usize,
+}
+
+/// Managed state holding the key while the store is unlocked.
+#[derive(Default)]
+pub struct CredentialStore {
+    key: Mutex<Option<Key>>,
+}
+
+impl CredentialStore {
+    pub fn is_unlocked(&self) -> bool {
+        self.key.lock().unwrap().is_some()
+    }
+
+    /// Derives the key from the master password and keeps it until `lock`.
+    /// The first unlock creates the store with that password. Argon2 is slow
+    /// on purpose, so the key is derived on a blocking thread and the key
+    /// lock is only taken to keep the result.
+    pub async fn unlock(
+        &self,
+        dir: &Path,
+        master_password: Zeroizing<String>,
+    ) -> Result<(), String> {
+        let path = vault_path(dir);
+        let vault = VaultFile::read(&path)?;
+        let (derived, created) =
+            tokio::task::spawn_blocking(move || derive_vault_key(vault, &master_password))
+                .await
+                .map_err(|e| format!("Failed to derive the key: {}", e))??;
+
+        let mut key = self.key.lock().unwrap();
+        if let Some(vault) = created {
+            // Another unlock may have set a master password in the meantime
+            if path.exists() {
+                return Err("A master password was just set, unlock again".to_string());
+            }
+            vault.write(&path)?;
+        }
+        *key = Some(derived);
+        Ok(())
+    }
+
+    pub fn lock(&self) {
+        *self.key.lock().unwrap() = None;
+    }
+
+    /// The connection's stored secrets, or None if it has none.
+    pub fn get(&self, dir: &Path, connection_id: &str) -> Result<Option<Secrets>, String> {
+        let key = self.key.lock().unwrap();
+        let Some(vault) = VaultFile::read(&vault_path(dir))? else {
+            return Ok(None);
+        };
+        let Some(entry) = vault.entries.get(connection_id) else {
+            return Ok(None);
+        };
+        let key = key.as_ref().ok_or_else(locked)?;
+        let plaintext = entry
+            .open(key, connection_id)
+            .ok_or("Stored credentials could not be decrypted")?;
+        serde_json::from_slice(&plaintext)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse stored credentials: {}", e))
+    }
+
+    /// Replaces the connection's stored secrets. Empty secrets remove the
+    /// entry, which doesn't need the store to be unlocked.
+    pub fn set(&self, dir: &Path, connection_id: &str, secrets: &Secrets) -> Result<(), String> {
+        if secrets.is_empty() {
+            return self.remove(dir, connection_id);
+        }
+        let path = vault_path(dir);
+        let key = self.key.lock().unwrap();
+        let key = key.as_ref().ok_or_else(locked)?;
+        let mut vault = VaultFile::read(&path)?.ok_or_else(locked)?;
+        let plaintext = Zeroizing::new(serde_json::to_vec(secrets).map_err(|e| e.to_string())?);
+        let entry = SealedEntry::seal(key, connection_id, &plaintext)?;
+        vault.entries.insert(connection_id.to_string(), entry);
+        vault.write(&path)
+    }
+
+    pub fn remove(&self, dir: &Path, connection_id: &str) -> Result<(), String> {
+        let path = vault_path(dir);
+        let _key = self.key.lock().unwrap();
+        let Some(mut vault) = VaultFile::read(&path)? else {
+            return Ok(());
+        };
+        if vault.entries.remove(connection_id).is_some() {
+            vault.write(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Fills in the connection's missing secrets from the store. Fails if
+    /// the connection has stored secrets but the store is locked.
+    pub fn fill(&self, dir: &Path, connection: &mut SavedConnection) -> Result<(), String> {
+        if let Some(secrets) = self.get(dir, &connection.id)? {
+            secrets.apply_to(connection);
+        }
+        Ok(())
+    }
+
+    fn status(&self, dir: &Path) -> Result<CredentialStoreStatus, String> {
+        Ok(CredentialStoreStatus {
+            initialized: is_initialized(dir),
+            unlocked: self.is_unlocked(),
+            plaintext_connections: connection_manager::plaintext_connection_count(dir)?,
+        })
+    }
+}
+
+/// Checks the master password against an existing store, or sets up a new
+/// store for it, which the caller still has to write.
+fn derive_vault_key(
+    vault: Option<VaultFile>,
+    master_password: &str,
+) -> Result<(Key, Option<VaultFile>), String> {
+    match vault {
+        Some(vault) => {
+            let derived = vault.kdf.derive_key(master_password)?;
+            vault
+                .check
+                .open(&derived, CHECK_CONTEXT)
+                .filter(|check| check.as_slice() == CHECK_PLAINTEXT)
+                .ok_or("Incorrect master password")?;
+            Ok((derived, None))
+        }
+        None => {
+            if master_password.is_empty() {
+                return Err("The master password can't be empty".to_string());
+            }
+            let kdf = KdfParams::generate();
+            let derived = kdf.derive_key(master_password)?;
+            let vault = VaultFile {
+                version: VAULT_VERSION,
+                check: SealedEntry::seal(&derived, CHECK_CONTEXT, CHECK_PLAINTEXT)?,
+                kdf,
+                entries: BTreeMap::new(),
+            };
+            Ok((derived, Some(vault)))
+        }
+    }
+}
+
+fn vault_path(dir: &Path) -> PathBuf {
+    dir.join("credentials.json")
+}
+
+/// Whether a master password has been set. Until then passwords are saved in
+/// plaintext as before, since nothing could unlock them after a restart.
+pub fn is_initialized(dir: &Path) -> bool {
+    vault_path(dir).exists()
+}
+
+fn locked() -> String {
+    "The credential store is locked".to_string()
+}
+
+#[tauri::command]
+pub fn get_credential_store_status(
+    app_handle: AppHandle,
+    credentials: State<'_, CredentialStore>,
+) -> Result<CredentialStoreStatus, String> {
+    credentials.status(&connection_manager::data_dir(&app_handle))
+}
+
+/// Unlocks the store, creating it on first use, and moves any passwords
+/// still stored in plaintext into it. Async so the key derivation runs off
+/// the main thread.
+#[tauri::command]
+pub async fn unlock_credential_store(
+    app_handle: AppHandle,
+    credentials: State<'_, CredentialStore>,
+    master_password: String,
+) -> Result<CredentialStoreStatus, String> {
+    let master_password = Zeroizing::new(master_password);
+    let dir = connection_manager::data_dir(&app_handle);
+    credentials.unlock(&dir, master_password).await?;
+    connection_manager::migrate_plaintext_secrets(&dir, &credentials)?;
+    state::strip_plaintext_secrets(&dir, &credentials)?;
+    credentials.status(&dir)
+}
+
+#[tauri::command]
+pub fn lock_credential_store(
+    app_handle: AppHandle,
+    credentials: State<'_, CredentialStore>,
+) -> Result<CredentialStoreStatus, String> {
+    credentials.lock();
+    credentials.status(&connection_manager::data_dir(&app_handle))
+}
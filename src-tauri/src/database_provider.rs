@@ -1,28 +1,289 @@
-use crate::db::{ColumnDefinition, DatabaseError, Filter, IndexDefinition, QueryResult, Sort};
+use crate::db::{
+    ColumnDefinition, ConstraintDefinition, ConstraintKind, DatabaseError, DatabaseObject, Filter,
+    IncomingForeignKey, IndexDefinition, QueryResult, QueryStreamEvent, RelatedRows,
+    RelationDirection, Sort, StatementResult,
+};
+use crate::dml::{self, DmlStatement, RowUpdate, TableChanges};
+use crate::session::TransactionState;
+use crate::sql_splitter::{SqlDialect, Statement};
 use async_trait::async_trait;
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
 #[async_trait]
 pub trait DatabaseProvider: Send + Sync {
+    fn dialect(&self) -> SqlDialect;
     async fn list_databases(&self) -> Result<Vec<String>, DatabaseError>;
-    async fn list_tables(&self) -> Result<Vec<String>, DatabaseError>;
+    /// Schemas (Postgres), databases (MySQL) or attached databases (SQLite)
+    /// that can hold tables.
+    async fn list_schemas(&self) -> Result<Vec<String>, DatabaseError>;
+    /// Table-level calls take the schema separately from the table name.
+    /// Without one, names resolve the way the server would resolve an
+    /// unqualified name (Postgres `search_path`, MySQL's current database,
+    /// SQLite's `main`).
+    async fn list_tables(&self, schema: Option<String>) -> Result<Vec<String>, DatabaseError>;
+    /// Tables, views, routines, triggers and other objects in the schema,
+    /// each labelled with its kind.
+    async fn list_objects(
+        &self,
+        schema: Option<String>,
+    ) -> Result<Vec<DatabaseObject>, DatabaseError>;
+    async fn get_object_ddl(
+        &self,
+        schema: Option<String>,
+        object: DatabaseObject,
+    ) -> Result<String, DatabaseError>;
     async fn get_table_data(
         &self,
+        schema: Option<String>,
         table_name: String,
         limit: i64,
         offset: i64,
         filters: Vec<Filter>,
         sorts: Vec<Sort>,
     ) -> Result<QueryResult, DatabaseError>;
+    /// Streams every row of the filtered, sorted table, e.g. for exporting it.
+    async fn stream_table_data(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        filters: Vec<Filter>,
+        sorts: Vec<Sort>,
+        query_id: Option<String>,
+        on_event: &(dyn Fn(QueryStreamEvent) -> bool + Send + Sync),
+    ) -> Result<(), DatabaseError>;
     async fn get_table_structure(
         &self,
+        schema: Option<String>,
         table_name: String,
     ) -> Result<Vec<ColumnDefinition>, DatabaseError>;
     async fn get_table_indexes(
         &self,
+        schema: Option<String>,
         table_name: String,
     ) -> Result<Vec<IndexDefinition>, DatabaseError>;
-    async fn execute_query(&self, query: String) -> Result<QueryResult, DatabaseError>;
+    async fn get_table_constraints(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+    ) -> Result<Vec<ConstraintDefinition>, DatabaseError>;
+    async fn get_incoming_foreign_keys(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+    ) -> Result<Vec<IncomingForeignKey>, DatabaseError>;
+    /// The parent row of each of the row's foreign keys and the child rows of
+    /// each foreign key referencing the table. Keys the row has NULLs in are
+    /// skipped, as nothing can be related through them.
+    async fn get_related_rows(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        row: &Map<String, Value>,
+    ) -> Result<Vec<RelatedRows>, DatabaseError> {
+        let mut related = Vec::new();
+        let constraints = self
+            .get_table_constraints(schema.clone(), table_name.clone())
+            .await?;
+        for constraint in constraints {
+            if constraint.kind != ConstraintKind::ForeignKey {
+                continue;
+            }
+            let filters = key_filters(
+                row,
+                &constraint.referenced_columns,
+                &constraint.column_names,
+            );
+            if let (Some(filters), Some(parent)) = (filters, constraint.referenced_table) {
+                related.push(RelatedRows {
+                    direction: RelationDirection::Parent,
+                    constraint_name: constraint.constraint_name,
+                    schema: constraint.referenced_schema,
+                    table_name: parent,
+                    filters,
+                });
+            }
+        }
+        for key in self.get_incoming_foreign_keys(schema, table_name).await? {
+            if let Some(filters) = key_filters(row, &key.column_names, &key.referenced_columns) {
+                related.push(RelatedRows {
+                    direction: RelationDirection::Child,
+                    constraint_name: key.constraint_name,
+                    schema: key.schema,
+                    table_name: key.table_name,
+                    filters,
+                });
+            }
+        }
+        Ok(related)
+    }
+    async fn execute_query(
+        &self,
+        query: String,
+        query_id: Option<String>,
+        session_id: Option<String>,
+    ) -> Result<QueryResult, DatabaseError>;
+    async fn execute_query_stream(
+        &self,
+        query: String,
+        query_id: Option<String>,
+        session_id: Option<String>,
+        batch_size: usize,
+        max_rows: usize,
+        on_event: &(dyn Fn(QueryStreamEvent) -> bool + Send + Sync),
+    ) -> Result<(), DatabaseError>;
+    /// Runs the statements in order on a single connection (the session's
+    /// transaction, if one is open). With `stop_on_error`
+    /// the remaining statements are skipped after the first failure.
+    async fn execute_script(
+        &self,
+        statements: Vec<Statement>,
+        stop_on_error: bool,
+        query_id: Option<String>,
+        session_id: Option<String>,
+    ) -> Result<Vec<StatementResult>, DatabaseError>;
+    /// Stops a query started with the given id. Returns false if it is no longer running.
+    async fn cancel_query(&self, query_id: String) -> Result<bool, DatabaseError>;
+    /// Opens a transaction pinned to its own connection. Queries passing the
+    /// same `session_id` run inside it until it is committed or rolled back.
+    async fn begin_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError>;
+    async fn commit_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError>;
+    async fn rollback_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError>;
+    async fn create_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError>;
+    async fn rollback_to_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError>;
+    async fn release_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError>;
+    fn transaction_state(&self, session_id: &str) -> TransactionState;
+    /// Runs the statements in a single transaction and returns the total
+    /// rows affected. Nothing is committed if any statement fails.
+    async fn execute_dml(&self, statements: Vec<DmlStatement>) -> Result<u64, DatabaseError>;
+    /// Inserts `rows`, each holding values in `columns` order, in a single
+    /// transaction using the fastest path the database offers.
+    async fn bulk_insert(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        columns: Vec<String>,
+        rows: Vec<Vec<Value>>,
+    ) -> Result<u64, DatabaseError>;
+    async fn plan_changes(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        changes: &TableChanges,
+    ) -> Result<Vec<DmlStatement>, DatabaseError> {
+        let columns = self
+            .get_table_structure(schema.clone(), table_name.clone())
+            .await?;
+        let indexes = self
+            .get_table_indexes(schema.clone(), table_name.clone())
+            .await?;
+        dml::plan_changes(
+            self.dialect(),
+            schema.as_deref(),
+            &table_name,
+            &columns,
+            &indexes,
+            changes,
+        )
+        .map_err(DatabaseError::new)
+    }
+    async fn apply_changes(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        changes: TableChanges,
+    ) -> Result<u64, DatabaseError> {
+        let statements = self.plan_changes(schema, table_name, &changes).await?;
+        self.execute_dml(statements).await
+    }
+    async fn update_rows(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        updates: Vec<RowUpdate>,
+    ) -> Result<u64, DatabaseError> {
+        let changes = TableChanges {
+            updates,
+            ..Default::default()
+        };
+        self.apply_changes(schema, table_name, changes).await
+    }
+    async fn insert_rows(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        rows: Vec<Map<String, Value>>,
+    ) -> Result<u64, DatabaseError> {
+        let changes = TableChanges {
+            inserts: rows,
+            ..Default::default()
+        };
+        self.apply_changes(schema, table_name, changes).await
+    }
+    async fn delete_rows(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        keys: Vec<Map<String, Value>>,
+    ) -> Result<u64, DatabaseError> {
+        let changes = TableChanges {
+            deletes: keys,
+            ..Default::default()
+        };
+        self.apply_changes(schema, table_name, changes).await
+    }
+    /// Points the connection at another database on the same server and
+    /// returns the database now in use.
+    async fn switch_database(&self, database: String) -> Result<String, DatabaseError>;
+    async fn current_database(&self) -> Result<String, DatabaseError>;
+    /// The schema unqualified table names resolve to, where the database names it.
+    async fn current_schema(&self) -> Result<Option<String>, DatabaseError>;
     async fn get_database_schema(&self) -> Result<HashMap<String, Vec<String>>, DatabaseError>;
-    async fn close(&self);
+    /// Rolls back any open transactions and closes the pool. The pool is
+    /// closed even when a rollback fails; the first failure is returned.
+    async fn close(&self) -> Result<(), DatabaseError>;
+}
+
+/// Equality filters on `fields`, taking each value from the row's column at
+/// the same position in `sources`. None if any of them is NULL or missing.
+fn key_filters(
+    row: &Map<String, Value>,
+    fields: &[String],
+    sources: &[String],
+) -> Option<Vec<Filter>> {
+    if fields.is_empty() || fields.len() != sources.len() {
+        return None;
+    }
+    fields
+        .iter()
+        .zip(sources)
+        .map(|(field, source)| {
+            Some(Filter {
+                field: field.clone(),
+                operator: "=".to_string(),
+                value: dml::value_as_text(row.get(source)?)?,
+            })
+        })
+        .collect()
 }
@@ -1,47 +1,256 @@
-use crate::connection_manager::SavedConnection;
+use crate::connect_options;
+use crate::connection_manager::{self, SavedConnection};
+use crate::credentials::CredentialStore;
 use crate::database_provider::DatabaseProvider;
+use crate::dml::{
+    qualified_name, quote_identifier, render_statement, ChangeKind, RowUpdate, TableChanges,
+};
+use crate::dump::{self, DumpEvent, DumpOptions, DumpSummary, RestoreEvent, RestoreSummary};
+use crate::er_diagram::{self, DiagramFormat, SchemaGraph};
+use crate::export::{ExportEvent, ExportFormat, ExportSource, ExportWriter};
+use crate::import::{
+    self, ColumnMapping, ImportEvent, ImportFormat, ImportPreview, ImportSummary,
+    DEFAULT_IMPORT_BATCH_SIZE, DEFAULT_PREVIEW_ROWS,
+};
 use crate::mysql_provider::MysqlProvider;
 use crate::postgres_provider::PostgresProvider;
+use crate::session::TransactionState;
+use crate::socket_relay::SocketRelay;
+use crate::sql_splitter::{split_statements, Statement};
+use crate::sqlite_provider::SqliteProvider;
 use crate::ssh_tunnel::{SshTunnel, TunnelConfig};
+use futures::{Stream, TryStreamExt};
+use sqlx::mysql::MySqlDatabaseError;
+use sqlx::postgres::{PgDatabaseError, PgErrorPosition};
+use sqlx::{Column, Either, TypeInfo};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, Weak};
-use tauri::State;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, State};
 
 pub struct DbConnection {
     pub provider: Arc<dyn DatabaseProvider + Send + Sync>,
     pub _ssh_tunnel: Option<Arc<SshTunnel>>,
+    pub _socket_relay: Option<SocketRelay>,
 }
 
+/// Identifies a table across connections: connection id, schema and table name.
+pub type TableKey = (String, Option<String>, String);
+
 pub struct AppState {
     pub connections: Mutex<HashMap<String, DbConnection>>,
     pub tunnels: Mutex<HashMap<TunnelConfig, Weak<SshTunnel>>>,
+    /// Grid edits not yet written, per table.
+    pub pending_changes: Mutex<HashMap<TableKey, TableChanges>>,
+}
+
+/// Broad category of a failure, so the UI can tell e.g. a lost connection from a typo.
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseErrorKind {
+    Connection,
+    Syntax,
+    UndefinedObject,
+    Constraint,
+    Permission,
+    Data,
+    Transaction,
+    Timeout,
+    Cancelled,
+    Other,
 }
 
 #[derive(serde::Serialize, Debug)]
 pub struct DatabaseError {
+    pub kind: DatabaseErrorKind,
     pub message: String,
+    /// SQLSTATE for Postgres, error number for MySQL, extended result code for SQLite.
+    pub code: Option<String>,
+    /// 1-based character offset of the failing token within the statement (Postgres only).
+    pub position: Option<usize>,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub column: Option<String>,
+    pub constraint: Option<String>,
+}
+
+impl DatabaseError {
+    pub fn new(message: impl Into<String>) -> Self {
+        DatabaseError::with_kind(DatabaseErrorKind::Other, message)
+    }
+
+    pub fn with_kind(kind: DatabaseErrorKind, message: impl Into<String>) -> Self {
+        DatabaseError {
+            kind,
+            message: message.into(),
+            code: None,
+            position: None,
+            detail: None,
+            hint: None,
+            schema: None,
+            table: None,
+            column: None,
+            constraint: None,
+        }
+    }
+
+    pub fn cancelled() -> Self {
+        DatabaseError::with_kind(DatabaseErrorKind::Cancelled, "Query was cancelled")
+    }
+
+    /// Keeps the driver's error, noting in `detail` what was being done.
+    pub fn with_context(err: sqlx::Error, context: impl Into<String>) -> Self {
+        let mut error = DatabaseError::from(err);
+        let context = context.into();
+        error.detail = Some(match error.detail.take() {
+            Some(detail) => format!("{}\n{}", detail, context),
+            None => context,
+        });
+        error
+    }
+
+    pub fn connection_not_found() -> Self {
+        DatabaseError::with_kind(DatabaseErrorKind::Connection, "Connection not found")
+    }
+
+    fn from_database(err: &dyn sqlx::error::DatabaseError) -> Self {
+        let mut error = DatabaseError::new(err.message());
+        error.code = err.code().map(|code| code.into_owned());
+        error.table = err.table().map(str::to_string);
+        error.constraint = err.constraint().map(str::to_string);
+
+        if let Some(pg) = err.try_downcast_ref::<PgDatabaseError>() {
+            error.kind = kind_from_sqlstate(pg.code(), pg.message());
+            error.position = match pg.position() {
+                Some(PgErrorPosition::Original(position)) => Some(position),
+                _ => None,
+            };
+            error.detail = pg.detail().map(str::to_string);
+            error.hint = pg.hint().map(str::to_string);
+            error.schema = pg.schema().map(str::to_string);
+            error.column = pg.column().map(str::to_string);
+        } else if let Some(mysql) = err.try_downcast_ref::<MySqlDatabaseError>() {
+            error.kind = kind_from_mysql_number(mysql.number());
+            error.code = Some(mysql.number().to_string());
+        } else {
+            error.kind = match err.kind() {
+                sqlx::error::ErrorKind::Other => kind_from_sqlite_message(err.message()),
+                _ => DatabaseErrorKind::Constraint,
+            };
+        }
+
+        error
+    }
+}
+
+fn kind_from_sqlstate(code: &str, message: &str) -> DatabaseErrorKind {
+    match code {
+        "42601" => DatabaseErrorKind::Syntax,
+        "42501" => DatabaseErrorKind::Permission,
+        "42P01" | "42P02" | "42703" | "42704" | "42883" | "3F000" => {
+            DatabaseErrorKind::UndefinedObject
+        }
+        // Duplicate objects are not syntax problems despite sharing class 42
+        "42P04" | "42P06" | "42P07" | "42710" | "42712" | "42723" => DatabaseErrorKind::Other,
+        "57014" if message.contains("timeout") => DatabaseErrorKind::Timeout,
+        "57014" => DatabaseErrorKind::Cancelled,
+        "55P03" => DatabaseErrorKind::Timeout,
+        _ => match code.get(..2).unwrap_or_default() {
+            "08" | "28" | "3D" | "53" | "57" => DatabaseErrorKind::Connection,
+            "22" => DatabaseErrorKind::Data,
+            "23" => DatabaseErrorKind::Constraint,
+            "25" | "40" => DatabaseErrorKind::Transaction,
+            "42" => DatabaseErrorKind::Syntax,
+            _ => DatabaseErrorKind::Other,
+        },
+    }
+}
+
+fn kind_from_mysql_number(number: u16) -> DatabaseErrorKind {
+    match number {
+        1064 | 1149 => DatabaseErrorKind::Syntax,
+        1049 | 1051 | 1054 | 1091 | 1146 | 1305 => DatabaseErrorKind::UndefinedObject,
+        1022 | 1048 | 1062 | 1216 | 1217 | 1451 | 1452 | 1557 | 1586 | 3819 => {
+            DatabaseErrorKind::Constraint
+        }
+        1044 | 1142 | 1143 | 1227 => DatabaseErrorKind::Permission,
+        1045 | 1040 | 1043 | 1053 | 1129 | 1130 | 2002 | 2003 | 2006 | 2013 => {
+            DatabaseErrorKind::Connection
+        }
+        1264 | 1265 | 1292 | 1366 | 1406 => DatabaseErrorKind::Data,
+        1205 | 3024 => DatabaseErrorKind::Timeout,
+        1213 | 1568 | 1792 => DatabaseErrorKind::Transaction,
+        1317 => DatabaseErrorKind::Cancelled,
+        _ => DatabaseErrorKind::Other,
+    }
+}
+
+fn kind_from_sqlite_message(message: &str) -> DatabaseErrorKind {
+    if message.contains("syntax error") || message.contains("incomplete input") {
+        DatabaseErrorKind::Syntax
+    } else if message.starts_with("no such") {
+        DatabaseErrorKind::UndefinedObject
+    } else if message.contains("database is locked") {
+        DatabaseErrorKind::Timeout
+    } else if message.contains("readonly") || message.contains("unable to open") {
+        DatabaseErrorKind::Connection
+    } else {
+        DatabaseErrorKind::Other
+    }
 }
 
 impl From<sqlx::Error> for DatabaseError {
     fn from(err: sqlx::Error) -> Self {
-        DatabaseError {
-            message: err.to_string(),
+        match &err {
+            sqlx::Error::Database(db_err) => DatabaseError::from_database(db_err.as_ref()),
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::Configuration(_)
+            | sqlx::Error::Protocol(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => {
+                DatabaseError::with_kind(DatabaseErrorKind::Connection, err.to_string())
+            }
+            _ => DatabaseError::new(err.to_string()),
         }
     }
 }
 
 #[tauri::command]
 pub async fn connect_db(
+    app_handle: AppHandle,
     connection_id: String,
     connection_config: SavedConnection,
     state: State<'_, AppState>,
+    credentials: State<'_, CredentialStore>,
 ) -> Result<(), DatabaseError> {
+    // Passwords the frontend doesn't pass come from the credential store,
+    // which isn't needed, and may be locked, when they are all given
+    let mut connection_config = connection_config;
+    let missing = |secret: &Option<String>| secret.as_deref().unwrap_or("").is_empty();
+    let needs_ssh_password = connection_config.ssh_enabled
+        && missing(&connection_config.ssh_key_path)
+        && missing(&connection_config.ssh_password);
+    if missing(&connection_config.password) || needs_ssh_password {
+        credentials
+            .fill(
+                &connection_manager::data_dir(&app_handle),
+                &mut connection_config,
+            )
+            .map_err(|e| DatabaseError::with_kind(DatabaseErrorKind::Connection, e))?;
+    }
+
     // 1. Handle SSH Tunnel if enabled
     let mut db_host = connection_config.host.clone();
     let mut db_port = connection_config.port;
     let mut ssh_tunnel = None;
 
-    if connection_config.ssh_enabled {
+    // SQLite databases are local files, so there is nothing to tunnel to
+    if connection_config.ssh_enabled && connection_config.db_type != "sqlite" {
         if let Some(ssh_host) = &connection_config.ssh_host {
             let config = TunnelConfig {
                 ssh_host: ssh_host.clone(),
@@ -75,8 +284,8 @@ pub async fn connect_db(
 
             if ssh_tunnel.is_none() {
                 eprintln!("Starting new SSH tunnel...");
-                let (tunnel, actual_local_port) =
-                    SshTunnel::start(config.clone()).map_err(|e| DatabaseError { message: e })?;
+                let (tunnel, actual_local_port) = SshTunnel::start(config.clone())
+                    .map_err(|e| DatabaseError::with_kind(DatabaseErrorKind::Connection, e))?;
 
                 let tunnel_arc = Arc::new(tunnel);
                 tunnels.insert(config, Arc::downgrade(&tunnel_arc));
@@ -88,31 +297,54 @@ pub async fn connect_db(
         }
     }
 
-    // 2. Construct Connection String and Connect
-    let password = connection_config.password.as_deref().unwrap_or("");
+    // verify-full checks the certificate against the host the driver is
+    // given, so reach another address, such as the tunnel, through a relay
+    let socket_relay = match connect_options::tls_server_name(&connection_config, &db_host) {
+        Some(_) if connection_config.db_type != "sqlite" => Some(
+            SocketRelay::start(&db_host, db_port)
+                .map_err(|e| DatabaseError::with_kind(DatabaseErrorKind::Connection, e))?,
+        ),
+        _ => None,
+    };
 
+    // 2. Build Connect Options and Connect
     let provider: Arc<dyn DatabaseProvider + Send + Sync> = match connection_config.db_type.as_str()
     {
+        "sqlite" => {
+            let file_path = connection_config
+                .file_path
+                .as_deref()
+                .filter(|p| !p.is_empty())
+                .ok_or(DatabaseError::new("SQLite connection requires a file path"))?;
+            Arc::new(SqliteProvider::new(file_path).await?)
+        }
         "mysql" => {
-            let connection_string = format!(
-                "mysql://{}:{}@{}:{}/{}",
-                connection_config.username, password, db_host, db_port, connection_config.database
-            );
-            Arc::new(MysqlProvider::new(&connection_string).await?)
+            let (options, session_variables) = connect_options::mysql(
+                &connection_config,
+                &db_host,
+                db_port,
+                socket_relay.as_ref().map(SocketRelay::path),
+            )
+            .map_err(|e| DatabaseError::with_kind(DatabaseErrorKind::Connection, e))?;
+            Arc::new(MysqlProvider::new(options, session_variables).await?)
         }
         _ => {
             // Default to Postgres
-            let connection_string = format!(
-                "postgres://{}:{}@{}:{}/{}",
-                connection_config.username, password, db_host, db_port, connection_config.database
-            );
-            Arc::new(PostgresProvider::new(&connection_string).await?)
+            let options = connect_options::postgres(
+                &connection_config,
+                &db_host,
+                db_port,
+                socket_relay.as_ref().map(SocketRelay::dir),
+            )
+            .map_err(|e| DatabaseError::with_kind(DatabaseErrorKind::Connection, e))?;
+            Arc::new(PostgresProvider::new(options).await?)
         }
     };
 
     let connection = DbConnection {
         provider,
         _ssh_tunnel: ssh_tunnel,
+        _socket_relay: socket_relay,
     };
 
     state
@@ -134,8 +366,15 @@ pub async fn disconnect_db(
         connections.remove(&connection_id)
     };
 
+    state
+        .pending_changes
+        .lock()
+        .unwrap()
+        .retain(|(id, _, _), _| *id != connection_id);
+
+    let mut closed = Ok(());
     if let Some(conn) = conn {
-        conn.provider.close().await;
+        closed = conn.provider.close().await;
 
         // Clean up dead tunnel references
         // When the connection is dropped, the Arc<SshTunnel> is dropped.
@@ -144,7 +383,48 @@ pub async fn disconnect_db(
         let mut tunnels = state.tunnels.lock().unwrap();
         tunnels.retain(|_, weak_tunnel| weak_tunnel.upgrade().is_some());
     }
-    Ok(())
+    closed
+}
+
+/// Moves the connection to another database on the same server, keeping its
+/// SSH tunnel, and returns the database now in use.
+#[tauri::command]
+pub async fn switch_database(
+    connection_id: String,
+    database: String,
+    state: State<'_, AppState>,
+) -> Result<String, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    let active = provider.switch_database(database).await?;
+
+    // Staged edits refer to tables of the previous database
+    state
+        .pending_changes
+        .lock()
+        .unwrap()
+        .retain(|(id, _, _), _| *id != connection_id);
+    Ok(active)
+}
+
+#[tauri::command]
+pub async fn get_current_database(
+    connection_id: String,
+    state: State<'_, AppState>,
+) -> Result<String, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.current_database().await
 }
 
 #[tauri::command]
@@ -154,52 +434,324 @@ pub async fn list_databases(
 ) -> Result<Vec<String>, DatabaseError> {
     let provider = {
         let connections = state.connections.lock().unwrap();
-        let conn = connections.get(&connection_id).ok_or(DatabaseError {
-            message: "Connection not found".to_string(),
-        })?;
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
         conn.provider.clone()
     };
     provider.list_databases().await
 }
 
+#[tauri::command]
+pub async fn list_schemas(
+    connection_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.list_schemas().await
+}
+
 #[tauri::command]
 pub async fn list_tables(
     connection_id: String,
+    schema: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<String>, DatabaseError> {
     let provider = {
         let connections = state.connections.lock().unwrap();
-        let conn = connections.get(&connection_id).ok_or(DatabaseError {
-            message: "Connection not found".to_string(),
-        })?;
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
         conn.provider.clone()
     };
-    provider.list_tables().await
+    provider.list_tables(schema).await
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectKind {
+    Table,
+    View,
+    MaterializedView,
+    Sequence,
+    Function,
+    Procedure,
+    Trigger,
+    Type,
+    Extension,
+}
+
+/// An entry in the object tree.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct DatabaseObject {
+    pub name: String,
+    pub kind: ObjectKind,
+    /// Table a trigger is defined on.
+    pub table_name: Option<String>,
+    /// Argument list of a function or procedure, to tell overloads apart (Postgres only).
+    pub arguments: Option<String>,
+}
+
+#[tauri::command]
+pub async fn list_objects(
+    connection_id: String,
+    schema: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DatabaseObject>, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.list_objects(schema).await
+}
+
+/// The statements that would recreate an object listed by `list_objects`.
+#[tauri::command]
+pub async fn get_object_ddl(
+    connection_id: String,
+    schema: Option<String>,
+    object: DatabaseObject,
+    state: State<'_, AppState>,
+) -> Result<String, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.get_object_ddl(schema, object).await
+}
+
+#[derive(serde::Serialize, Default)]
 pub struct QueryResult {
     pub columns: Vec<String>,
     pub rows: Vec<Vec<serde_json::Value>>,
     pub total_rows: Option<i64>,
+    /// Set for statements that do not return rows (INSERT, UPDATE, DDL, ...).
+    pub rows_affected: Option<u64>,
+    /// Auto-increment id generated by the statement (MySQL only).
+    pub last_insert_id: Option<u64>,
+    /// Notices and warnings the server raised while running the statement.
+    pub notices: Vec<String>,
+    pub execution_time_ms: Option<u64>,
+}
+
+/// Events pushed to the frontend while a streamed query is running.
+#[derive(serde::Serialize, Clone)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum QueryStreamEvent {
+    /// Column names, with the driver's type name for each column.
+    Columns {
+        columns: Vec<String>,
+        types: Vec<String>,
+    },
+    Rows {
+        rows: Vec<Vec<serde_json::Value>>,
+    },
+    Finished {
+        row_count: usize,
+        truncated: bool,
+    },
 }
 
-#[derive(serde::Deserialize, Debug)]
+pub const DEFAULT_STREAM_BATCH_SIZE: usize = 500;
+pub const DEFAULT_STREAM_MAX_ROWS: usize = 100_000;
+
+/// Drains a row stream in batches of `batch_size`, handing each batch to `on_event`.
+/// Stops early once `max_rows` rows have been sent or `on_event` returns false.
+/// `columns` come from the prepared statement, so a query without rows still
+/// reports them; when the statement described none they are taken from the
+/// first row.
+pub async fn stream_rows<R, S, C>(
+    mut rows: S,
+    columns: &[C],
+    to_values: fn(R) -> Vec<serde_json::Value>,
+    batch_size: usize,
+    max_rows: usize,
+    on_event: &(dyn Fn(QueryStreamEvent) -> bool + Send + Sync),
+) -> Result<(), DatabaseError>
+where
+    R: sqlx::Row,
+    S: Stream<Item = Result<R, sqlx::Error>> + Unpin + Send,
+    C: Column,
+{
+    let batch_size = batch_size.max(1);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut row_count = 0;
+    let mut truncated = false;
+
+    let mut columns_sent = !columns.is_empty();
+    if columns_sent && !on_event(columns_event(columns)) {
+        return Ok(());
+    }
+
+    while let Some(row) = rows.try_next().await.map_err(DatabaseError::from)? {
+        if row_count >= max_rows {
+            truncated = true;
+            break;
+        }
+
+        if !columns_sent {
+            columns_sent = true;
+            if !on_event(columns_event(row.columns())) {
+                return Ok(());
+            }
+        }
+
+        batch.push(to_values(row));
+        row_count += 1;
+
+        if batch.len() >= batch_size {
+            let rows = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+            if !on_event(QueryStreamEvent::Rows { rows }) {
+                return Ok(());
+            }
+        }
+    }
+
+    if !batch.is_empty() && !on_event(QueryStreamEvent::Rows { rows: batch }) {
+        return Ok(());
+    }
+
+    on_event(QueryStreamEvent::Finished {
+        row_count,
+        truncated,
+    });
+    Ok(())
+}
+
+fn columns_event<C: Column>(columns: &[C]) -> QueryStreamEvent {
+    let (columns, types) = columns
+        .iter()
+        .map(|col| (col.name().to_string(), col.type_info().name().to_string()))
+        .unzip();
+    QueryStreamEvent::Columns { columns, types }
+}
+
+/// Collects the rows produced by a `fetch_many` stream. `columns` may be left
+/// empty to take them from the first row. Statements that return no columns
+/// report their affected-row count and, where the driver has one, the last insert id.
+pub async fn collect_results<Q, R, S>(
+    mut results: S,
+    mut columns: Vec<String>,
+    to_values: fn(R) -> Vec<serde_json::Value>,
+    summarize: fn(&Q) -> (u64, Option<u64>),
+) -> Result<QueryResult, DatabaseError>
+where
+    R: sqlx::Row,
+    S: Stream<Item = Result<Either<Q, R>, sqlx::Error>> + Unpin + Send,
+{
+    let mut rows = Vec::new();
+    let mut rows_affected = 0;
+    let mut last_insert_id = None;
+
+    while let Some(step) = results.try_next().await.map_err(DatabaseError::from)? {
+        match step {
+            Either::Left(done) => {
+                let (affected, insert_id) = summarize(&done);
+                rows_affected += affected;
+                last_insert_id = insert_id.filter(|id| *id > 0).or(last_insert_id);
+            }
+            Either::Right(row) => {
+                if columns.is_empty() {
+                    columns = row
+                        .columns()
+                        .iter()
+                        .map(|col| col.name().to_string())
+                        .collect();
+                }
+                rows.push(to_values(row));
+            }
+        }
+    }
+
+    let returns_rows = !columns.is_empty();
+    Ok(QueryResult {
+        columns,
+        rows,
+        rows_affected: (!returns_rows).then_some(rows_affected),
+        last_insert_id,
+        ..Default::default()
+    })
+}
+
+/// Outcome of one statement of a script run through `execute_script`.
+#[derive(serde::Serialize)]
+pub struct StatementResult {
+    pub sql: String,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    pub result: Option<QueryResult>,
+    pub duration_ms: u64,
+    pub error: Option<DatabaseError>,
+    /// Character offset of the error within the whole script, when the server reports one.
+    pub error_offset: Option<usize>,
+}
+
+impl StatementResult {
+    pub fn new(
+        statement: Statement,
+        outcome: Result<QueryResult, DatabaseError>,
+        duration: std::time::Duration,
+    ) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(err) => (None, Some(err)),
+        };
+        let error_offset = error
+            .as_ref()
+            .and_then(|err| err.position)
+            .map(|position| statement.offset + position.saturating_sub(1));
+        StatementResult {
+            sql: statement.sql,
+            offset: statement.offset,
+            line: statement.line,
+            column: statement.column,
+            result,
+            duration_ms: duration.as_millis() as u64,
+            error,
+            error_offset,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct Filter {
     pub field: String,
     pub operator: String,
     pub value: String,
 }
 
-#[derive(serde::Deserialize, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct Sort {
     pub field: String,
     pub order: String,
 }
 
+/// A filtered, sorted table as `FROM ... WHERE ...` plus `ORDER BY ...`, with
+/// the filter values to bind in placeholder order.
+pub struct TableQuery {
+    pub from: String,
+    pub order_by: String,
+    pub params: Vec<String>,
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn get_table_data(
     connection_id: String,
+    schema: Option<String>,
     table_name: String,
     limit: i64,
     offset: i64,
@@ -209,13 +761,13 @@ pub async fn get_table_data(
 ) -> Result<QueryResult, DatabaseError> {
     let provider = {
         let connections = state.connections.lock().unwrap();
-        let conn = connections.get(&connection_id).ok_or(DatabaseError {
-            message: "Connection not found".to_string(),
-        })?;
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
         conn.provider.clone()
     };
     provider
-        .get_table_data(table_name, limit, offset, filters, sorts)
+        .get_table_data(schema, table_name, limit, offset, filters, sorts)
         .await
 }
 
@@ -232,17 +784,18 @@ pub struct ColumnDefinition {
 #[tauri::command]
 pub async fn get_table_structure(
     connection_id: String,
+    schema: Option<String>,
     table_name: String,
     state: State<'_, AppState>,
 ) -> Result<Vec<ColumnDefinition>, DatabaseError> {
     let provider = {
         let connections = state.connections.lock().unwrap();
-        let conn = connections.get(&connection_id).ok_or(DatabaseError {
-            message: "Connection not found".to_string(),
-        })?;
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
         conn.provider.clone()
     };
-    provider.get_table_structure(table_name).await
+    provider.get_table_structure(schema, table_name).await
 }
 
 #[derive(serde::Serialize)]
@@ -251,7 +804,9 @@ pub struct IndexDefinition {
     pub index_algorithm: String,
     pub is_unique: bool,
     pub is_primary: bool,
-    pub column_names: String,
+    /// Indexed columns in key order. Parts over an expression hold its text
+    /// instead of a column name.
+    pub column_names: Vec<String>,
     pub condition: Option<String>,
     pub comment: Option<String>,
 }
@@ -259,33 +814,790 @@ pub struct IndexDefinition {
 #[tauri::command]
 pub async fn get_table_indexes(
     connection_id: String,
+    schema: Option<String>,
     table_name: String,
     state: State<'_, AppState>,
 ) -> Result<Vec<IndexDefinition>, DatabaseError> {
     let provider = {
         let connections = state.connections.lock().unwrap();
-        let conn = connections.get(&connection_id).ok_or(DatabaseError {
-            message: "Connection not found".to_string(),
-        })?;
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.get_table_indexes(schema, table_name).await
+}
+
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstraintKind {
+    PrimaryKey,
+    Unique,
+    ForeignKey,
+    Check,
+    Exclusion,
+}
+
+#[derive(serde::Serialize)]
+pub struct ConstraintDefinition {
+    pub constraint_name: String,
+    pub kind: ConstraintKind,
+    /// In key order, which for foreign keys pairs up with `referenced_columns`.
+    pub column_names: Vec<String>,
+    pub referenced_schema: Option<String>,
+    pub referenced_table: Option<String>,
+    pub referenced_columns: Vec<String>,
+    /// Foreign key actions, e.g. `CASCADE` or `NO ACTION`.
+    pub on_update: Option<String>,
+    pub on_delete: Option<String>,
+    pub is_deferrable: bool,
+    pub initially_deferred: bool,
+    /// Expression of a check constraint, or the whole `EXCLUDE ...` clause
+    /// of an exclusion constraint.
+    pub check_clause: Option<String>,
+}
+
+#[tauri::command]
+pub async fn get_table_constraints(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<ConstraintDefinition>, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.get_table_constraints(schema, table_name).await
+}
+
+/// A foreign key on another table that references this one.
+#[derive(serde::Serialize)]
+pub struct IncomingForeignKey {
+    pub constraint_name: String,
+    pub schema: Option<String>,
+    pub table_name: String,
+    /// Columns of the referencing table, paired with `referenced_columns`.
+    pub column_names: Vec<String>,
+    pub referenced_columns: Vec<String>,
+    pub on_update: Option<String>,
+    pub on_delete: Option<String>,
+}
+
+#[tauri::command]
+pub async fn get_incoming_foreign_keys(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<IncomingForeignKey>, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.get_incoming_foreign_keys(schema, table_name).await
+}
+
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationDirection {
+    /// The row this row's foreign key points at.
+    Parent,
+    /// Rows whose foreign key points at this row.
+    Child,
+}
+
+/// Where to jump from a row: the `get_table_data` arguments that select the
+/// related rows.
+#[derive(serde::Serialize)]
+pub struct RelatedRows {
+    pub direction: RelationDirection,
+    pub constraint_name: String,
+    pub schema: Option<String>,
+    pub table_name: String,
+    pub filters: Vec<Filter>,
+}
+
+#[tauri::command]
+pub async fn get_related_rows(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    row: serde_json::Map<String, serde_json::Value>,
+    state: State<'_, AppState>,
+) -> Result<Vec<RelatedRows>, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.get_related_rows(schema, table_name, &row).await
+}
+
+/// Tables, columns and foreign keys of a schema, for drawing an ER diagram.
+#[tauri::command]
+pub async fn get_schema_graph(
+    connection_id: String,
+    schema: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SchemaGraph, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    er_diagram::build_graph(provider.as_ref(), schema).await
+}
+
+/// The schema graph as Mermaid, Graphviz DOT or PlantUML source.
+#[tauri::command]
+pub async fn export_schema_diagram(
+    connection_id: String,
+    schema: Option<String>,
+    format: DiagramFormat,
+    state: State<'_, AppState>,
+) -> Result<String, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    let graph = er_diagram::build_graph(provider.as_ref(), schema).await?;
+    Ok(graph.render(format))
+}
+
+/// Streams a table or query result into the file at `path`. A partially written
+/// file is removed when the export fails or is cancelled.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_data(
+    connection_id: String,
+    source: ExportSource,
+    format: ExportFormat,
+    path: String,
+    query_id: Option<String>,
+    on_progress: Channel<ExportEvent>,
+    state: State<'_, AppState>,
+) -> Result<usize, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    let dialect = provider.dialect();
+    let insert_target = match (&format, &source) {
+        (
+            ExportFormat::SqlInsert {
+                table_name: Some(name),
+                ..
+            },
+            _,
+        ) => quote_identifier(dialect, name),
+        (
+            ExportFormat::SqlInsert { .. },
+            ExportSource::Table {
+                schema, table_name, ..
+            },
+        ) => qualified_name(dialect, schema.as_deref(), table_name),
+        (ExportFormat::SqlInsert { .. }, ExportSource::Query { .. }) => {
+            return Err(DatabaseError::new(
+                "A table name is required to export a query as INSERT statements",
+            ))
+        }
+        _ => String::new(),
+    };
+    let write_error =
+        |e: std::io::Error| DatabaseError::new(format!("Failed to write {}: {}", path, e));
+
+    let writer =
+        ExportWriter::create(&path, format, dialect, insert_target).map_err(write_error)?;
+    let writer = Mutex::new(writer);
+    let failure = Mutex::new(None);
+    // The frontend dropping the progress channel stops the export
+    let cancelled = AtomicBool::new(false);
+    let sink = |event: QueryStreamEvent| {
+        let is_rows = matches!(event, QueryStreamEvent::Rows { .. });
+        let mut writer = writer.lock().unwrap();
+        match writer.write_event(event) {
+            Ok(()) if is_rows => {
+                let sent = on_progress.send(ExportEvent::Progress {
+                    rows_written: writer.rows_written(),
+                });
+                if sent.is_err() {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+                sent.is_ok()
+            }
+            Ok(()) => true,
+            Err(e) => {
+                *failure.lock().unwrap() = Some(e);
+                false
+            }
+        }
+    };
+
+    let streamed = match source {
+        ExportSource::Table {
+            schema,
+            table_name,
+            filters,
+            sorts,
+        } => {
+            provider
+                .stream_table_data(schema, table_name, filters, sorts, query_id, &sink)
+                .await
+        }
+        ExportSource::Query { query, session_id } => {
+            provider
+                .execute_query_stream(
+                    query,
+                    query_id,
+                    session_id,
+                    DEFAULT_STREAM_BATCH_SIZE,
+                    usize::MAX,
+                    &sink,
+                )
+                .await
+        }
+    };
+    let writer = writer.into_inner().unwrap();
+    let result = match (streamed, failure.into_inner().unwrap()) {
+        (Err(e), _) => Err(e),
+        (Ok(()), Some(e)) => Err(write_error(e)),
+        (Ok(()), None) if cancelled.load(Ordering::Relaxed) => Err(DatabaseError::cancelled()),
+        (Ok(()), None) => writer.finish().map_err(write_error),
+    };
+
+    match result {
+        Ok(rows_written) => {
+            let _ = on_progress.send(ExportEvent::Finished { rows_written });
+            Ok(rows_written)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&path);
+            Err(e)
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn preview_import(
+    path: String,
+    format: ImportFormat,
+    limit: Option<usize>,
+) -> Result<ImportPreview, DatabaseError> {
+    import::preview(&path, &format, limit.unwrap_or(DEFAULT_PREVIEW_ROWS))
+        .map_err(DatabaseError::new)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn import_data(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    path: String,
+    format: ImportFormat,
+    mapping: Vec<ColumnMapping>,
+    batch_size: Option<usize>,
+    on_progress: Channel<ImportEvent>,
+    state: State<'_, AppState>,
+) -> Result<ImportSummary, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    // A failed send means the frontend dropped the channel, so stop reading
+    let sink = move |event: ImportEvent| on_progress.send(event).is_ok();
+    import::import_file(
+        provider.as_ref(),
+        schema,
+        table_name,
+        &path,
+        &format,
+        &mapping,
+        batch_size.unwrap_or(DEFAULT_IMPORT_BATCH_SIZE),
+        &sink,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn dump_database(
+    connection_id: String,
+    schema: Option<String>,
+    path: String,
+    options: DumpOptions,
+    query_id: Option<String>,
+    on_progress: Channel<DumpEvent>,
+    state: State<'_, AppState>,
+) -> Result<DumpSummary, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
         conn.provider.clone()
     };
-    provider.get_table_indexes(table_name).await
+    let sink = move |event: DumpEvent| on_progress.send(event).is_ok();
+    let result = dump::dump(provider.as_ref(), schema, &path, &options, query_id, &sink).await;
+    if result.is_err() {
+        let _ = std::fs::remove_file(&path);
+    }
+    result
+}
+
+#[tauri::command]
+pub async fn restore_database(
+    connection_id: String,
+    path: String,
+    query_id: Option<String>,
+    on_progress: Channel<RestoreEvent>,
+    state: State<'_, AppState>,
+) -> Result<RestoreSummary, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    let sink = move |event: RestoreEvent| on_progress.send(event).is_ok();
+    dump::restore(provider.as_ref(), &path, query_id, &sink).await
+}
+
+#[tauri::command]
+pub async fn update_rows(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    updates: Vec<RowUpdate>,
+    state: State<'_, AppState>,
+) -> Result<u64, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.update_rows(schema, table_name, updates).await
+}
+
+#[tauri::command]
+pub async fn insert_rows(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    rows: Vec<serde_json::Map<String, serde_json::Value>>,
+    state: State<'_, AppState>,
+) -> Result<u64, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.insert_rows(schema, table_name, rows).await
+}
+
+#[tauri::command]
+pub async fn delete_rows(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    keys: Vec<serde_json::Map<String, serde_json::Value>>,
+    state: State<'_, AppState>,
+) -> Result<u64, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.delete_rows(schema, table_name, keys).await
+}
+
+#[tauri::command]
+pub async fn stage_row_update(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    update: RowUpdate,
+    state: State<'_, AppState>,
+) -> Result<TableChanges, DatabaseError> {
+    let mut pending = state.pending_changes.lock().unwrap();
+    let changes = pending
+        .entry((connection_id, schema, table_name))
+        .or_default();
+    changes.stage_update(update).map_err(DatabaseError::new)?;
+    Ok(changes.clone())
+}
+
+#[tauri::command]
+pub async fn stage_row_insert(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    row: serde_json::Map<String, serde_json::Value>,
+    state: State<'_, AppState>,
+) -> Result<TableChanges, DatabaseError> {
+    let mut pending = state.pending_changes.lock().unwrap();
+    let changes = pending
+        .entry((connection_id, schema, table_name))
+        .or_default();
+    changes.stage_insert(row);
+    Ok(changes.clone())
+}
+
+#[tauri::command]
+pub async fn stage_row_delete(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    key: serde_json::Map<String, serde_json::Value>,
+    state: State<'_, AppState>,
+) -> Result<TableChanges, DatabaseError> {
+    let mut pending = state.pending_changes.lock().unwrap();
+    let changes = pending
+        .entry((connection_id, schema, table_name))
+        .or_default();
+    changes.stage_delete(key);
+    Ok(changes.clone())
+}
+
+#[tauri::command]
+pub async fn unstage_change(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    kind: ChangeKind,
+    index: usize,
+    state: State<'_, AppState>,
+) -> Result<TableChanges, DatabaseError> {
+    let mut pending = state.pending_changes.lock().unwrap();
+    let changes = pending
+        .entry((connection_id, schema, table_name))
+        .or_default();
+    changes.unstage(kind, index).map_err(DatabaseError::new)?;
+    Ok(changes.clone())
+}
+
+#[tauri::command]
+pub async fn get_pending_changes(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    state: State<'_, AppState>,
+) -> Result<TableChanges, DatabaseError> {
+    let pending = state.pending_changes.lock().unwrap();
+    Ok(pending
+        .get(&(connection_id, schema, table_name))
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Renders the pending changes as the statements `apply_pending_changes` would run.
+#[tauri::command]
+pub async fn preview_pending_changes(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    let changes = {
+        let pending = state.pending_changes.lock().unwrap();
+        pending
+            .get(&(connection_id, schema.clone(), table_name.clone()))
+            .cloned()
+            .unwrap_or_default()
+    };
+    let statements = provider.plan_changes(schema, table_name, &changes).await?;
+    Ok(statements
+        .iter()
+        .map(|statement| render_statement(provider.dialect(), statement))
+        .collect())
+}
+
+/// Writes all pending changes of the table in one transaction. They are taken
+/// out while being written, so changes staged meanwhile are kept for later,
+/// and put back on failure for another attempt.
+#[tauri::command]
+pub async fn apply_pending_changes(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    state: State<'_, AppState>,
+) -> Result<u64, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    let key = (connection_id, schema, table_name);
+    let changes = state.pending_changes.lock().unwrap().remove(&key);
+    let Some(changes) = changes.filter(|c| !c.is_empty()) else {
+        return Ok(0);
+    };
+
+    let result = provider
+        .apply_changes(key.1.clone(), key.2.clone(), changes.clone())
+        .await;
+    // Nothing to keep them for once the connection has been closed
+    let connected = state.connections.lock().unwrap().contains_key(&key.0);
+    if result.is_err() && connected {
+        let mut pending = state.pending_changes.lock().unwrap();
+        let mut restored = changes;
+        if let Some(later) = pending.remove(&key) {
+            restored.merge(later);
+        }
+        pending.insert(key, restored);
+    }
+    result
+}
+
+#[tauri::command]
+pub async fn discard_pending_changes(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    state: State<'_, AppState>,
+) -> Result<(), DatabaseError> {
+    state
+        .pending_changes
+        .lock()
+        .unwrap()
+        .remove(&(connection_id, schema, table_name));
+    Ok(())
 }
 
 #[tauri::command]
 pub async fn execute_query(
     connection_id: String,
     query: String,
+    query_id: Option<String>,
+    session_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<QueryResult, DatabaseError> {
     let provider = {
         let connections = state.connections.lock().unwrap();
-        let conn = connections.get(&connection_id).ok_or(DatabaseError {
-            message: "Connection not found".to_string(),
-        })?;
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.execute_query(query, query_id, session_id).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_query_stream(
+    connection_id: String,
+    query: String,
+    query_id: Option<String>,
+    session_id: Option<String>,
+    batch_size: Option<usize>,
+    max_rows: Option<usize>,
+    on_event: Channel<QueryStreamEvent>,
+    state: State<'_, AppState>,
+) -> Result<(), DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    // A failed send means the frontend dropped the channel, so stop fetching
+    let sink = move |event: QueryStreamEvent| on_event.send(event).is_ok();
+    provider
+        .execute_query_stream(
+            query,
+            query_id,
+            session_id,
+            batch_size.unwrap_or(DEFAULT_STREAM_BATCH_SIZE),
+            max_rows.unwrap_or(DEFAULT_STREAM_MAX_ROWS),
+            &sink,
+        )
+        .await
+}
+
+#[tauri::command]
+pub async fn execute_script(
+    connection_id: String,
+    script: String,
+    stop_on_error: bool,
+    query_id: Option<String>,
+    session_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<StatementResult>, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    let statements = split_statements(&script, provider.dialect());
+    provider
+        .execute_script(statements, stop_on_error, query_id, session_id)
+        .await
+}
+
+#[tauri::command]
+pub async fn cancel_query(
+    connection_id: String,
+    query_id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.cancel_query(query_id).await
+}
+
+#[tauri::command]
+pub async fn begin_transaction(
+    connection_id: String,
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.begin_transaction(session_id).await
+}
+
+#[tauri::command]
+pub async fn commit_transaction(
+    connection_id: String,
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.commit_transaction(session_id).await
+}
+
+#[tauri::command]
+pub async fn rollback_transaction(
+    connection_id: String,
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.rollback_transaction(session_id).await
+}
+
+#[tauri::command]
+pub async fn create_savepoint(
+    connection_id: String,
+    session_id: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.create_savepoint(session_id, name).await
+}
+
+#[tauri::command]
+pub async fn rollback_to_savepoint(
+    connection_id: String,
+    session_id: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
         conn.provider.clone()
     };
-    provider.execute_query(query).await
+    provider.rollback_to_savepoint(session_id, name).await
+}
+
+#[tauri::command]
+pub async fn release_savepoint(
+    connection_id: String,
+    session_id: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, DatabaseError> {
+    let provider = {
+        let connections = state.connections.lock().unwrap();
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
+        conn.provider.clone()
+    };
+    provider.release_savepoint(session_id, name).await
+}
+
+#[tauri::command]
+pub async fn get_transaction_state(
+    connection_id: String,
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, DatabaseError> {
+    let connections = state.connections.lock().unwrap();
+    let conn = connections
+        .get(&connection_id)
+        .ok_or_else(DatabaseError::connection_not_found)?;
+    Ok(conn.provider.transaction_state(&session_id))
 }
 
 #[tauri::command]
@@ -295,9 +1607,9 @@ pub async fn get_database_schema(
 ) -> Result<std::collections::HashMap<String, Vec<String>>, DatabaseError> {
     let provider = {
         let connections = state.connections.lock().unwrap();
-        let conn = connections.get(&connection_id).ok_or(DatabaseError {
-            message: "Connection not found".to_string(),
-        })?;
+        let conn = connections
+            .get(&connection_id)
+            .ok_or_else(DatabaseError::connection_not_found)?;
         conn.provider.clone()
     };
     provider.get_database_schema().await
@@ -0,0 +1,695 @@
+use crate::db::{ColumnDefinition, DatabaseError, IndexDefinition};
+use crate::sql_splitter::SqlDialect;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// An edit to one existing row. `key` holds the original values identifying
+/// the row (it may be the whole original row; only a primary or unique key's
+/// columns are used) and `values` the columns to change.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct RowUpdate {
+    pub key: Map<String, Value>,
+    pub values: Map<String, Value>,
+}
+
+/// Row edits against a single table, applied together in one transaction.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+pub struct TableChanges {
+    #[serde(default)]
+    pub updates: Vec<RowUpdate>,
+    #[serde(default)]
+    pub inserts: Vec<Map<String, Value>>,
+    #[serde(default)]
+    pub deletes: Vec<Map<String, Value>>,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Update,
+    Insert,
+    Delete,
+}
+
+impl TableChanges {
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty() && self.inserts.is_empty() && self.deletes.is_empty()
+    }
+
+    /// Records an edit to a loaded row, merging it into an earlier edit of the same row.
+    pub fn stage_update(&mut self, update: RowUpdate) -> Result<(), String> {
+        if self.deletes.contains(&update.key) {
+            return Err("The row is already marked for deletion".to_string());
+        }
+        match self.updates.iter_mut().find(|u| u.key == update.key) {
+            Some(existing) => existing.values.extend(update.values),
+            None => self.updates.push(update),
+        }
+        Ok(())
+    }
+
+    pub fn stage_insert(&mut self, row: Map<String, Value>) {
+        self.inserts.push(row);
+    }
+
+    /// Marks a loaded row for deletion, dropping any pending edit of it.
+    pub fn stage_delete(&mut self, key: Map<String, Value>) {
+        self.updates.retain(|u| u.key != key);
+        if !self.deletes.contains(&key) {
+            self.deletes.push(key);
+        }
+    }
+
+    /// Stages changes made after these on top of them. As with staging one by
+    /// one, an edit of a row these already delete is dropped.
+    pub fn merge(&mut self, later: TableChanges) {
+        for key in later.deletes {
+            self.stage_delete(key);
+        }
+        for update in later.updates {
+            let _ = self.stage_update(update);
+        }
+        self.inserts.extend(later.inserts);
+    }
+
+    /// Drops a single pending change, by its position in its list.
+    pub fn unstage(&mut self, kind: ChangeKind, index: usize) -> Result<(), String> {
+        let len = match kind {
+            ChangeKind::Update => self.updates.len(),
+            ChangeKind::Insert => self.inserts.len(),
+            ChangeKind::Delete => self.deletes.len(),
+        };
+        if index >= len {
+            return Err(format!("No pending change at index {}", index));
+        }
+        match kind {
+            ChangeKind::Update => {
+                self.updates.remove(index);
+            }
+            ChangeKind::Insert => {
+                self.inserts.remove(index);
+            }
+            ChangeKind::Delete => {
+                self.deletes.remove(index);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A generated statement with its parameters in placeholder order.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct DmlStatement {
+    pub sql: String,
+    pub params: Vec<Value>,
+    /// Updates and deletes must hit exactly one row, or the whole change set
+    /// is rolled back.
+    pub single_row: bool,
+}
+
+pub fn quote_identifier(dialect: SqlDialect, name: &str) -> String {
+    match dialect {
+        SqlDialect::Mysql => format!("`{}`", name.replace('`', "``")),
+        SqlDialect::Postgres | SqlDialect::Sqlite => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
+
+/// `schema.table` with both parts quoted, or just the quoted table name.
+pub fn qualified_name(dialect: SqlDialect, schema: Option<&str>, table_name: &str) -> String {
+    match schema {
+        Some(schema) => format!(
+            "{}.{}",
+            quote_identifier(dialect, schema),
+            quote_identifier(dialect, table_name)
+        ),
+        None => quote_identifier(dialect, table_name),
+    }
+}
+
+/// Text form of a value for drivers that bind everything as text.
+pub fn value_as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// The error to raise when an update or delete did not hit exactly one row,
+/// e.g. because the row was changed or removed since it was loaded.
+pub fn row_count_error(statement: &DmlStatement, rows_affected: u64) -> Option<DatabaseError> {
+    if !statement.single_row || rows_affected == 1 {
+        return None;
+    }
+    Some(DatabaseError::new(format!(
+        "Expected to change 1 row but changed {}: {}",
+        rows_affected, statement.sql
+    )))
+}
+
+/// Column sets that identify a row: the primary key first, then full
+/// (non-partial) unique indexes. Indexes with a part over an expression are
+/// skipped, since a row cannot be matched on their columns alone.
+fn key_column_sets(
+    indexes: &[IndexDefinition],
+    column_types: &HashMap<&str, &str>,
+) -> Vec<Vec<String>> {
+    let mut keys: Vec<&IndexDefinition> = indexes
+        .iter()
+        .filter(|index| (index.is_primary || index.is_unique) && index.condition.is_none())
+        .filter(|index| {
+            !index.column_names.is_empty()
+                && index
+                    .column_names
+                    .iter()
+                    .all(|c| column_types.contains_key(c.as_str()))
+        })
+        .collect();
+    keys.sort_by_key(|index| !index.is_primary);
+    keys.into_iter()
+        .map(|index| index.column_names.clone())
+        .collect()
+}
+
+/// The type to cast a Postgres parameter to: the column's type without its
+/// modifier. Casting to `varchar(20)` or `bit(3)` would silently cut a value
+/// that is too long, while the base type leaves the length check to the
+/// assignment, which raises an error instead.
+fn parameter_type(data_type: &str) -> String {
+    let mut base = String::with_capacity(data_type.len());
+    let mut depth = 0usize;
+    let mut quoted = false;
+    for c in data_type.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                if depth == 0 {
+                    base.push(c);
+                }
+            }
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            _ if depth == 0 => base.push(c),
+            _ => {}
+        }
+    }
+
+    let name = base.trim_end_matches("[]");
+    let dimensions = &base[name.len()..];
+    // `character` and `bit` alone mean a length of one, and interval fields
+    // are a modifier too
+    let name = match name {
+        "character" => "bpchar",
+        "bit" => "\"bit\"",
+        name if name.starts_with("interval") => "interval",
+        name => name,
+    };
+    format!("{}{}", name, dimensions)
+}
+
+struct Planner<'a> {
+    dialect: SqlDialect,
+    table: String,
+    table_name: &'a str,
+    /// Declared type per column, used for Postgres parameter casts.
+    column_types: HashMap<&'a str, &'a str>,
+    keys: Vec<Vec<String>>,
+}
+
+impl Planner<'_> {
+    fn check_columns<'v>(
+        &self,
+        columns: impl IntoIterator<Item = &'v String>,
+    ) -> Result<(), String> {
+        for column in columns {
+            if !self.column_types.contains_key(column.as_str()) {
+                return Err(format!(
+                    "Column {} does not exist in {}",
+                    column, self.table_name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn placeholder(&self, column: &str, params: &[Value]) -> String {
+        match self.dialect {
+            // Values are bound as text and cast, like the filters in get_table_data
+            SqlDialect::Postgres => format!(
+                "${}::{}",
+                params.len(),
+                parameter_type(self.column_types[column])
+            ),
+            SqlDialect::Mysql | SqlDialect::Sqlite => "?".to_string(),
+        }
+    }
+
+    fn bind(&self, column: &str, value: &Value, params: &mut Vec<Value>) -> String {
+        params.push(value.clone());
+        self.placeholder(column, params)
+    }
+
+    /// Builds the WHERE clause from the first key whose columns are all
+    /// present and non-null. Unique keys allow several rows with NULLs, so a
+    /// key holding one does not identify a single row.
+    fn where_key(
+        &self,
+        key: &Map<String, Value>,
+        params: &mut Vec<Value>,
+    ) -> Result<String, String> {
+        let columns = self
+            .keys
+            .iter()
+            .find(|columns| {
+                columns
+                    .iter()
+                    .all(|c| key.get(c).is_some_and(|value| !value.is_null()))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Row key must give non-null values for the columns of a primary or \
+                     unique key of {} ({})",
+                    self.table_name,
+                    self.keys
+                        .iter()
+                        .map(|columns| columns.join(", "))
+                        .collect::<Vec<_>>()
+                        .join(" | ")
+                )
+            })?;
+
+        let conditions: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                format!(
+                    "{} = {}",
+                    quote_identifier(self.dialect, column),
+                    self.bind(column, &key[column], params)
+                )
+            })
+            .collect();
+        Ok(conditions.join(" AND "))
+    }
+
+    fn update(&self, row: &RowUpdate) -> Result<Option<DmlStatement>, String> {
+        if row.values.is_empty() {
+            return Ok(None);
+        }
+        self.check_columns(row.values.keys())?;
+
+        let mut params = Vec::new();
+        let assignments: Vec<String> = row
+            .values
+            .iter()
+            .map(|(column, value)| {
+                format!(
+                    "{} = {}",
+                    quote_identifier(self.dialect, column),
+                    self.bind(column, value, &mut params)
+                )
+            })
+            .collect();
+        let condition = self.where_key(&row.key, &mut params)?;
+
+        Ok(Some(DmlStatement {
+            sql: format!(
+                "UPDATE {} SET {} WHERE {}",
+                self.table,
+                assignments.join(", "),
+                condition
+            ),
+            params,
+            single_row: true,
+        }))
+    }
+
+    fn insert(&self, row: &Map<String, Value>) -> Result<DmlStatement, String> {
+        self.check_columns(row.keys())?;
+
+        if row.is_empty() {
+            let sql = match self.dialect {
+                SqlDialect::Mysql => format!("INSERT INTO {} () VALUES ()", self.table),
+                SqlDialect::Postgres | SqlDialect::Sqlite => {
+                    format!("INSERT INTO {} DEFAULT VALUES", self.table)
+                }
+            };
+            return Ok(DmlStatement {
+                sql,
+                params: Vec::new(),
+                single_row: false,
+            });
+        }
+
+        let mut params = Vec::new();
+        let columns: Vec<String> = row
+            .keys()
+            .map(|column| quote_identifier(self.dialect, column))
+            .collect();
+        let values: Vec<String> = row
+            .iter()
+            .map(|(column, value)| self.bind(column, value, &mut params))
+            .collect();
+
+        Ok(DmlStatement {
+            sql: format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                self.table,
+                columns.join(", "),
+                values.join(", ")
+            ),
+            params,
+            single_row: false,
+        })
+    }
+
+    fn delete(&self, key: &Map<String, Value>) -> Result<DmlStatement, String> {
+        let mut params = Vec::new();
+        let condition = self.where_key(key, &mut params)?;
+        Ok(DmlStatement {
+            sql: format!("DELETE FROM {} WHERE {}", self.table, condition),
+            params,
+            single_row: true,
+        })
+    }
+}
+
+/// Turns a change set into parameterized statements. Deletes run first so
+/// freed unique values can be reused by the updates and inserts that follow.
+/// Tables without a primary or unique key are refused, since their rows
+/// cannot be addressed reliably.
+pub fn plan_changes(
+    dialect: SqlDialect,
+    schema: Option<&str>,
+    table_name: &str,
+    columns: &[ColumnDefinition],
+    indexes: &[IndexDefinition],
+    changes: &TableChanges,
+) -> Result<Vec<DmlStatement>, String> {
+    let column_types: HashMap<&str, &str> = columns
+        .iter()
+        .map(|c| (c.column_name.as_str(), c.data_type.as_str()))
+        .collect();
+    let planner = Planner {
+        dialect,
+        table: qualified_name(dialect, schema, table_name),
+        table_name,
+        keys: key_column_sets(indexes, &column_types),
+        column_types,
+    };
+
+    if planner.keys.is_empty() {
+        return Err(format!(
+            "{} has no primary or unique key, so its rows cannot be edited",
+            table_name
+        ));
+    }
+
+    let mut statements = Vec::new();
+    for key in &changes.deletes {
+        statements.push(planner.delete(key)?);
+    }
+    for row in &changes.updates {
+        statements.extend(planner.update(row)?);
+    }
+    for row in &changes.inserts {
+        statements.push(planner.insert(row)?);
+    }
+    Ok(statements)
+}
+
+/// Renders a JSON value as a SQL literal of the dialect.
+pub fn render_literal(dialect: SqlDialect, value: &Value) -> String {
+    match (dialect, value) {
+        (_, Value::Null) => "NULL".to_string(),
+        // Postgres parameters are bound as text, so show them the same way
+        (SqlDialect::Postgres, value) => {
+            let text = value_as_text(value).unwrap_or_default();
+            format!("'{}'", text.replace('\'', "''"))
+        }
+        (_, Value::Bool(b)) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        (_, Value::Number(n)) => n.to_string(),
+        (SqlDialect::Mysql, value) => {
+            let text = value_as_text(value).unwrap_or_default();
+            format!("'{}'", text.replace('\\', "\\\\").replace('\'', "''"))
+        }
+        (SqlDialect::Sqlite, value) => {
+            let text = value_as_text(value).unwrap_or_default();
+            format!("'{}'", text.replace('\'', "''"))
+        }
+    }
+}
+
+/// Inlines a statement's parameters as literals, for previewing the SQL that
+/// will run. Placeholders inside quoted identifiers are left alone.
+pub fn render_statement(dialect: SqlDialect, statement: &DmlStatement) -> String {
+    let chars: Vec<char> = statement.sql.chars().collect();
+    let mut rendered = String::with_capacity(statement.sql.len());
+    let mut next_param = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '`' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&q| q == c)
+                    .map_or(chars.len(), |p| i + 1 + p + 1);
+                rendered.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '?' if dialect != SqlDialect::Postgres => {
+                if let Some(value) = statement.params.get(next_param) {
+                    rendered.push_str(&render_literal(dialect, value));
+                }
+                next_param += 1;
+            }
+            '$' if dialect == SqlDialect::Postgres => {
+                let digits: String = chars[i + 1..]
+                    .iter()
+                    .take_while(|d| d.is_ascii_digit())
+                    .collect();
+                if let Some(value) = digits
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| statement.params.get(n.wrapping_sub(1)))
+                {
+                    rendered.push_str(&render_literal(dialect, value));
+                    i += 1 + digits.len();
+                    continue;
+                }
+                rendered.push(c);
+            }
+            _ => rendered.push(c),
+        }
+        i += 1;
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn column(name: &str, data_type: &str) -> ColumnDefinition {
+        ColumnDefinition {
+            column_name: name.to_string(),
+            data_type: data_type.to_string(),
+            is_nullable: "YES".to_string(),
+            column_default: None,
+            comment: None,
+            foreign_key: None,
+        }
+    }
+
+    fn index(name: &str, is_primary: bool, columns: &[&str]) -> IndexDefinition {
+        IndexDefinition {
+            index_name: name.to_string(),
+            index_algorithm: "btree".to_string(),
+            is_unique: true,
+            is_primary,
+            column_names: columns.iter().map(|c| c.to_string()).collect(),
+            condition: None,
+            comment: None,
+        }
+    }
+
+    fn row(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn casts_parameters_to_the_type_without_its_modifier() {
+        assert_eq!(parameter_type("character varying(20)"), "character varying");
+        assert_eq!(parameter_type("numeric(5,2)"), "numeric");
+        assert_eq!(
+            parameter_type("timestamp(3) without time zone"),
+            "timestamp without time zone"
+        );
+        assert_eq!(parameter_type("character(3)"), "bpchar");
+        assert_eq!(parameter_type("bit(3)"), "\"bit\"");
+        assert_eq!(
+            parameter_type("character varying(10)[]"),
+            "character varying[]"
+        );
+        assert_eq!(parameter_type("interval year to month"), "interval");
+        assert_eq!(parameter_type("\"odd(type)\""), "\"odd(type)\"");
+        assert_eq!(parameter_type("integer"), "integer");
+    }
+
+    #[test]
+    fn prefers_the_primary_key_and_skips_partial_and_expression_indexes() {
+        let columns = [
+            column("id", "integer"),
+            column("email", "text"),
+            column("tenant", "integer"),
+        ];
+        let mut partial = index("active_email", false, &["email"]);
+        partial.condition = Some("deleted = 0".to_string());
+        let indexes = [
+            index("lower_email", false, &["tenant", "lower(email)"]),
+            partial,
+            index("pk", true, &["id"]),
+            index("empty", false, &[]),
+        ];
+        let changes = TableChanges {
+            deletes: vec![row(json!({ "id": 1, "email": "a@b" }))],
+            ..Default::default()
+        };
+
+        let statements = plan_changes(
+            SqlDialect::Postgres,
+            Some("public"),
+            "users",
+            &columns,
+            &indexes,
+            &changes,
+        )
+        .unwrap();
+        assert_eq!(
+            statements[0].sql,
+            "DELETE FROM \"public\".\"users\" WHERE \"id\" = $1::integer"
+        );
+
+        let error = plan_changes(
+            SqlDialect::Postgres,
+            None,
+            "users",
+            &columns,
+            &indexes[..2],
+            &changes,
+        )
+        .unwrap_err();
+        assert!(error.contains("no primary or unique key"));
+    }
+
+    #[test]
+    fn binds_updates_with_base_type_casts() {
+        let columns = [
+            column("id", "integer"),
+            column("code", "character varying(3)"),
+        ];
+        let changes = TableChanges {
+            updates: vec![RowUpdate {
+                key: row(json!({ "id": 7, "code": "old" })),
+                values: row(json!({ "code": "toolong" })),
+            }],
+            ..Default::default()
+        };
+
+        let statements = plan_changes(
+            SqlDialect::Postgres,
+            None,
+            "t",
+            &columns,
+            &[index("pk", true, &["id"])],
+            &changes,
+        )
+        .unwrap();
+        assert_eq!(
+            statements[0].sql,
+            "UPDATE \"t\" SET \"code\" = $1::character varying WHERE \"id\" = $2::integer"
+        );
+        assert_eq!(statements[0].params, [json!("toolong"), json!(7)]);
+        assert!(statements[0].single_row);
+    }
+
+    fn statement(sql: &str, params: Vec<Value>) -> DmlStatement {
+        DmlStatement {
+            sql: sql.to_string(),
+            params,
+            single_row: false,
+        }
+    }
+
+    #[test]
+    fn renders_literals_for_each_dialect() {
+        let text = json!("it's a \\ path");
+        assert_eq!(
+            render_literal(SqlDialect::Postgres, &text),
+            "'it''s a \\ path'"
+        );
+        assert_eq!(
+            render_literal(SqlDialect::Mysql, &text),
+            "'it''s a \\\\ path'"
+        );
+        assert_eq!(
+            render_literal(SqlDialect::Sqlite, &text),
+            "'it''s a \\ path'"
+        );
+        assert_eq!(render_literal(SqlDialect::Postgres, &json!(42)), "'42'");
+        assert_eq!(render_literal(SqlDialect::Mysql, &json!(42)), "42");
+        assert_eq!(render_literal(SqlDialect::Sqlite, &json!(true)), "TRUE");
+        assert_eq!(render_literal(SqlDialect::Mysql, &Value::Null), "NULL");
+    }
+
+    #[test]
+    fn inlines_numbered_postgres_parameters() {
+        let update = statement(
+            "UPDATE \"t\" SET \"a\" = $2::text WHERE \"id\" = $1::integer AND \"b\" = $10",
+            vec![json!(7), json!("O'Brien")],
+        );
+        assert_eq!(
+            render_statement(SqlDialect::Postgres, &update),
+            "UPDATE \"t\" SET \"a\" = 'O''Brien'::text WHERE \"id\" = '7'::integer AND \"b\" = $10"
+        );
+    }
+
+    #[test]
+    fn inlines_positional_parameters_in_order() {
+        let insert = statement(
+            "INSERT INTO `t` (`a`, `b`, `c`) VALUES (?, ?, ?)",
+            vec![json!("x\\y"), Value::Null, json!(1.5)],
+        );
+        assert_eq!(
+            render_statement(SqlDialect::Mysql, &insert),
+            "INSERT INTO `t` (`a`, `b`, `c`) VALUES ('x\\\\y', NULL, 1.5)"
+        );
+    }
+
+    #[test]
+    fn leaves_placeholders_in_quoted_identifiers() {
+        let sqlite = statement(
+            "UPDATE \"why?\" SET \"a?\" = ? WHERE \"id\" = ?",
+            vec![json!("v"), json!(3)],
+        );
+        assert_eq!(
+            render_statement(SqlDialect::Sqlite, &sqlite),
+            "UPDATE \"why?\" SET \"a?\" = 'v' WHERE \"id\" = 3"
+        );
+
+        let mysql = statement("DELETE FROM `a?b` WHERE `id` = ?", vec![json!(1)]);
+        assert_eq!(
+            render_statement(SqlDialect::Mysql, &mysql),
+            "DELETE FROM `a?b` WHERE `id` = 1"
+        );
+
+        let postgres = statement("DELETE FROM \"$1\" WHERE \"id\" = $1", vec![json!(5)]);
+        assert_eq!(
+            render_statement(SqlDialect::Postgres, &postgres),
+            "DELETE FROM \"$1\" WHERE \"id\" = '5'"
+        );
+    }
+}
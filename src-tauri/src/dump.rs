@@ -0,0 +1,768 @@
+use crate::database_provider::DatabaseProvider;
+use crate::db::{
+    ConstraintKind, DatabaseError, DatabaseErrorKind, DatabaseObject, ObjectKind, QueryStreamEvent,
+    DEFAULT_STREAM_BATCH_SIZE,
+};
+use crate::dml::{qualified_name, quote_identifier, render_literal};
+use crate::sql_splitter::{split_statements, SqlDialect, Statement};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::sync::Mutex;
+
+/// Text read from a dump before it is split and run, in bytes.
+const RESTORE_CHUNK_SIZE: usize = 1 << 20;
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DumpOptions {
+    /// Tables to dump; every table of the schema when unset.
+    pub tables: Option<Vec<String>>,
+    pub include_schema: bool,
+    pub include_data: bool,
+    /// Starts the dump with `DROP TABLE IF EXISTS` for each dumped table.
+    pub drop_existing: bool,
+    pub rows_per_statement: usize,
+}
+
+impl Default for DumpOptions {
+    fn default() -> Self {
+        Self {
+            tables: None,
+            include_schema: true,
+            include_data: true,
+            drop_existing: false,
+            rows_per_statement: 100,
+        }
+    }
+}
+
+/// Events pushed to the frontend while a dump is being written.
+#[derive(serde::Serialize, Clone)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum DumpEvent {
+    Progress {
+        table: String,
+        tables_done: usize,
+        table_count: usize,
+        rows_written: usize,
+    },
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct DumpSummary {
+    /// The dumped tables, in the order they are restored.
+    pub tables: Vec<String>,
+    pub rows_written: usize,
+}
+
+/// Events pushed to the frontend while a dump is being restored.
+#[derive(serde::Serialize, Clone)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum RestoreEvent {
+    Progress {
+        statements_run: usize,
+        bytes_read: u64,
+        total_bytes: u64,
+    },
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct RestoreSummary {
+    pub statements_run: usize,
+}
+
+/// A Postgres sequence, with the column it feeds when it backs a serial or
+/// identity column.
+struct PgSequence {
+    name: String,
+    owner_table: Option<String>,
+    owner_column: Option<String>,
+    identity: bool,
+    last_value: Option<String>,
+}
+
+/// Writes the tables' DDL followed by their rows as `INSERT` statements.
+/// Tables are ordered so that referenced tables come before the tables
+/// referencing them; tables in a reference cycle come last, in name order.
+/// Postgres foreign keys are added at the end, after the data, so cycles and
+/// self-references restore as well. Ownership is left to whoever restores the dump, as with `pg_dump --no-owner`.
+pub async fn dump(
+    provider: &dyn DatabaseProvider,
+    schema: Option<String>,
+    path: &str,
+    options: &DumpOptions,
+    query_id: Option<String>,
+    on_event: &(dyn Fn(DumpEvent) -> bool + Send + Sync),
+) -> Result<DumpSummary, DatabaseError> {
+    let dialect = provider.dialect();
+    let tables = dump_order(provider, &schema, options.tables.as_deref()).await?;
+    let sequences = match dialect {
+        SqlDialect::Postgres => {
+            pg_sequences(provider, &schema, &tables, options.tables.is_none()).await?
+        }
+        _ => Vec::new(),
+    };
+    let qualify = |name: &str| qualified_name(dialect, schema.as_deref(), name);
+    let write_error = |e: io::Error| DatabaseError::new(format!("Failed to write {}: {}", path, e));
+
+    let file = File::create(path).map_err(write_error)?;
+    let out = Mutex::new(BufWriter::new(file));
+    let write = |text: &str| out.lock().unwrap().write_all(text.as_bytes());
+
+    let mut header = format!(
+        "-- Dump of {} ({:?})\n-- Created {}\n\n",
+        schema.as_deref().unwrap_or("the default schema"),
+        dialect,
+        chrono::Utc::now().to_rfc3339()
+    );
+    match dialect {
+        SqlDialect::Mysql => header.push_str("SET FOREIGN_KEY_CHECKS = 0;\n\n"),
+        // Checked when the restore commits instead of after each statement
+        SqlDialect::Sqlite => header.push_str("PRAGMA defer_foreign_keys = ON;\n\n"),
+        SqlDialect::Postgres => {}
+    }
+    write(&header).map_err(write_error)?;
+
+    // Postgres foreign keys, added once every table has its rows
+    let mut foreign_keys = Vec::new();
+    if options.include_schema {
+        let mut ddl = String::new();
+        if options.drop_existing {
+            match dialect {
+                // Dropped together, tables referencing each other don't block
+                // one another
+                SqlDialect::Postgres if !tables.is_empty() => ddl.push_str(&format!(
+                    "DROP TABLE IF EXISTS {};\n",
+                    tables
+                        .iter()
+                        .map(|table| qualify(table))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+                _ => {
+                    for table in tables.iter().rev() {
+                        ddl.push_str(&format!("DROP TABLE IF EXISTS {};\n", qualify(table)));
+                    }
+                }
+            }
+            for sequence in sequences.iter().filter(|s| !s.identity) {
+                ddl.push_str(&format!(
+                    "DROP SEQUENCE IF EXISTS {};\n",
+                    qualify(&sequence.name)
+                ));
+            }
+            ddl.push('\n');
+        }
+        for sequence in sequences.iter().filter(|s| !s.identity) {
+            let definition =
+                object_ddl(provider, &schema, &sequence.name, ObjectKind::Sequence).await?;
+            ddl.push_str(&format!(
+                "-- Sequence: {}\n{}\n",
+                sequence.name,
+                definition.concat()
+            ));
+        }
+        for table in &tables {
+            let (keys, definition): (Vec<String>, Vec<String>) =
+                object_ddl(provider, &schema, table, ObjectKind::Table)
+                    .await?
+                    .into_iter()
+                    .partition(|sql| is_foreign_key(sql));
+            ddl.push_str(&format!("-- Table: {}\n{}\n", table, definition.concat()));
+            foreign_keys.extend(keys);
+        }
+        for sequence in &sequences {
+            if let (Some(table), Some(column), false) = (
+                &sequence.owner_table,
+                &sequence.owner_column,
+                sequence.identity,
+            ) {
+                ddl.push_str(&format!(
+                    "ALTER SEQUENCE {} OWNED BY {}.{};\n",
+                    qualify(&sequence.name),
+                    qualify(table),
+                    quote_identifier(dialect, column)
+                ));
+            }
+        }
+        write(&ddl).map_err(write_error)?;
+    }
+
+    let mut rows_written = 0;
+    if options.include_data {
+        for (tables_done, table) in tables.iter().enumerate() {
+            let columns = data_columns(provider, &schema, table).await?;
+            if columns.is_empty() {
+                continue;
+            }
+            let overriding = if sequences
+                .iter()
+                .any(|s| s.identity && s.owner_table.as_ref() == Some(table))
+            {
+                "OVERRIDING SYSTEM VALUE "
+            } else {
+                ""
+            };
+            let insert = format!(
+                "INSERT INTO {} ({}) {}VALUES",
+                qualify(table),
+                columns
+                    .iter()
+                    .map(|(name, _)| quote_identifier(dialect, name))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                overriding
+            );
+            let select = format!(
+                "SELECT {} FROM {}",
+                columns
+                    .iter()
+                    .map(|(name, data_type)| format!(
+                        "{} AS {}",
+                        literal_expression(dialect, name, data_type),
+                        quote_identifier(dialect, name)
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                qualify(table)
+            );
+            write(&format!("\n-- Data for {}\n", table)).map_err(write_error)?;
+
+            let table_rows = Mutex::new(0);
+            let failure = Mutex::new(None);
+            let cancelled = Mutex::new(false);
+            let sink = |event: QueryStreamEvent| {
+                let QueryStreamEvent::Rows { rows } = event else {
+                    return true;
+                };
+                let statements = insert_statements(&insert, &rows, options.rows_per_statement);
+                if let Err(e) = write(&statements) {
+                    *failure.lock().unwrap() = Some(e);
+                    return false;
+                }
+                let mut table_rows = table_rows.lock().unwrap();
+                *table_rows += rows.len();
+                let keep_going = on_event(DumpEvent::Progress {
+                    table: table.clone(),
+                    tables_done,
+                    table_count: tables.len(),
+                    rows_written: rows_written + *table_rows,
+                });
+                *cancelled.lock().unwrap() = !keep_going;
+                keep_going
+            };
+            provider
+                .execute_query_stream(
+                    select,
+                    query_id.clone(),
+                    None,
+                    DEFAULT_STREAM_BATCH_SIZE,
+                    usize::MAX,
+                    &sink,
+                )
+                .await?;
+            if let Some(e) = failure.into_inner().unwrap() {
+                return Err(write_error(e));
+            }
+            if cancelled.into_inner().unwrap() {
+                return Err(DatabaseError::cancelled());
+            }
+            rows_written += table_rows.into_inner().unwrap();
+        }
+
+        let mut values = String::new();
+        for sequence in &sequences {
+            if let Some(last_value) = &sequence.last_value {
+                values.push_str(&format!(
+                    "SELECT pg_catalog.setval({}, {}, true);\n",
+                    render_literal(dialect, &Value::String(qualify(&sequence.name))),
+                    last_value
+                ));
+            }
+        }
+        if !values.is_empty() {
+            write(&format!("\n-- Sequence values\n{}", values)).map_err(write_error)?;
+        }
+    }
+
+    if !foreign_keys.is_empty() {
+        write(&format!("\n-- Foreign keys\n{}", foreign_keys.concat())).map_err(write_error)?;
+    }
+    if dialect == SqlDialect::Mysql {
+        write("\nSET FOREIGN_KEY_CHECKS = 1;\n").map_err(write_error)?;
+    }
+    out.into_inner().unwrap().flush().map_err(write_error)?;
+    on_event(DumpEvent::Progress {
+        table: String::new(),
+        tables_done: tables.len(),
+        table_count: tables.len(),
+        rows_written,
+    });
+    Ok(DumpSummary {
+        tables,
+        rows_written,
+    })
+}
+
+/// The schema's tables, or the selected ones, with referenced tables first.
+async fn dump_order(
+    provider: &dyn DatabaseProvider,
+    schema: &Option<String>,
+    selected: Option<&[String]>,
+) -> Result<Vec<String>, DatabaseError> {
+    let mut tables: Vec<String> = provider
+        .list_objects(schema.clone())
+        .await?
+        .into_iter()
+        .filter(|o| o.kind == ObjectKind::Table)
+        .map(|o| o.name)
+        .collect();
+    if let Some(selected) = selected {
+        if let Some(missing) = selected.iter().find(|t| !tables.contains(t)) {
+            return Err(DatabaseError::with_kind(
+                DatabaseErrorKind::UndefinedObject,
+                format!("Table {} does not exist", missing),
+            ));
+        }
+        tables.retain(|t| selected.contains(t));
+    }
+
+    let mut parents: HashMap<String, HashSet<String>> = HashMap::new();
+    for table in &tables {
+        let constraints = provider
+            .get_table_constraints(schema.clone(), table.clone())
+            .await?;
+        let referenced = constraints
+            .into_iter()
+            .filter(|c| c.kind == ConstraintKind::ForeignKey)
+            .filter(|c| {
+                schema.is_none() || c.referenced_schema.is_none() || c.referenced_schema == *schema
+            })
+            .filter_map(|c| c.referenced_table)
+            .filter(|parent| tables.contains(parent))
+            .collect();
+        parents.insert(table.clone(), referenced);
+    }
+
+    Ok(dependency_order(tables, &parents))
+}
+
+/// Orders the tables so each comes after the tables it references, other
+/// than itself. Tables left in a reference cycle follow in their original order; their foreign
+/// keys are added after the data or checked once the restore commits.
+fn dependency_order(
+    tables: Vec<String>,
+    parents: &HashMap<String, HashSet<String>>,
+) -> Vec<String> {
+    let mut ordered: Vec<String> = Vec::with_capacity(tables.len());
+    let mut remaining = tables;
+    while !remaining.is_empty() {
+        let ready: Vec<String> = remaining
+            .iter()
+            .filter(|t| {
+                parents
+                    .get(*t)
+                    .is_none_or(|p| p.iter().all(|p| p == *t || ordered.contains(p)))
+            })
+            .cloned()
+            .collect();
+        if ready.is_empty() {
+            ordered.append(&mut remaining);
+            break;
+        }
+        remaining.retain(|t| !ready.contains(t));
+        ordered.extend(ready);
+    }
+    ordered
+}
+
+/// An object's DDL statements, each with its `;` and newline, without the
+/// statements assigning its owner.
+async fn object_ddl(
+    provider: &dyn DatabaseProvider,
+    schema: &Option<String>,
+    name: &str,
+    kind: ObjectKind,
+) -> Result<Vec<String>, DatabaseError> {
+    let object = DatabaseObject {
+        name: name.to_string(),
+        kind,
+        table_name: None,
+        arguments: None,
+    };
+    let ddl = provider.get_object_ddl(schema.clone(), object).await?;
+    Ok(split_statements(&ddl, provider.dialect())
+        .into_iter()
+        .map(|s| s.sql.trim().to_string())
+        .filter(|sql| {
+            let upper = sql.to_ascii_uppercase();
+            !(upper.starts_with("ALTER ") && upper.contains(" OWNER TO "))
+        })
+        .map(|sql| format!("{};\n", sql))
+        .collect())
+}
+
+/// The `ALTER TABLE ... ADD CONSTRAINT ... FOREIGN KEY` statements Postgres
+/// table DDL ends with.
+fn is_foreign_key(sql: &str) -> bool {
+    let upper = sql.to_ascii_uppercase();
+    upper.starts_with("ALTER TABLE ")
+        && upper.contains(" ADD CONSTRAINT ")
+        && upper.contains(" FOREIGN KEY ")
+}
+
+/// The table's insertable columns with their types, leaving out generated ones.
+async fn data_columns(
+    provider: &dyn DatabaseProvider,
+    schema: &Option<String>,
+    table: &str,
+) -> Result<Vec<(String, String)>, DatabaseError> {
+    let dialect = provider.dialect();
+    let text = |s: &str| render_literal(dialect, &Value::String(s.to_string()));
+    let schema_literal = schema.as_deref().map_or("NULL".to_string(), text);
+    let generated_query = match dialect {
+        SqlDialect::Postgres => Some(format!(
+            "SELECT attname::text FROM pg_attribute
+             WHERE attrelid = {}::regclass AND attnum > 0 AND NOT attisdropped
+               AND attgenerated <> ''",
+            text(&qualified_name(dialect, schema.as_deref(), table))
+        )),
+        SqlDialect::Mysql => Some(format!(
+            "SELECT CAST(COLUMN_NAME AS CHAR) FROM information_schema.COLUMNS
+             WHERE TABLE_SCHEMA = COALESCE({}, DATABASE()) AND TABLE_NAME = {}
+               AND GENERATION_EXPRESSION <> ''",
+            schema_literal,
+            text(table)
+        )),
+        // pragma_table_info, behind get_table_structure, already skips them
+        SqlDialect::Sqlite => None,
+    };
+    let generated: HashSet<String> = match generated_query {
+        Some(query) => provider
+            .execute_query(query, None, None)
+            .await?
+            .rows
+            .into_iter()
+            .filter_map(|row| row.into_iter().next())
+            .filter_map(|value| value.as_str().map(str::to_string))
+            .collect(),
+        None => HashSet::new(),
+    };
+
+    Ok(provider
+        .get_table_structure(schema.clone(), table.to_string())
+        .await?
+        .into_iter()
+        .filter(|c| !generated.contains(&c.column_name))
+        .map(|c| (c.column_name, c.data_type))
+        .collect())
+}
+
+/// Selects a column as the SQL literal that reproduces its value, so the
+/// database does the quoting for every type it has.
+fn literal_expression(dialect: SqlDialect, column: &str, data_type: &str) -> String {
+    let column = quote_identifier(dialect, column);
+    match dialect {
+        SqlDialect::Postgres => format!("quote_nullable({}::text)", column),
+        SqlDialect::Mysql => {
+            let data_type = data_type.to_ascii_lowercase();
+            if data_type.contains("blob")
+                || data_type.contains("binary")
+                || data_type.starts_with("bit")
+            {
+                format!(
+                    "IF({0} IS NULL, 'NULL', CONCAT('X''', HEX({0}), ''''))",
+                    column
+                )
+            } else {
+                format!("CAST(QUOTE({}) AS CHAR)", column)
+            }
+        }
+        SqlDialect::Sqlite => format!("quote({})", column),
+    }
+}
+
+fn insert_statements(insert: &str, rows: &[Vec<Value>], rows_per_statement: usize) -> String {
+    let mut statements = String::new();
+    for chunk in rows.chunks(rows_per_statement.max(1)) {
+        let values: Vec<String> = chunk
+            .iter()
+            .map(|row| {
+                let literals: Vec<&str> = row
+                    .iter()
+                    .map(|value| value.as_str().unwrap_or("NULL"))
+                    .collect();
+                format!("({})", literals.join(", "))
+            })
+            .collect();
+        statements.push_str(insert);
+        statements.push_str("\n  ");
+        statements.push_str(&values.join(",\n  "));
+        statements.push_str(";\n");
+    }
+    statements
+}
+
+async fn pg_sequences(
+    provider: &dyn DatabaseProvider,
+    schema: &Option<String>,
+    tables: &[String],
+    all_tables: bool,
+) -> Result<Vec<PgSequence>, DatabaseError> {
+    let schema_literal = schema.as_ref().map_or("NULL".to_string(), |s| {
+        render_literal(SqlDialect::Postgres, &Value::String(s.clone()))
+    });
+    let query = format!(
+        "SELECT s.relname::text AS sequence_name, t.relname::text AS table_name,
+                a.attname::text AS column_name, d.deptype::text AS dependency,
+                ps.last_value::text AS last_value
+         FROM pg_class s
+         JOIN pg_namespace n ON n.oid = s.relnamespace
+         LEFT JOIN pg_depend d ON d.classid = 'pg_class'::regclass AND d.objid = s.oid
+              AND d.refclassid = 'pg_class'::regclass AND d.deptype IN ('a', 'i')
+         LEFT JOIN pg_class t ON t.oid = d.refobjid
+         LEFT JOIN pg_attribute a ON a.attrelid = d.refobjid AND a.attnum = d.refobjsubid
+         LEFT JOIN pg_sequences ps ON ps.schemaname = n.nspname AND ps.sequencename = s.relname
+         WHERE s.relkind = 'S' AND n.nspname = COALESCE({}, current_schema())
+         ORDER BY s.relname",
+        schema_literal
+    );
+    let text = |value: &Value| value.as_str().map(str::to_string);
+    let sequences = provider
+        .execute_query(query, None, None)
+        .await?
+        .rows
+        .iter()
+        .map(|row| PgSequence {
+            name: text(&row[0]).unwrap_or_default(),
+            owner_table: text(&row[1]),
+            owner_column: text(&row[2]),
+            identity: row[3].as_str() == Some("i"),
+            last_value: text(&row[4]),
+        })
+        // Standalone sequences go with a whole-schema dump
+        .filter(|s| match &s.owner_table {
+            Some(table) => tables.contains(table),
+            None => all_tables,
+        })
+        .collect();
+    Ok(sequences)
+}
+
+/// Replays a dump, or any SQL script, through the multi-statement executor.
+/// The script runs in one transaction on one connection, so session settings
+/// carry over between statements and a failing statement rolls everything
+/// back (except, on MySQL, DDL that has already committed implicitly). The
+/// file is read and split a chunk at a time rather than all at once.
+pub async fn restore(
+    provider: &dyn DatabaseProvider,
+    path: &str,
+    query_id: Option<String>,
+    on_event: &(dyn Fn(RestoreEvent) -> bool + Send + Sync),
+) -> Result<RestoreSummary, DatabaseError> {
+    let read_error = |e: io::Error| DatabaseError::new(format!("Failed to read {}: {}", path, e));
+    let mut file = File::open(path).map_err(read_error)?;
+    let total_bytes = file.metadata().map_err(read_error)?.len();
+
+    let session_id = format!("restore-{}", uuid::Uuid::new_v4());
+    provider.begin_transaction(session_id.clone()).await?;
+    let replayed = replay(
+        provider,
+        &mut file,
+        path,
+        total_bytes,
+        &session_id,
+        query_id,
+        on_event,
+    )
+    .await;
+    match replayed {
+        Ok(statements_run) => {
+            provider.commit_transaction(session_id).await?;
+            Ok(RestoreSummary { statements_run })
+        }
+        Err(e) => {
+            let _ = provider.rollback_transaction(session_id).await;
+            Err(e)
+        }
+    }
+}
+
+async fn replay(
+    provider: &dyn DatabaseProvider,
+    file: &mut File,
+    path: &str,
+    total_bytes: u64,
+    session_id: &str,
+    query_id: Option<String>,
+    on_event: &(dyn Fn(RestoreEvent) -> bool + Send + Sync),
+) -> Result<usize, DatabaseError> {
+    let read_error = |e: io::Error| DatabaseError::new(format!("Failed to read {}: {}", path, e));
+    let dialect = provider.dialect();
+    let mut buffer = vec![0; RESTORE_CHUNK_SIZE];
+    let mut bytes = Vec::new();
+    let mut text = String::new();
+    // Where `text` starts within the file, as (character offset, line)
+    let mut start = (0, 0);
+    let mut bytes_read = 0;
+    let mut statements_run = 0;
+    // A statement longer than a chunk is carried over whole, so wait for a
+    // full chunk beyond it before splitting again
+    let mut split_at = RESTORE_CHUNK_SIZE;
+
+    loop {
+        let read = file.read(&mut buffer).map_err(read_error)?;
+        let eof = read == 0;
+        bytes.extend_from_slice(&buffer[..read]);
+        bytes_read += read as u64;
+
+        // Hold back a character split across two reads
+        let valid = match std::str::from_utf8(&bytes) {
+            Ok(s) => s.len(),
+            Err(e) if !eof && e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(read_error(io::Error::new(io::ErrorKind::InvalidData, e))),
+        };
+        text.push_str(std::str::from_utf8(&bytes[..valid]).unwrap_or_default());
+        bytes.drain(..valid);
+        if !eof && text.len() < split_at {
+            continue;
+        }
+
+        // The last statement may continue in the next chunk, so it waits for it
+        let mut statements = split_statements(&text, dialect);
+        let carried_from = if eof {
+            None
+        } else {
+            Some(statements.pop().map_or(0, |last| last.offset))
+        };
+        let statements: Vec<Statement> = statements
+            .into_iter()
+            .map(|s| Statement {
+                offset: start.0 + s.offset,
+                line: start.1 + s.line,
+                ..s
+            })
+            .collect();
+
+        if !statements.is_empty() {
+            let results = provider
+                .execute_script(
+                    statements,
+                    true,
+                    query_id.clone(),
+                    Some(session_id.to_string()),
+                )
+                .await?;
+            for result in results {
+                if let Some(mut error) = result.error {
+                    error.message = format!("Line {}: {}", result.line, error.message);
+                    return Err(error);
+                }
+                statements_run += 1;
+            }
+        }
+
+        if !on_event(RestoreEvent::Progress {
+            statements_run,
+            bytes_read,
+            total_bytes,
+        }) {
+            return Err(DatabaseError::cancelled());
+        }
+
+        match carried_from {
+            None => return Ok(statements_run),
+            Some(offset) => {
+                let carried_bytes = text
+                    .char_indices()
+                    .nth(offset)
+                    .map_or(text.len(), |(i, _)| i);
+                start.0 += offset;
+                start.1 += text[..carried_bytes].matches('\n').count();
+                text.drain(..carried_bytes);
+                split_at = text.len() + RESTORE_CHUNK_SIZE;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(tables: &[&str]) -> Vec<String> {
+        tables.iter().map(|t| t.to_string()).collect()
+    }
+
+    fn references(edges: &[(&str, &[&str])]) -> HashMap<String, HashSet<String>> {
+        edges
+            .iter()
+            .map(|(table, parents)| {
+                (
+                    table.to_string(),
+                    parents.iter().map(|p| p.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn orders_referenced_tables_first() {
+        let parents = references(&[
+            ("order_items", &["orders", "products"]),
+            ("orders", &["customers"]),
+            ("products", &[]),
+            ("customers", &[]),
+        ]);
+        let ordered = dependency_order(
+            names(&["order_items", "orders", "products", "customers"]),
+            &parents,
+        );
+        assert_eq!(
+            ordered,
+            names(&["products", "customers", "orders", "order_items"])
+        );
+    }
+
+    #[test]
+    fn ignores_self_references() {
+        let parents = references(&[("employees", &["employees", "departments"])]);
+        let ordered = dependency_order(names(&["employees", "departments"]), &parents);
+        assert_eq!(ordered, names(&["departments", "employees"]));
+    }
+
+    #[test]
+    fn appends_tables_in_a_cycle_in_their_original_order() {
+        let parents = references(&[("a", &["b"]), ("b", &["a"]), ("c", &["a"]), ("d", &[])]);
+        let ordered = dependency_order(names(&["a", "b", "c", "d"]), &parents);
+        assert_eq!(ordered, names(&["d", "a", "b", "c"]));
+    }
+
+    #[test]
+    fn defers_only_foreign_key_constraints() {
+        let statements = [
+            "CREATE TABLE public.employees (\n    id integer NOT NULL\n);\n",
+            "ALTER TABLE public.employees ADD CONSTRAINT employees_pkey PRIMARY KEY (id);\n",
+            "ALTER TABLE public.employees ADD CONSTRAINT employees_manager_fkey \
+             FOREIGN KEY (manager_id) REFERENCES public.employees(id);\n",
+            "CREATE INDEX employees_name ON public.employees (name);\n",
+        ];
+        let (keys, definition): (Vec<&str>, Vec<&str>) =
+            statements.into_iter().partition(|sql| is_foreign_key(sql));
+        assert_eq!(keys, vec![statements[2]]);
+        assert_eq!(definition.len(), 3);
+    }
+
+    #[test]
+    fn splits_rows_into_statements() {
+        let row = |id: &str| vec![Value::String(id.to_string()), Value::Null];
+        let rows = vec![row("1"), row("2"), row("'three'")];
+        assert_eq!(
+            insert_statements("INSERT INTO t (a, b) VALUES", &rows, 2),
+            "INSERT INTO t (a, b) VALUES\n  (1, NULL),\n  (2, NULL);\n\
+             INSERT INTO t (a, b) VALUES\n  ('three', NULL);\n"
+        );
+    }
+}
@@ -0,0 +1,484 @@
+use crate::database_provider::DatabaseProvider;
+use crate::db::{ConstraintKind, DatabaseError, ObjectKind};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+#[derive(serde::Serialize)]
+pub struct SchemaGraph {
+    /// The schema drawn: the one asked for, or else the connection's current one.
+    pub schema: Option<String>,
+    pub tables: Vec<GraphTable>,
+    /// Foreign keys, including those pointing at tables in other schemas.
+    pub relationships: Vec<GraphRelationship>,
+}
+
+#[derive(serde::Serialize)]
+pub struct GraphTable {
+    pub name: String,
+    pub columns: Vec<GraphColumn>,
+}
+
+#[derive(serde::Serialize)]
+pub struct GraphColumn {
+    pub name: String,
+    pub data_type: String,
+    pub is_nullable: bool,
+    pub is_primary_key: bool,
+    pub is_foreign_key: bool,
+}
+
+/// A foreign key from `from_table` (the child) to `to_table` (the parent).
+#[derive(serde::Serialize)]
+pub struct GraphRelationship {
+    pub constraint_name: String,
+    pub from_table: String,
+    pub from_columns: Vec<String>,
+    pub to_schema: Option<String>,
+    pub to_table: String,
+    pub to_columns: Vec<String>,
+    /// A child row may have no parent, because a key column is nullable.
+    pub optional: bool,
+    /// A parent has at most one child, because the key is also unique.
+    pub one_to_one: bool,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagramFormat {
+    Mermaid,
+    Dot,
+    #[serde(rename = "plantuml")]
+    PlantUml,
+}
+
+/// Collects every table of the schema with its columns and foreign keys.
+pub async fn build_graph(
+    provider: &dyn DatabaseProvider,
+    schema: Option<String>,
+) -> Result<SchemaGraph, DatabaseError> {
+    let objects = provider.list_objects(schema.clone()).await?;
+    // Relationships name the schema they point into, so the graph needs the
+    // name of its own schema to tell them apart
+    let graph_schema = match &schema {
+        Some(schema) => Some(schema.clone()),
+        None => provider.current_schema().await?,
+    };
+    let mut tables = Vec::new();
+    let mut relationships = Vec::new();
+
+    for object in objects {
+        if object.kind != ObjectKind::Table {
+            continue;
+        }
+        let structure = provider
+            .get_table_structure(schema.clone(), object.name.clone())
+            .await?;
+        let constraints = provider
+            .get_table_constraints(schema.clone(), object.name.clone())
+            .await?;
+
+        let primary_key: HashSet<&String> = constraints
+            .iter()
+            .filter(|c| c.kind == ConstraintKind::PrimaryKey)
+            .flat_map(|c| &c.column_names)
+            .collect();
+        let foreign_key: HashSet<&String> = constraints
+            .iter()
+            .filter(|c| c.kind == ConstraintKind::ForeignKey)
+            .flat_map(|c| &c.column_names)
+            .collect();
+        let unique_keys: Vec<HashSet<&String>> = constraints
+            .iter()
+            .filter(|c| matches!(c.kind, ConstraintKind::PrimaryKey | ConstraintKind::Unique))
+            .map(|c| c.column_names.iter().collect())
+            .collect();
+
+        let columns: Vec<GraphColumn> = structure
+            .into_iter()
+            .map(|column| GraphColumn {
+                is_nullable: column.is_nullable == "YES",
+                is_primary_key: primary_key.contains(&column.column_name),
+                is_foreign_key: foreign_key.contains(&column.column_name),
+                name: column.column_name,
+                data_type: column.data_type,
+            })
+            .collect();
+
+        for constraint in &constraints {
+            if constraint.kind != ConstraintKind::ForeignKey {
+                continue;
+            }
+            let Some(to_table) = constraint.referenced_table.clone() else {
+                continue;
+            };
+            let key: HashSet<&String> = constraint.column_names.iter().collect();
+            relationships.push(GraphRelationship {
+                constraint_name: constraint.constraint_name.clone(),
+                from_table: object.name.clone(),
+                from_columns: constraint.column_names.clone(),
+                to_schema: constraint.referenced_schema.clone(),
+                to_table,
+                to_columns: constraint.referenced_columns.clone(),
+                optional: columns
+                    .iter()
+                    .any(|column| column.is_nullable && key.contains(&column.name)),
+                one_to_one: unique_keys.contains(&key),
+            });
+        }
+
+        tables.push(GraphTable {
+            name: object.name,
+            columns,
+        });
+    }
+
+    Ok(SchemaGraph {
+        schema: graph_schema,
+        tables,
+        relationships,
+    })
+}
+
+impl SchemaGraph {
+    /// Relationships between tables that are both part of the diagram.
+    fn internal_relationships(&self) -> impl Iterator<Item = &GraphRelationship> {
+        let names: HashSet<&str> = self.tables.iter().map(|t| t.name.as_str()).collect();
+        self.relationships.iter().filter(move |r| {
+            names.contains(r.to_table.as_str())
+                && (r.to_schema.is_none() || r.to_schema == self.schema)
+        })
+    }
+
+    pub fn render(&self, format: DiagramFormat) -> String {
+        match format {
+            DiagramFormat::Mermaid => self.to_mermaid(),
+            DiagramFormat::Dot => self.to_dot(),
+            DiagramFormat::PlantUml => self.to_plantuml(),
+        }
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("erDiagram\n");
+        for table in &self.tables {
+            let _ = writeln!(out, "    {} {{", mermaid_word(&table.name));
+            for column in &table.columns {
+                let keys: Vec<&str> =
+                    [(column.is_primary_key, "PK"), (column.is_foreign_key, "FK")]
+                        .iter()
+                        .filter(|(flag, _)| *flag)
+                        .map(|(_, key)| *key)
+                        .collect();
+                let line = format!(
+                    "        {} {} {}",
+                    mermaid_word(&column.data_type),
+                    mermaid_word(&column.name),
+                    keys.join(", ")
+                );
+                let _ = writeln!(out, "{}", line.trim_end());
+            }
+            out.push_str("    }\n");
+        }
+        for r in self.internal_relationships() {
+            let parent = if r.optional { "|o" } else { "||" };
+            let child = if r.one_to_one { "o|" } else { "o{" };
+            let _ = writeln!(
+                out,
+                "    {} {}--{} {} : \"{}\"",
+                mermaid_word(&r.to_table),
+                parent,
+                child,
+                mermaid_word(&r.from_table),
+                r.constraint_name.replace('"', "'")
+            );
+        }
+        out
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from(
+            "digraph schema {\n    rankdir=LR;\n    node [shape=plaintext];\n    edge [dir=both];\n",
+        );
+        for table in &self.tables {
+            let _ = write!(
+                out,
+                "    {} [label=<<table border=\"0\" cellborder=\"1\" cellspacing=\"0\">\
+                 <tr><td bgcolor=\"lightgrey\"><b>{}</b></td></tr>",
+                dot_id(&table.name),
+                html_escape(&table.name)
+            );
+            for column in &table.columns {
+                let name = if column.is_primary_key {
+                    format!("<u>{}</u>", html_escape(&column.name))
+                } else {
+                    html_escape(&column.name)
+                };
+                let _ = write!(
+                    out,
+                    "<tr><td port=\"{}\" align=\"left\">{} : {}{}</td></tr>",
+                    html_escape(&column.name),
+                    name,
+                    html_escape(&column.data_type),
+                    if column.is_foreign_key { " (FK)" } else { "" }
+                );
+            }
+            out.push_str("</table>>];\n");
+        }
+        for r in self.internal_relationships() {
+            // Crow's foot ends: the tail sits on the child, the head on the parent
+            let tail = if r.one_to_one { "teeodot" } else { "crowodot" };
+            let head = if r.optional { "teeodot" } else { "teetee" };
+            let _ = writeln!(
+                out,
+                "    {}:{} -> {}:{} [arrowtail={}, arrowhead={}, label={}];",
+                dot_id(&r.from_table),
+                dot_id(r.from_columns.first().map_or("", |c| c.as_str())),
+                dot_id(&r.to_table),
+                dot_id(r.to_columns.first().map_or("", |c| c.as_str())),
+                tail,
+                head,
+                dot_id(&r.constraint_name)
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_plantuml(&self) -> String {
+        let mut out = String::from("@startuml\nhide circle\nskinparam linetype ortho\n\n");
+        // Table names may not be valid PlantUML identifiers, so each gets an alias
+        let alias = |name: &str| {
+            self.tables
+                .iter()
+                .position(|t| t.name == name)
+                .map(|i| format!("t{}", i))
+        };
+        for (i, table) in self.tables.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "entity \"{}\" as t{} {{",
+                plantuml_text(&table.name),
+                i
+            );
+            let (keys, others): (Vec<&GraphColumn>, Vec<&GraphColumn>) =
+                table.columns.iter().partition(|c| c.is_primary_key);
+            for column in &keys {
+                out.push_str(&plantuml_column(column));
+            }
+            out.push_str("  --\n");
+            for column in &others {
+                out.push_str(&plantuml_column(column));
+            }
+            out.push_str("}\n\n");
+        }
+        for r in self.internal_relationships() {
+            let (Some(child), Some(parent)) = (alias(&r.from_table), alias(&r.to_table)) else {
+                continue;
+            };
+            let child_end = if r.one_to_one { "|o" } else { "}o" };
+            let parent_end = if r.optional { "o|" } else { "||" };
+            let _ = writeln!(
+                out,
+                "{} {}--{} {} : {}",
+                child,
+                child_end,
+                parent_end,
+                parent,
+                plantuml_text(&r.constraint_name)
+            );
+        }
+        out.push_str("@enduml\n");
+        out
+    }
+}
+
+/// Mermaid names and types are bare words, so anything else becomes `_`.
+fn mermaid_word(text: &str) -> String {
+    let word: String = text
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '_' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if word.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", word)
+    } else {
+        word
+    }
+}
+
+fn dot_id(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// PlantUML reads names as Creole markup up to the end of the line, so tags,
+/// braces and doubled markup characters (`__`, `**`, `//`, `--`) are escaped
+/// with `~` and line breaks flattened. Quotes can't be escaped inside a quoted
+/// name and become apostrophes.
+fn plantuml_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => escaped.push('\''),
+            c if c.is_control() => escaped.push(' '),
+            '~' | '<' | '>' | '{' | '}' | '[' | ']' => {
+                escaped.push('~');
+                escaped.push(c);
+            }
+            '_' | '*' | '/' | '-' if chars.peek() == Some(&c) => {
+                escaped.push('~');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn plantuml_column(column: &GraphColumn) -> String {
+    let mut line = format!(
+        "  {}{} : {}",
+        if column.is_nullable { "" } else { "* " },
+        plantuml_text(&column.name),
+        plantuml_text(&column.data_type)
+    );
+    if column.is_primary_key {
+        line.push_str(" <<PK>>");
+    }
+    if column.is_foreign_key {
+        line.push_str(" <<FK>>");
+    }
+    line.push('\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(
+        name: &str,
+        data_type: &str,
+        is_primary_key: bool,
+        is_foreign_key: bool,
+    ) -> GraphColumn {
+        GraphColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            is_nullable: !is_primary_key,
+            is_primary_key,
+            is_foreign_key,
+        }
+    }
+
+    fn relationship(to_schema: Option<&str>, to_table: &str) -> GraphRelationship {
+        GraphRelationship {
+            constraint_name: "order_\"fk\"".to_string(),
+            from_table: "order items".to_string(),
+            from_columns: vec!["order id".to_string()],
+            to_schema: to_schema.map(str::to_string),
+            to_table: to_table.to_string(),
+            to_columns: vec!["id".to_string()],
+            optional: true,
+            one_to_one: false,
+        }
+    }
+
+    fn graph() -> SchemaGraph {
+        SchemaGraph {
+            schema: Some("public".to_string()),
+            tables: vec![
+                GraphTable {
+                    name: "order items".to_string(),
+                    columns: vec![
+                        column("id", "integer", true, false),
+                        column("order id", "integer", false, true),
+                        column("<note>", "character varying(10)", false, false),
+                    ],
+                },
+                GraphTable {
+                    name: "orders".to_string(),
+                    columns: vec![column("id", "integer", true, false)],
+                },
+            ],
+            relationships: vec![
+                relationship(None, "orders"),
+                // Same name in another schema, not drawn
+                relationship(Some("archive"), "orders"),
+            ],
+        }
+    }
+
+    #[test]
+    fn renders_mermaid_with_bare_words() {
+        assert_eq!(
+            graph().to_mermaid(),
+            "erDiagram\n\
+             \x20   order_items {\n\
+             \x20       integer id PK\n\
+             \x20       integer order_id FK\n\
+             \x20       character_varying_10_ _note_\n\
+             \x20   }\n\
+             \x20   orders {\n\
+             \x20       integer id PK\n\
+             \x20   }\n\
+             \x20   orders |o--o{ order_items : \"order_'fk'\"\n"
+        );
+    }
+
+    #[test]
+    fn makes_mermaid_words_start_with_a_letter() {
+        assert_eq!(mermaid_word("2024_sales"), "_2024_sales");
+        assert_eq!(mermaid_word("naïve-name"), "naïve-name");
+        assert_eq!(mermaid_word("a.b c"), "a_b_c");
+    }
+
+    #[test]
+    fn quotes_dot_identifiers_and_escapes_labels() {
+        let dot = graph().to_dot();
+        assert!(dot.contains(
+            "    \"order items\" [label=<<table border=\"0\" cellborder=\"1\" \
+             cellspacing=\"0\"><tr><td bgcolor=\"lightgrey\"><b>order items</b></td></tr>"
+        ));
+        assert!(dot.contains(
+            "<tr><td port=\"&lt;note&gt;\" align=\"left\">&lt;note&gt; : \
+             character varying(10)</td></tr>"
+        ));
+        assert!(dot.contains(
+            "    \"order items\":\"order id\" -> \"orders\":\"id\" \
+             [arrowtail=crowodot, arrowhead=teeodot, label=\"order_\\\"fk\\\"\"];\n"
+        ));
+        assert_eq!(dot.matches(" -> ").count(), 1);
+        assert_eq!(dot_id("C:\\dir"), "\"C:\\\\dir\"");
+    }
+
+    #[test]
+    fn escapes_plantuml_markup() {
+        assert_eq!(plantuml_text("a__b"), "a~__b");
+        assert_eq!(plantuml_text("<b>{x}</b>"), "~<b~>~{x~}~</b~>");
+        assert_eq!(plantuml_text("say \"hi\"\nnow"), "say 'hi' now");
+        assert_eq!(plantuml_text("a_b-c"), "a_b-c");
+    }
+
+    #[test]
+    fn renders_plantuml_with_table_aliases() {
+        let plantuml = graph().to_plantuml();
+        assert!(
+            plantuml.contains("entity \"order items\" as t0 {\n  * id : integer <<PK>>\n  --\n")
+        );
+        assert!(plantuml.contains("  ~<note~> : character varying(10)\n"));
+        assert!(plantuml.contains("t0 }o--o| t1 : order_'fk'\n"));
+        assert_eq!(plantuml.matches("--o|").count(), 1);
+    }
+}
@@ -0,0 +1,406 @@
+use crate::db::{Filter, QueryStreamEvent, Sort};
+use crate::dml::{quote_identifier, render_literal};
+use crate::export_parquet::ParquetOutput;
+use crate::export_xlsx::XlsxOutput;
+use crate::sql_splitter::SqlDialect;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Rows to export: a table, filtered and sorted like `get_table_data`, or the
+/// result of a query.
+#[derive(serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExportSource {
+    Table {
+        schema: Option<String>,
+        table_name: String,
+        #[serde(default)]
+        filters: Vec<Filter>,
+        #[serde(default)]
+        sorts: Vec<Sort>,
+    },
+    Query {
+        query: String,
+        /// Runs the query inside an open transaction, so uncommitted rows are included.
+        session_id: Option<String>,
+    },
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv(CsvOptions),
+    /// A single JSON array of objects keyed by column name.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    /// `INSERT` statements into `table_name`, which defaults to the exported table.
+    SqlInsert {
+        table_name: Option<String>,
+        #[serde(default = "default_rows_per_statement")]
+        rows_per_statement: usize,
+    },
+    /// A single worksheet with typed number, date and boolean cells.
+    Xlsx,
+    /// Apache Parquet, with a schema derived from the column types.
+    Parquet,
+}
+
+fn default_rows_per_statement() -> usize {
+    1
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub quote: char,
+    pub header: bool,
+    /// Quotes every field instead of only those containing a delimiter, quote or newline.
+    pub quote_all: bool,
+    /// Written for NULL, which is otherwise indistinguishable from an empty string.
+    pub null_value: String,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: '"',
+            header: true,
+            quote_all: false,
+            null_value: String::new(),
+        }
+    }
+}
+
+/// Events pushed to the frontend while an export is running.
+#[derive(serde::Serialize, Clone)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum ExportEvent {
+    Progress { rows_written: usize },
+    Finished { rows_written: usize },
+}
+
+/// How a column's values are typed in XLSX cells and Parquet columns, derived
+/// from the driver type names that `row_to_values` decodes by.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColumnKind {
+    Boolean,
+    Integer,
+    UnsignedInteger,
+    Float,
+    Date,
+    Time,
+    DateTime,
+    /// A timestamp with time zone, decoded as UTC.
+    Timestamp,
+    Text,
+}
+
+impl ColumnKind {
+    pub fn from_type_name(type_name: &str) -> Self {
+        match type_name.to_ascii_uppercase().as_str() {
+            "BOOL" | "BOOLEAN" | "TINYINT(1)" => ColumnKind::Boolean,
+            "INT2" | "INT4" | "INT8" | "INT" | "INTEGER" | "BIGINT" | "TINYINT" | "SMALLINT"
+            | "MEDIUMINT" | "INT UNSIGNED" | "INTEGER UNSIGNED" | "SMALLINT UNSIGNED"
+            | "TINYINT UNSIGNED" => ColumnKind::Integer,
+            "BIGINT UNSIGNED" => ColumnKind::UnsignedInteger,
+            "FLOAT4" | "FLOAT8" | "REAL" | "DOUBLE PRECISION" | "FLOAT" | "DOUBLE" => {
+                ColumnKind::Float
+            }
+            "DATE" => ColumnKind::Date,
+            "TIME" => ColumnKind::Time,
+            "TIMESTAMP" | "DATETIME" => ColumnKind::DateTime,
+            "TIMESTAMPTZ" => ColumnKind::Timestamp,
+            _ => ColumnKind::Text,
+        }
+    }
+
+    /// Reads a streamed value as this kind, or None when it doesn't fit.
+    pub fn convert(self, value: &Value) -> Option<TypedValue> {
+        if value.is_null() {
+            return Some(TypedValue::Null);
+        }
+        let text = value.as_str();
+        let typed = match self {
+            ColumnKind::Boolean => TypedValue::Boolean(match value {
+                Value::Bool(b) => *b,
+                Value::Number(n) => n.as_i64()? != 0,
+                _ => text?.parse().ok()?,
+            }),
+            ColumnKind::Integer => TypedValue::Integer(match value {
+                Value::Number(n) => n.as_i64()?,
+                _ => text?.parse().ok()?,
+            }),
+            ColumnKind::UnsignedInteger => TypedValue::UnsignedInteger(match value {
+                Value::Number(n) => n.as_u64()?,
+                _ => text?.parse().ok()?,
+            }),
+            ColumnKind::Float => TypedValue::Float(match value {
+                Value::Number(n) => n.as_f64()?,
+                _ => text?.parse().ok()?,
+            }),
+            ColumnKind::Date => TypedValue::Date(text?.parse().ok()?),
+            ColumnKind::Time => TypedValue::Time(text?.parse().ok()?),
+            ColumnKind::DateTime => TypedValue::DateTime(parse_datetime(text?)?),
+            ColumnKind::Timestamp => TypedValue::DateTime(
+                chrono::DateTime::parse_from_rfc3339(text?)
+                    .ok()?
+                    .naive_utc(),
+            ),
+            ColumnKind::Text => TypedValue::Text(match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            }),
+        };
+        Some(typed)
+    }
+}
+
+/// A streamed value read as its column's kind.
+pub enum TypedValue {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    UnsignedInteger(u64),
+    Float(f64),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    /// Timestamps with time zone are held in UTC.
+    DateTime(NaiveDateTime),
+    Text(String),
+}
+
+/// Accepts both chrono's `T`-separated form and SQLite's space-separated text.
+fn parse_datetime(text: &str) -> Option<NaiveDateTime> {
+    text.parse()
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f").ok())
+}
+
+enum Output {
+    Text(BufWriter<File>),
+    Xlsx(Box<XlsxOutput>),
+    Parquet(Box<ParquetOutput>),
+}
+
+/// Writes streamed query events to a file in one of the export formats.
+pub struct ExportWriter {
+    output: Output,
+    format: ExportFormat,
+    dialect: SqlDialect,
+    /// Quoted, possibly schema-qualified target of `INSERT` statements.
+    insert_target: String,
+    columns: Vec<String>,
+    rows_written: usize,
+}
+
+impl ExportWriter {
+    pub fn create(
+        path: &str,
+        format: ExportFormat,
+        dialect: SqlDialect,
+        insert_target: String,
+    ) -> io::Result<Self> {
+        let output = match format {
+            ExportFormat::Xlsx => Output::Xlsx(Box::new(XlsxOutput::create(path)?)),
+            ExportFormat::Parquet => Output::Parquet(Box::new(ParquetOutput::create(path)?)),
+            _ => {
+                let mut out = BufWriter::new(File::create(path)?);
+                if let ExportFormat::Json = format {
+                    out.write_all(b"[")?;
+                }
+                Output::Text(out)
+            }
+        };
+        Ok(Self {
+            output,
+            format,
+            dialect,
+            insert_target,
+            columns: Vec::new(),
+            rows_written: 0,
+        })
+    }
+
+    pub fn rows_written(&self) -> usize {
+        self.rows_written
+    }
+
+    pub fn write_event(&mut self, event: QueryStreamEvent) -> io::Result<()> {
+        match event {
+            QueryStreamEvent::Columns { columns, types } => {
+                let kinds: Vec<ColumnKind> = types
+                    .iter()
+                    .map(|t| ColumnKind::from_type_name(t))
+                    .collect();
+                match &mut self.output {
+                    Output::Xlsx(xlsx) => xlsx.write_header(&columns, kinds)?,
+                    Output::Parquet(parquet) => parquet.start(&columns, kinds)?,
+                    Output::Text(out) => {
+                        if let ExportFormat::Csv(options) = &self.format {
+                            if options.header {
+                                let line =
+                                    csv_line(options, columns.iter().map(|c| Some(c.clone())));
+                                out.write_all(line.as_bytes())?;
+                            }
+                        }
+                    }
+                }
+                self.columns = columns;
+                Ok(())
+            }
+            QueryStreamEvent::Rows { rows } => {
+                match &mut self.output {
+                    Output::Xlsx(xlsx) => xlsx.write_rows(&rows)?,
+                    Output::Parquet(parquet) => parquet.write_rows(&rows)?,
+                    Output::Text(out) => write_text_rows(
+                        out,
+                        &self.format,
+                        self.dialect,
+                        &self.insert_target,
+                        &self.columns,
+                        self.rows_written,
+                        &rows,
+                    )?,
+                }
+                self.rows_written += rows.len();
+                Ok(())
+            }
+            QueryStreamEvent::Finished { .. } => Ok(()),
+        }
+    }
+
+    /// Completes the file (closing a JSON array, writing the XLSX workbook or the
+    /// Parquet footer) and returns the number of rows written.
+    pub fn finish(self) -> io::Result<usize> {
+        match self.output {
+            Output::Xlsx(xlsx) => xlsx.finish()?,
+            Output::Parquet(parquet) => parquet.finish()?,
+            Output::Text(mut out) => {
+                if let ExportFormat::Json = self.format {
+                    let end: &[u8] = if self.rows_written == 0 {
+                        b"]\n"
+                    } else {
+                        b"\n]\n"
+                    };
+                    out.write_all(end)?;
+                }
+                out.flush()?;
+            }
+        }
+        Ok(self.rows_written)
+    }
+}
+
+fn write_text_rows(
+    out: &mut BufWriter<File>,
+    format: &ExportFormat,
+    dialect: SqlDialect,
+    insert_target: &str,
+    columns: &[String],
+    rows_written: usize,
+    rows: &[Vec<Value>],
+) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv(options) => {
+            for row in rows {
+                let line = csv_line(options, row.iter().map(csv_value));
+                out.write_all(line.as_bytes())?;
+            }
+        }
+        ExportFormat::Json => {
+            for (i, row) in rows.iter().enumerate() {
+                let separator = if rows_written + i == 0 {
+                    "\n  "
+                } else {
+                    ",\n  "
+                };
+                out.write_all(separator.as_bytes())?;
+                out.write_all(json_object(columns, row).as_bytes())?;
+            }
+        }
+        ExportFormat::Ndjson => {
+            for row in rows {
+                out.write_all(json_object(columns, row).as_bytes())?;
+                out.write_all(b"\n")?;
+            }
+        }
+        ExportFormat::SqlInsert {
+            rows_per_statement, ..
+        } => {
+            let columns: Vec<String> = columns
+                .iter()
+                .map(|c| quote_identifier(dialect, c))
+                .collect();
+            for chunk in rows.chunks((*rows_per_statement).max(1)) {
+                let values: Vec<String> = chunk
+                    .iter()
+                    .map(|row| {
+                        let literals: Vec<String> = row
+                            .iter()
+                            .map(|value| render_literal(dialect, value))
+                            .collect();
+                        format!("({})", literals.join(", "))
+                    })
+                    .collect();
+                let separator = if values.len() == 1 { " " } else { "\n  " };
+                writeln!(
+                    out,
+                    "INSERT INTO {} ({}) VALUES{}{};",
+                    insert_target,
+                    columns.join(", "),
+                    separator,
+                    values.join(&format!(",{}", separator))
+                )?;
+            }
+        }
+        ExportFormat::Xlsx | ExportFormat::Parquet => {}
+    }
+    Ok(())
+}
+
+fn csv_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn csv_line(options: &CsvOptions, fields: impl Iterator<Item = Option<String>>) -> String {
+    let quote = options.quote.to_string();
+    let fields: Vec<String> = fields
+        .map(|field| match field {
+            None => options.null_value.clone(),
+            Some(text) => {
+                let needs_quotes = options.quote_all
+                    || text.contains(options.delimiter)
+                    || text.contains(options.quote)
+                    || text.contains(['\n', '\r']);
+                if needs_quotes {
+                    let escaped = text.replace(&quote, &quote.repeat(2));
+                    format!("{}{}{}", quote, escaped, quote)
+                } else {
+                    text
+                }
+            }
+        })
+        .collect();
+    let mut line = fields.join(&options.delimiter.to_string());
+    line.push('\n');
+    line
+}
+
+/// Serializes a row as an object, keeping the result's column order.
+fn json_object(columns: &[String], row: &[Value]) -> String {
+    let fields: Vec<String> = columns
+        .iter()
+        .zip(row)
+        .map(|(column, value)| format!("{}:{}", Value::String(column.clone()), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
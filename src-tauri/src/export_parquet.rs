@@ -0,0 +1,205 @@
+use crate::export::{ColumnKind, TypedValue};
+use arrow_array::{
+    ArrayRef, BooleanArray, Date32Array, Float64Array, Int64Array, RecordBatch, StringArray,
+    Time64MicrosecondArray, TimestampMicrosecondArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::Timelike;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use serde_json::Value;
+use std::fs::File;
+use std::io;
+use std::sync::Arc;
+
+/// Rows buffered before a row group is flushed to the file.
+const ROW_GROUP_SIZE: usize = 65_536;
+
+/// A Parquet file whose schema is fixed once the first row group is buffered.
+/// A column with a value in that group that doesn't fit its type is written as
+/// text instead, the way XLSX falls back per cell. Later rows go out in row
+/// groups of `ROW_GROUP_SIZE` as each fills.
+pub struct ParquetOutput {
+    file: Option<File>,
+    writer: Option<ArrowWriter<File>>,
+    schema: SchemaRef,
+    columns: Vec<String>,
+    kinds: Vec<ColumnKind>,
+    /// Rows held back until the schema is decided.
+    pending: Vec<Vec<Value>>,
+}
+
+impl ParquetOutput {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(Self {
+            file: Some(File::create(path)?),
+            writer: None,
+            schema: Arc::new(Schema::empty()),
+            columns: Vec::new(),
+            kinds: Vec::new(),
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn start(&mut self, columns: &[String], kinds: Vec<ColumnKind>) -> io::Result<()> {
+        self.columns = columns.to_vec();
+        self.kinds = kinds;
+        Ok(())
+    }
+
+    /// Settles the column types against the buffered rows, opens the writer
+    /// and writes those rows out.
+    fn open_writer(&mut self) -> io::Result<()> {
+        let Some(file) = self.file.take() else {
+            return Ok(());
+        };
+        for (i, kind) in self.kinds.iter_mut().enumerate() {
+            let fits = self
+                .pending
+                .iter()
+                .all(|row| kind.convert(row.get(i).unwrap_or(&Value::Null)).is_some());
+            if !fits {
+                *kind = ColumnKind::Text;
+            }
+        }
+        let fields: Vec<Field> = self
+            .columns
+            .iter()
+            .zip(&self.kinds)
+            .map(|(name, kind)| Field::new(name, data_type(*kind), true))
+            .collect();
+        self.schema = Arc::new(Schema::new(fields));
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .build();
+        let writer = ArrowWriter::try_new(file, self.schema.clone(), Some(properties))
+            .map_err(parquet_error)?;
+        self.writer = Some(writer);
+
+        let pending = std::mem::take(&mut self.pending);
+        if !pending.is_empty() {
+            self.write_batch(&pending)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_rows(&mut self, rows: &[Vec<Value>]) -> io::Result<()> {
+        if self.writer.is_some() {
+            return self.write_batch(rows);
+        }
+        self.pending.extend_from_slice(rows);
+        if self.pending.len() >= ROW_GROUP_SIZE {
+            self.open_writer()?;
+        }
+        Ok(())
+    }
+
+    fn write_batch(&mut self, rows: &[Vec<Value>]) -> io::Result<()> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        let mut arrays: Vec<ArrayRef> = Vec::with_capacity(self.kinds.len());
+        for (i, kind) in self.kinds.iter().enumerate() {
+            let mut typed = Vec::with_capacity(rows.len());
+            for row in rows {
+                let value = row.get(i).unwrap_or(&Value::Null);
+                // The schema is already written, so the column can't become text anymore
+                let converted = kind.convert(value).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Column {} has a value that doesn't match the type of its first {} rows: {}",
+                            self.columns.get(i).map_or("", |c| c.as_str()),
+                            ROW_GROUP_SIZE,
+                            value
+                        ),
+                    )
+                })?;
+                typed.push(converted);
+            }
+            arrays.push(to_array(*kind, typed));
+        }
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        writer.write(&batch).map_err(parquet_error)
+    }
+
+    /// Flushes the last row group and writes the footer. A result without
+    /// columns still produces a valid, empty file.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.open_writer()?;
+        if let Some(writer) = self.writer {
+            writer.close().map_err(parquet_error)?;
+        }
+        Ok(())
+    }
+}
+
+fn data_type(kind: ColumnKind) -> DataType {
+    match kind {
+        ColumnKind::Boolean => DataType::Boolean,
+        ColumnKind::Integer => DataType::Int64,
+        ColumnKind::UnsignedInteger => DataType::UInt64,
+        ColumnKind::Float => DataType::Float64,
+        ColumnKind::Date => DataType::Date32,
+        ColumnKind::Time => DataType::Time64(TimeUnit::Microsecond),
+        ColumnKind::DateTime => DataType::Timestamp(TimeUnit::Microsecond, None),
+        ColumnKind::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        ColumnKind::Text => DataType::Utf8,
+    }
+}
+
+/// Builds one column of a record batch. Values have already been converted
+/// to `kind`, so anything else is unreachable and left null.
+fn to_array(kind: ColumnKind, values: Vec<TypedValue>) -> ArrayRef {
+    let values = values.into_iter();
+    match kind {
+        ColumnKind::Boolean => Arc::new(BooleanArray::from_iter(values.map(|v| match v {
+            TypedValue::Boolean(b) => Some(b),
+            _ => None,
+        }))),
+        ColumnKind::Integer => Arc::new(Int64Array::from_iter(values.map(|v| match v {
+            TypedValue::Integer(i) => Some(i),
+            _ => None,
+        }))),
+        ColumnKind::UnsignedInteger => Arc::new(UInt64Array::from_iter(values.map(|v| match v {
+            TypedValue::UnsignedInteger(u) => Some(u),
+            _ => None,
+        }))),
+        ColumnKind::Float => Arc::new(Float64Array::from_iter(values.map(|v| match v {
+            TypedValue::Float(f) => Some(f),
+            _ => None,
+        }))),
+        ColumnKind::Date => Arc::new(Date32Array::from_iter(values.map(|v| match v {
+            TypedValue::Date(d) => Some((d - chrono::NaiveDate::default()).num_days() as i32),
+            _ => None,
+        }))),
+        ColumnKind::Time => Arc::new(Time64MicrosecondArray::from_iter(values.map(|v| match v {
+            TypedValue::Time(t) => Some(
+                t.num_seconds_from_midnight() as i64 * 1_000_000 + t.nanosecond() as i64 / 1_000,
+            ),
+            _ => None,
+        }))),
+        ColumnKind::DateTime | ColumnKind::Timestamp => {
+            let array = TimestampMicrosecondArray::from_iter(values.map(|v| match v {
+                TypedValue::DateTime(dt) => Some(dt.and_utc().timestamp_micros()),
+                _ => None,
+            }));
+            match kind {
+                ColumnKind::Timestamp => Arc::new(array.with_timezone("UTC")),
+                _ => Arc::new(array),
+            }
+        }
+        ColumnKind::Text => Arc::new(StringArray::from_iter(values.map(|v| match v {
+            TypedValue::Text(s) => Some(s),
+            _ => None,
+        }))),
+    }
+}
+
+fn parquet_error(e: ParquetError) -> io::Error {
+    io::Error::other(e.to_string())
+}
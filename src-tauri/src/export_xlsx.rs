@@ -0,0 +1,111 @@
+use crate::export::{ColumnKind, TypedValue};
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use serde_json::Value;
+use std::fs::File;
+use std::io;
+
+/// Excel's sheet size limit, header row included.
+const MAX_ROWS: u32 = 1_048_576;
+/// Excel's cell text limit, in characters.
+const MAX_STRING_LENGTH: usize = 32_767;
+
+/// A single-sheet workbook whose rows are flushed to a temporary file as they
+/// are written, so memory stays flat however many rows are exported.
+pub struct XlsxOutput {
+    workbook: Workbook,
+    path: String,
+    kinds: Vec<ColumnKind>,
+    next_row: u32,
+    header: Format,
+    date: Format,
+    time: Format,
+    datetime: Format,
+}
+
+impl XlsxOutput {
+    pub fn create(path: &str) -> io::Result<Self> {
+        // The workbook is only written on finish, so check the path up front
+        File::create(path)?;
+        let mut workbook = Workbook::new();
+        workbook.add_worksheet_with_constant_memory();
+        Ok(Self {
+            workbook,
+            path: path.to_string(),
+            kinds: Vec::new(),
+            next_row: 0,
+            header: Format::new().set_bold(),
+            date: Format::new().set_num_format("yyyy-mm-dd"),
+            time: Format::new().set_num_format("hh:mm:ss"),
+            datetime: Format::new().set_num_format("yyyy-mm-dd hh:mm:ss"),
+        })
+    }
+
+    pub fn write_header(&mut self, columns: &[String], kinds: Vec<ColumnKind>) -> io::Result<()> {
+        let sheet = self.workbook.worksheet_from_index(0).map_err(xlsx_error)?;
+        for (col, name) in columns.iter().enumerate() {
+            sheet
+                .write_string_with_format(0, col as u16, name, &self.header)
+                .map_err(xlsx_error)?;
+        }
+        sheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+        self.kinds = kinds;
+        self.next_row = 1;
+        Ok(())
+    }
+
+    pub fn write_rows(&mut self, rows: &[Vec<Value>]) -> io::Result<()> {
+        if self.next_row as usize + rows.len() > MAX_ROWS as usize {
+            return Err(io::Error::other(format!(
+                "XLSX sheets are limited to {} rows",
+                MAX_ROWS
+            )));
+        }
+        let sheet = self.workbook.worksheet_from_index(0).map_err(xlsx_error)?;
+        for values in rows {
+            let row = self.next_row;
+            for (col, value) in values.iter().enumerate() {
+                let col = col as u16;
+                let kind = self
+                    .kinds
+                    .get(col as usize)
+                    .copied()
+                    .unwrap_or(ColumnKind::Text);
+                // A value that doesn't fit its column's type is kept as text
+                let typed = kind
+                    .convert(value)
+                    .or_else(|| ColumnKind::Text.convert(value));
+                let written = match typed {
+                    None | Some(TypedValue::Null) => continue,
+                    Some(TypedValue::Boolean(b)) => sheet.write_boolean(row, col, b),
+                    Some(TypedValue::Integer(i)) => sheet.write_number(row, col, i as f64),
+                    Some(TypedValue::UnsignedInteger(u)) => sheet.write_number(row, col, u as f64),
+                    Some(TypedValue::Float(f)) => sheet.write_number(row, col, f),
+                    Some(TypedValue::Date(d)) => {
+                        sheet.write_datetime_with_format(row, col, d, &self.date)
+                    }
+                    Some(TypedValue::Time(t)) => {
+                        sheet.write_datetime_with_format(row, col, t, &self.time)
+                    }
+                    Some(TypedValue::DateTime(dt)) => {
+                        sheet.write_datetime_with_format(row, col, dt, &self.datetime)
+                    }
+                    Some(TypedValue::Text(text)) => {
+                        let text: String = text.chars().take(MAX_STRING_LENGTH).collect();
+                        sheet.write_string(row, col, text)
+                    }
+                };
+                written.map_err(xlsx_error)?;
+            }
+            self.next_row += 1;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.workbook.save(&self.path).map_err(xlsx_error)
+    }
+}
+
+fn xlsx_error(e: XlsxError) -> io::Error {
+    io::Error::other(e.to_string())
+}
@@ -0,0 +1,539 @@
+use crate::database_provider::DatabaseProvider;
+use crate::db::{ColumnDefinition, DatabaseError, DatabaseErrorKind};
+use crate::export::CsvOptions;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+pub const DEFAULT_IMPORT_BATCH_SIZE: usize = 1_000;
+pub const DEFAULT_PREVIEW_ROWS: usize = 50;
+/// Row errors kept for the summary; later ones are only counted.
+const MAX_REPORTED_ERRORS: usize = 1_000;
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum ImportFormat {
+    /// Fields equal to `null_value` are imported as NULL. Without a header
+    /// row the columns are named `column1`, `column2`, ...
+    Csv(CsvOptions),
+    /// One JSON object per line, keyed by column name.
+    Ndjson,
+}
+
+/// Loads the file column `source` into the table column `target`.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ColumnMapping {
+    pub source: String,
+    pub target: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct ImportPreview {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    pub errors: Vec<RowError>,
+}
+
+/// A record that could not be read, converted or inserted. `line` is the
+/// 1-based line of the file the record starts on.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct ImportSummary {
+    pub rows_read: usize,
+    pub rows_imported: u64,
+    pub error_count: usize,
+    /// The first errors, up to a limit; `error_count` has the total.
+    pub errors: Vec<RowError>,
+}
+
+/// Events pushed to the frontend while an import is running.
+#[derive(serde::Serialize, Clone)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum ImportEvent {
+    Progress {
+        rows_read: usize,
+        rows_imported: u64,
+        error_count: usize,
+    },
+}
+
+/// A record with the line it starts on, or the error reading that record.
+type Record = (u64, Result<Map<String, Value>, String>);
+
+/// Records of a CSV or NDJSON file, each as a map from file column to value.
+enum RecordReader {
+    Csv {
+        reader: csv::Reader<File>,
+        headers: Vec<String>,
+        null_value: String,
+        /// The first record, already read to count the columns of a headerless file.
+        pending: Option<csv::StringRecord>,
+    },
+    Ndjson {
+        lines: std::io::Lines<BufReader<File>>,
+        line: u64,
+    },
+}
+
+impl RecordReader {
+    fn open(path: &str, format: &ImportFormat) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        match format {
+            ImportFormat::Csv(options) => {
+                let delimiter = ascii_byte(options.delimiter, "delimiter")?;
+                let quote = ascii_byte(options.quote, "quote")?;
+                let mut reader = csv::ReaderBuilder::new()
+                    .delimiter(delimiter)
+                    .quote(quote)
+                    .has_headers(options.header)
+                    .flexible(true)
+                    .from_reader(file);
+                let (headers, pending) = if options.header {
+                    let headers = reader.headers().map_err(|e| e.to_string())?;
+                    (unique_names(headers.iter()), None)
+                } else {
+                    let mut first = csv::StringRecord::new();
+                    let found = reader.read_record(&mut first).map_err(|e| e.to_string())?;
+                    let headers = (1..=first.len()).map(|i| format!("column{}", i)).collect();
+                    (headers, found.then_some(first))
+                };
+                Ok(RecordReader::Csv {
+                    reader,
+                    headers,
+                    null_value: options.null_value.clone(),
+                    pending,
+                })
+            }
+            ImportFormat::Ndjson => Ok(RecordReader::Ndjson {
+                lines: BufReader::new(file).lines(),
+                line: 0,
+            }),
+        }
+    }
+
+    /// Column names known before reading any record; empty for NDJSON.
+    fn headers(&self) -> &[String] {
+        match self {
+            RecordReader::Csv { headers, .. } => headers,
+            RecordReader::Ndjson { .. } => &[],
+        }
+    }
+
+    fn next_record(&mut self) -> Option<Record> {
+        match self {
+            RecordReader::Csv {
+                reader,
+                headers,
+                null_value,
+                pending,
+            } => {
+                let record = match pending.take() {
+                    Some(record) => record,
+                    None => {
+                        let mut record = csv::StringRecord::new();
+                        match reader.read_record(&mut record) {
+                            Ok(true) => record,
+                            Ok(false) => return None,
+                            Err(e) => {
+                                let line = e.position().map_or(0, |p| p.line());
+                                return Some((line, Err(e.to_string())));
+                            }
+                        }
+                    }
+                };
+                let line = record.position().map_or(0, |p| p.line());
+                if record.len() != headers.len() {
+                    let message =
+                        format!("Expected {} fields, found {}", headers.len(), record.len());
+                    return Some((line, Err(message)));
+                }
+                let values = headers
+                    .iter()
+                    .zip(record.iter())
+                    .map(|(header, field)| {
+                        let value = if field == null_value {
+                            Value::Null
+                        } else {
+                            Value::String(field.to_string())
+                        };
+                        (header.clone(), value)
+                    })
+                    .collect();
+                Some((line, Ok(values)))
+            }
+            RecordReader::Ndjson { lines, line } => loop {
+                let text = match lines.next()? {
+                    Ok(text) => text,
+                    Err(e) => return Some((*line + 1, Err(e.to_string()))),
+                };
+                *line += 1;
+                if text.trim().is_empty() {
+                    continue;
+                }
+                let record = match serde_json::from_str::<Value>(&text) {
+                    Ok(Value::Object(values)) => Ok(values),
+                    Ok(_) => Err("Expected a JSON object".to_string()),
+                    Err(e) => Err(format!("Invalid JSON: {}", e)),
+                };
+                return Some((*line, record));
+            },
+        }
+    }
+}
+
+fn ascii_byte(c: char, what: &str) -> Result<u8, String> {
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(format!("The CSV {} must be an ASCII character", what))
+    }
+}
+
+/// Header names, with repeats suffixed so each column can be mapped on its own.
+fn unique_names<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    names
+        .map(|name| {
+            let mut unique = name.to_string();
+            let mut n = 2;
+            while !seen.insert(unique.clone()) {
+                unique = format!("{}_{}", name, n);
+                n += 1;
+            }
+            unique
+        })
+        .collect()
+}
+
+/// Reads the first `limit` records of the file, for choosing a column mapping.
+pub fn preview(path: &str, format: &ImportFormat, limit: usize) -> Result<ImportPreview, String> {
+    let mut reader = RecordReader::open(path, format)?;
+    let mut columns = reader.headers().to_vec();
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+
+    while records.len() + errors.len() < limit {
+        let Some((line, record)) = reader.next_record() else {
+            break;
+        };
+        match record {
+            Ok(record) => {
+                // NDJSON objects need not share keys; show every key seen
+                for key in record.keys() {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+                records.push(record);
+            }
+            Err(message) => errors.push(RowError { line, message }),
+        }
+    }
+
+    let rows = records
+        .iter()
+        .map(|record| {
+            columns
+                .iter()
+                .map(|c| record.get(c).cloned().unwrap_or(Value::Null))
+                .collect()
+        })
+        .collect();
+    Ok(ImportPreview {
+        columns,
+        rows,
+        errors,
+    })
+}
+
+/// The kinds of target column a file value is converted for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum TargetType {
+    Boolean,
+    Integer,
+    Float,
+    /// Exact numbers, kept as text so no precision is lost.
+    Decimal,
+    Json,
+    /// Anything else, including dates and times, which the database parses itself.
+    Text,
+}
+
+impl TargetType {
+    /// Classifies the `data_type` of `get_table_structure`, e.g. `integer`,
+    /// `int(11) unsigned`, `numeric(10,2)` or `VARCHAR(20)`.
+    fn from_data_type(data_type: &str) -> Self {
+        let lower = data_type.to_ascii_lowercase();
+        let base = lower.split('(').next().unwrap_or_default().trim();
+        let first_word = base.split_whitespace().next().unwrap_or_default();
+        for name in [base, first_word] {
+            let target = match name {
+                "bool" | "boolean" => TargetType::Boolean,
+                "smallint" | "integer" | "int" | "bigint" | "tinyint" | "mediumint" | "int2"
+                | "int4" | "int8" | "smallserial" | "serial" | "bigserial" => TargetType::Integer,
+                "real" | "double precision" | "double" | "float" | "float4" | "float8" => {
+                    TargetType::Float
+                }
+                "numeric" | "decimal" => TargetType::Decimal,
+                "json" | "jsonb" => TargetType::Json,
+                _ => continue,
+            };
+            return target;
+        }
+        TargetType::Text
+    }
+
+    fn convert(self, value: Value) -> Result<Value, String> {
+        let text = match value {
+            Value::Null => return Ok(Value::Null),
+            Value::String(ref s) => s.trim().to_string(),
+            ref other => other.to_string(),
+        };
+        match (self, value) {
+            (TargetType::Boolean, Value::Bool(b)) => Ok(Value::Bool(b)),
+            (TargetType::Boolean, _) => match text.to_ascii_lowercase().as_str() {
+                "true" | "t" | "yes" | "y" | "on" | "1" => Ok(Value::Bool(true)),
+                "false" | "f" | "no" | "n" | "off" | "0" => Ok(Value::Bool(false)),
+                _ => Err(format!("'{}' is not a boolean", text)),
+            },
+            (TargetType::Integer, Value::Bool(b)) => Ok(Value::from(b as i64)),
+            (TargetType::Integer, _) => text
+                .parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| format!("'{}' is not an integer", text)),
+            (TargetType::Float, _) => match text.parse::<f64>() {
+                Ok(f) if f.is_finite() => Ok(Value::from(f)),
+                _ => Err(format!("'{}' is not a number", text)),
+            },
+            (TargetType::Decimal, _) => match text.parse::<f64>() {
+                Ok(f) if f.is_finite() => Ok(Value::String(text)),
+                _ => Err(format!("'{}' is not a number", text)),
+            },
+            (TargetType::Json, Value::String(s)) => serde_json::from_str::<Value>(&s)
+                .map(|_| Value::String(s))
+                .map_err(|e| format!("Invalid JSON: {}", e)),
+            (TargetType::Json, other) => Ok(Value::String(other.to_string())),
+            (TargetType::Text, Value::String(s)) => Ok(Value::String(s)),
+            (TargetType::Text, other) => Ok(Value::String(other.to_string())),
+        }
+    }
+}
+
+/// Imports the file into the table in transactions of `batch_size` rows.
+/// When a batch fails its rows are retried one at a time, so a bad row is
+/// reported and skipped without losing the rest of its batch. An NDJSON object
+/// without a mapped key is such a row. If `on_event` asks to stop, the import
+/// ends with a cancelled error; batches already committed stay in the table.
+#[allow(clippy::too_many_arguments)]
+pub async fn import_file(
+    provider: &dyn DatabaseProvider,
+    schema: Option<String>,
+    table_name: String,
+    path: &str,
+    format: &ImportFormat,
+    mapping: &[ColumnMapping],
+    batch_size: usize,
+    on_event: &(dyn Fn(ImportEvent) -> bool + Send + Sync),
+) -> Result<ImportSummary, DatabaseError> {
+    if mapping.is_empty() {
+        return Err(DatabaseError::new("Map at least one column to import"));
+    }
+    let structure = provider
+        .get_table_structure(schema.clone(), table_name.clone())
+        .await?;
+    let targets = target_types(&structure, mapping).map_err(DatabaseError::new)?;
+
+    let mut reader = RecordReader::open(path, format).map_err(DatabaseError::new)?;
+    if let ImportFormat::Csv(_) = format {
+        if let Some(missing) = mapping
+            .iter()
+            .find(|m| !reader.headers().contains(&m.source))
+        {
+            return Err(DatabaseError::new(format!(
+                "The file has no column {}",
+                missing.source
+            )));
+        }
+    }
+
+    let columns: Vec<String> = mapping.iter().map(|m| m.target.clone()).collect();
+    let batch_size = batch_size.max(1);
+    let mut summary = ImportSummary {
+        rows_read: 0,
+        rows_imported: 0,
+        error_count: 0,
+        errors: Vec::new(),
+    };
+
+    loop {
+        let mut lines = Vec::with_capacity(batch_size);
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut finished = false;
+        let rows_read_before = summary.rows_read;
+        while batch.len() < batch_size {
+            let Some((line, record)) = reader.next_record() else {
+                finished = true;
+                break;
+            };
+            summary.rows_read += 1;
+            match record.and_then(|mut record| convert_row(&mut record, mapping, &targets)) {
+                Ok(row) => {
+                    lines.push(line);
+                    batch.push(row);
+                }
+                Err(message) => summary.record_error(line, message),
+            }
+        }
+
+        if !batch.is_empty() {
+            insert_batch(
+                provider,
+                &schema,
+                &table_name,
+                &columns,
+                lines,
+                batch,
+                &mut summary,
+            )
+            .await?;
+        }
+
+        let keep_going = summary.rows_read == rows_read_before
+            || on_event(ImportEvent::Progress {
+                rows_read: summary.rows_read,
+                rows_imported: summary.rows_imported,
+                error_count: summary.error_count,
+            });
+        if !keep_going {
+            return Err(DatabaseError::with_kind(
+                DatabaseErrorKind::Cancelled,
+                format!(
+                    "Import was cancelled after {} rows were imported",
+                    summary.rows_imported
+                ),
+            ));
+        }
+        if finished {
+            // Rows retried after a failed batch are reported after later rows
+            summary.errors.sort_by_key(|e| e.line);
+            return Ok(summary);
+        }
+    }
+}
+
+impl ImportSummary {
+    fn record_error(&mut self, line: u64, message: String) {
+        self.error_count += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(RowError { line, message });
+        }
+    }
+}
+
+fn target_types(
+    structure: &[ColumnDefinition],
+    mapping: &[ColumnMapping],
+) -> Result<Vec<TargetType>, String> {
+    let mut seen = HashSet::new();
+    mapping
+        .iter()
+        .map(|m| {
+            if !seen.insert(&m.target) {
+                return Err(format!("Column {} is mapped more than once", m.target));
+            }
+            structure
+                .iter()
+                .find(|c| c.column_name == m.target)
+                .map(|c| TargetType::from_data_type(&c.data_type))
+                .ok_or_else(|| format!("The table has no column {}", m.target))
+        })
+        .collect()
+}
+
+fn convert_row(
+    record: &mut Map<String, Value>,
+    mapping: &[ColumnMapping],
+    targets: &[TargetType],
+) -> Result<Vec<Value>, String> {
+    mapping
+        .iter()
+        .zip(targets)
+        .map(|(m, target)| {
+            // An explicit null imports as NULL, but a missing key is more
+            // likely a misspelled mapping than a value left out
+            let value = record
+                .remove(&m.source)
+                .ok_or_else(|| format!("The record has no key {}", m.source))?;
+            target
+                .convert(value)
+                .map_err(|e| format!("{}: {}", m.target, e))
+        })
+        .collect()
+}
+
+async fn insert_batch(
+    provider: &dyn DatabaseProvider,
+    schema: &Option<String>,
+    table_name: &str,
+    columns: &[String],
+    lines: Vec<u64>,
+    batch: Vec<Vec<Value>>,
+    summary: &mut ImportSummary,
+) -> Result<(), DatabaseError> {
+    let single_row = batch.len() == 1;
+    let result = provider
+        .bulk_insert(
+            schema.clone(),
+            table_name.to_string(),
+            columns.to_vec(),
+            batch.clone(),
+        )
+        .await;
+    match result {
+        Ok(inserted) => {
+            summary.rows_imported += inserted;
+            return Ok(());
+        }
+        Err(e) if is_fatal(&e) => return Err(e),
+        Err(e) if single_row => {
+            summary.record_error(lines[0], e.message);
+            return Ok(());
+        }
+        Err(_) => {}
+    }
+
+    for (line, row) in lines.into_iter().zip(batch) {
+        let result = provider
+            .bulk_insert(
+                schema.clone(),
+                table_name.to_string(),
+                columns.to_vec(),
+                vec![row],
+            )
+            .await;
+        match result {
+            Ok(inserted) => summary.rows_imported += inserted,
+            Err(e) if is_fatal(&e) => return Err(e),
+            Err(e) => summary.record_error(line, e.message),
+        }
+    }
+    Ok(())
+}
+
+/// Errors that no other row would avoid, which end the import.
+fn is_fatal(error: &DatabaseError) -> bool {
+    matches!(
+        error.kind,
+        DatabaseErrorKind::Connection
+            | DatabaseErrorKind::Permission
+            | DatabaseErrorKind::UndefinedObject
+            | DatabaseErrorKind::Cancelled
+            | DatabaseErrorKind::Timeout
+    )
+}
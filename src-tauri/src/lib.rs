@@ -4,14 +4,33 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+pub mod connect_options;
+pub mod connection_bundle;
+pub mod connection_import;
 pub mod connection_manager;
+pub mod credentials;
 pub mod database_provider;
 pub mod db;
+pub mod dml;
+pub mod dump;
+pub mod er_diagram;
+pub mod export;
+pub mod export_parquet;
+pub mod export_xlsx;
+pub mod import;
 pub mod mysql_provider;
+pub mod notices;
+pub mod postgres_ddl;
 pub mod postgres_provider;
+pub mod query_registry;
+pub mod session;
+pub mod socket_relay;
+pub mod sql_splitter;
+pub mod sqlite_provider;
 pub mod ssh_tunnel;
 pub mod state;
 
+use credentials::CredentialStore;
 use db::AppState;
 use std::collections::HashMap;
 use std::sync::Mutex;
@@ -24,21 +43,65 @@ pub fn run() {
         .manage(AppState {
             connections: Mutex::new(HashMap::new()),
             tunnels: Mutex::new(HashMap::new()),
+            pending_changes: Mutex::new(HashMap::new()),
         })
+        .manage(CredentialStore::default())
         .invoke_handler(tauri::generate_handler![
             greet,
             db::connect_db,
             db::disconnect_db,
+            db::switch_database,
+            db::get_current_database,
             db::list_databases,
+            db::list_schemas,
             db::list_tables,
+            db::list_objects,
+            db::get_object_ddl,
             db::get_table_data,
             db::get_table_structure,
             db::get_table_indexes,
+            db::get_table_constraints,
+            db::get_incoming_foreign_keys,
+            db::get_related_rows,
+            db::get_schema_graph,
+            db::export_schema_diagram,
+            db::export_data,
+            db::preview_import,
+            db::import_data,
+            db::dump_database,
+            db::restore_database,
+            db::update_rows,
+            db::insert_rows,
+            db::delete_rows,
+            db::stage_row_update,
+            db::stage_row_insert,
+            db::stage_row_delete,
+            db::unstage_change,
+            db::get_pending_changes,
+            db::preview_pending_changes,
+            db::apply_pending_changes,
+            db::discard_pending_changes,
             db::execute_query,
+            db::execute_query_stream,
+            db::execute_script,
+            db::cancel_query,
+            db::begin_transaction,
+            db::commit_transaction,
+            db::rollback_transaction,
+            db::create_savepoint,
+            db::rollback_to_savepoint,
+            db::release_savepoint,
+            db::get_transaction_state,
             db::get_database_schema,
             connection_manager::save_connection,
             connection_manager::load_connections,
             connection_manager::delete_connection,
+            connection_import::import_connections,
+            connection_bundle::export_connections,
+            connection_bundle::import_connection_bundle,
+            credentials::get_credential_store_status,
+            credentials::unlock_credential_store,
+            credentials::lock_credential_store,
             state::save_app_state,
             state::load_app_state
         ])
@@ -1,22 +1,196 @@
 use crate::database_provider::DatabaseProvider;
-use crate::db::{ColumnDefinition, DatabaseError, Filter, IndexDefinition, QueryResult, Sort};
+use crate::db::{
+    collect_results, stream_rows, ColumnDefinition, ConstraintDefinition, ConstraintKind,
+    DatabaseError, DatabaseErrorKind, DatabaseObject, Filter, IncomingForeignKey, IndexDefinition,
+    ObjectKind, QueryResult, QueryStreamEvent, Sort, StatementResult, TableQuery,
+    DEFAULT_STREAM_BATCH_SIZE,
+};
+use crate::dml::{self, qualified_name, quote_identifier, DmlStatement};
+use crate::query_registry::{map_cancelled, QueryGuard, QueryRegistry};
+use crate::session::{Sessions, TransactionState};
+use crate::sql_splitter::{SqlDialect, Statement};
 use async_trait::async_trait;
-use sqlx::mysql::{MySqlPoolOptions, MySqlRow};
-use sqlx::{Column, MySql, Pool, Row, TypeInfo};
+use serde_json::Value;
+use sqlx::mysql::{
+    MySqlArguments, MySqlConnectOptions, MySqlConnection, MySqlDatabaseError, MySqlPoolOptions,
+    MySqlQueryResult, MySqlRow,
+};
+use sqlx::query::Query;
+use sqlx::{Column, Executor, MySql, Pool, Row, Statement as _, TypeInfo};
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
 
 pub struct MysqlProvider {
-    pool: Pool<MySql>,
+    /// Swapped out when switching databases.
+    pool: RwLock<Pool<MySql>>,
+    /// Set on every new connection, including those of a switched pool.
+    session_variables: Arc<Vec<(String, String)>>,
+    sessions: Sessions<MySql>,
+    running: QueryRegistry,
 }
 
 impl MysqlProvider {
-    pub async fn new(connection_string: &str) -> Result<Self, DatabaseError> {
-        let pool = MySqlPoolOptions::new()
-            .max_connections(5)
-            .connect(connection_string)
+    pub async fn new(
+        options: MySqlConnectOptions,
+        session_variables: Vec<(String, String)>,
+    ) -> Result<Self, DatabaseError> {
+        let session_variables = Arc::new(session_variables);
+        let pool = pool_options(&session_variables)
+            .connect_with(options)
             .await
             .map_err(DatabaseError::from)?;
-        Ok(Self { pool })
+        Ok(Self {
+            pool: RwLock::new(pool),
+            session_variables,
+            sessions: Sessions::default(),
+            running: QueryRegistry::default(),
+        })
+    }
+
+    fn pool(&self) -> Pool<MySql> {
+        self.pool.read().unwrap().clone()
+    }
+
+    /// Records which backend runs the query so `cancel_query` can reach it.
+    async fn track_query(
+        &self,
+        conn: &mut MySqlConnection,
+        query_id: Option<String>,
+    ) -> Result<Option<QueryGuard<'_>>, DatabaseError> {
+        let Some(query_id) = query_id else {
+            return Ok(None);
+        };
+        let backend_id: u64 = sqlx::query_scalar("SELECT CONNECTION_ID()")
+            .fetch_one(conn)
+            .await
+            .map_err(DatabaseError::from)?;
+        self.running
+            .register(&query_id, backend_id)
+            .map(Some)
+            .ok_or_else(|| {
+                DatabaseError::new(format!("A query with id {} is already running", query_id))
+            })
+    }
+
+    /// The FROM/WHERE and ORDER BY parts shared by paging and exporting a table.
+    fn table_query(
+        &self,
+        schema: Option<&str>,
+        table_name: &str,
+        filters: &[Filter],
+        sorts: &[Sort],
+    ) -> TableQuery {
+        let table = qualified_name(SqlDialect::Mysql, schema, table_name);
+
+        // MySQL doesn't need explicit casting as much as Postgres, but we might need some.
+        // For now, let's try without complex type mapping for filters.
+
+        let mut where_clauses = Vec::new();
+        let mut query_params = Vec::new();
+        // MySQL uses ? for parameters
+
+        for filter in filters {
+            let field = quote_identifier(SqlDialect::Mysql, &filter.field);
+
+            match filter.operator.as_str() {
+                "=" | ">=" | "<=" | ">" | "<" => {
+                    where_clauses.push(format!("{} {} ?", field, filter.operator));
+                    query_params.push(filter.value.clone());
+                }
+                "contain" => {
+                    where_clauses.push(format!("{} LIKE ?", field));
+                    query_params.push(format!("%{}%", filter.value));
+                }
+                "start with" => {
+                    where_clauses.push(format!("{} LIKE ?", field));
+                    query_params.push(format!("{}%", filter.value));
+                }
+                "end with" => {
+                    where_clauses.push(format!("{} LIKE ?", field));
+                    query_params.push(format!("%{}", filter.value));
+                }
+                "not null" => {
+                    where_clauses.push(format!("{} IS NOT NULL", field));
+                }
+                "is null" => {
+                    where_clauses.push(format!("{} IS NULL", field));
+                }
+                _ => {}
+            }
+        }
+
+        let where_sql = if where_clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", where_clauses.join(" AND "))
+        };
+
+        let mut order_clauses = Vec::new();
+        for sort in sorts {
+            let direction = if sort.order.to_uppercase() == "DESC" {
+                "DESC"
+            } else {
+                "ASC"
+            };
+            order_clauses.push(format!(
+                "{} {}",
+                quote_identifier(SqlDialect::Mysql, &sort.field),
+                direction
+            ));
+        }
+
+        let order_sql = if order_clauses.is_empty() {
+            String::new()
+        } else {
+            format!("ORDER BY {}", order_clauses.join(", "))
+        };
+
+        TableQuery {
+            from: format!("{} {}", table, where_sql),
+            order_by: order_sql,
+            params: query_params,
+        }
+    }
+}
+
+/// Pool settings that set the session variables on each new connection.
+/// Names were checked to be plain identifiers; numeric values are bound as
+/// numbers since MySQL rejects strings for integer variables.
+fn pool_options(session_variables: &Arc<Vec<(String, String)>>) -> MySqlPoolOptions {
+    let session_variables = session_variables.clone();
+    MySqlPoolOptions::new()
+        .max_connections(5)
+        .after_connect(move |conn, _| {
+            let session_variables = session_variables.clone();
+            Box::pin(async move {
+                for (name, value) in session_variables.iter() {
+                    let sql = format!("SET SESSION {} = ?", name);
+                    let query = sqlx::query(&sql);
+                    let query = match value.parse::<i64>() {
+                        Ok(number) => query.bind(number),
+                        Err(_) => query.bind(value.as_str()),
+                    };
+                    query.execute(&mut *conn).await?;
+                }
+                Ok(())
+            })
+        })
+}
+
+fn bind_value<'q>(
+    query: Query<'q, MySql, MySqlArguments>,
+    value: &Value,
+) -> Query<'q, MySql, MySqlArguments> {
+    match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(b) => query.bind(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => query.bind(i),
+            None => query.bind(n.as_f64()),
+        },
+        Value::String(s) => query.bind(s.clone()),
+        other => query.bind(other.to_string()),
     }
 }
 
@@ -99,11 +273,83 @@ fn row_to_values(row: MySqlRow) -> Vec<serde_json::Value> {
     values
 }
 
+/// MySQL error returned for statements the prepared protocol cannot run.
+const ER_UNSUPPORTED_PS: u16 = 1295;
+
+/// MySQL error for an information_schema table the server doesn't have.
+const ER_UNKNOWN_TABLE: u16 = 1109;
+
+/// Placeholders MySQL allows in one prepared statement.
+const MAX_BIND_PARAMS: usize = 65_535;
+
+/// Runs a single statement, capturing its rows or affected-row count, the
+/// warnings it produced and how long it took.
+async fn run_statement(
+    conn: &mut MySqlConnection,
+    sql: &str,
+) -> Result<QueryResult, DatabaseError> {
+    let started = Instant::now();
+    let summarize = |done: &MySqlQueryResult| (done.rows_affected(), Some(done.last_insert_id()));
+
+    // Preparing first tells us the result columns even when no rows come back.
+    // Some statements (CREATE PROCEDURE, ...) cannot be prepared and are sent as plain text.
+    let mut result = match conn.prepare(sql).await {
+        Ok(statement) => {
+            let columns = statement
+                .columns()
+                .iter()
+                .map(|col| col.name().to_string())
+                .collect();
+            collect_results(
+                conn.fetch_many(statement.query()),
+                columns,
+                row_to_values,
+                summarize,
+            )
+            .await?
+        }
+        Err(sqlx::Error::Database(e))
+            if e.try_downcast_ref::<MySqlDatabaseError>()
+                .is_some_and(|e| e.number() == ER_UNSUPPORTED_PS) =>
+        {
+            collect_results(
+                conn.fetch_many(sqlx::raw_sql(sql)),
+                Vec::new(),
+                row_to_values,
+                summarize,
+            )
+            .await?
+        }
+        Err(e) => return Err(e.into()),
+    };
+    result.execution_time_ms = Some(started.elapsed().as_millis() as u64);
+
+    let warnings = sqlx::query("SHOW WARNINGS")
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(DatabaseError::from)?;
+    result.notices = warnings
+        .iter()
+        .map(|row| {
+            let level: String = row.get("Level");
+            let code: u32 = row.get("Code");
+            let message: String = row.get("Message");
+            format!("{} {}: {}", level, code, message)
+        })
+        .collect();
+
+    Ok(result)
+}
+
 #[async_trait]
 impl DatabaseProvider for MysqlProvider {
+    fn dialect(&self) -> SqlDialect {
+        SqlDialect::Mysql
+    }
+
     async fn list_databases(&self) -> Result<Vec<String>, DatabaseError> {
         let rows = sqlx::query("SHOW DATABASES;")
-            .fetch_all(&self.pool)
+            .fetch_all(&self.pool())
             .await
             .map_err(DatabaseError::from)?;
 
@@ -111,9 +357,21 @@ impl DatabaseProvider for MysqlProvider {
         Ok(databases)
     }
 
-    async fn list_tables(&self) -> Result<Vec<String>, DatabaseError> {
-        let rows = sqlx::query("SHOW TABLES;")
-            .fetch_all(&self.pool)
+    async fn list_schemas(&self) -> Result<Vec<String>, DatabaseError> {
+        // A MySQL schema is a database
+        self.list_databases().await
+    }
+
+    async fn list_tables(&self, schema: Option<String>) -> Result<Vec<String>, DatabaseError> {
+        let query = match &schema {
+            Some(schema) => format!(
+                "SHOW TABLES FROM {};",
+                quote_identifier(SqlDialect::Mysql, schema)
+            ),
+            None => "SHOW TABLES;".to_string(),
+        };
+        let rows = sqlx::query(&query)
+            .fetch_all(&self.pool())
             .await
             .map_err(DatabaseError::from)?;
 
@@ -121,99 +379,124 @@ impl DatabaseProvider for MysqlProvider {
         Ok(tables)
     }
 
+    async fn list_objects(
+        &self,
+        schema: Option<String>,
+    ) -> Result<Vec<DatabaseObject>, DatabaseError> {
+        // information_schema columns come back as binary on some servers
+        let rows = sqlx::query(
+            "SELECT CAST(TABLE_NAME AS CHAR) AS name, CAST(TABLE_TYPE AS CHAR) AS kind,
+                    CAST(NULL AS CHAR) AS table_name
+             FROM information_schema.TABLES
+             WHERE TABLE_SCHEMA = COALESCE(?, DATABASE())
+             UNION ALL
+             SELECT CAST(ROUTINE_NAME AS CHAR), CAST(ROUTINE_TYPE AS CHAR), NULL
+             FROM information_schema.ROUTINES
+             WHERE ROUTINE_SCHEMA = COALESCE(?, DATABASE())
+             UNION ALL
+             SELECT CAST(TRIGGER_NAME AS CHAR), 'TRIGGER', CAST(EVENT_OBJECT_TABLE AS CHAR)
+             FROM information_schema.TRIGGERS
+             WHERE TRIGGER_SCHEMA = COALESCE(?, DATABASE())
+             ORDER BY name;",
+        )
+        .bind(&schema)
+        .bind(&schema)
+        .bind(&schema)
+        .fetch_all(&self.pool())
+        .await
+        .map_err(DatabaseError::from)?;
+
+        let objects = rows
+            .iter()
+            .map(|row: &MySqlRow| {
+                let kind: String = row.get("kind");
+                DatabaseObject {
+                    name: row.get("name"),
+                    kind: match kind.as_str() {
+                        "VIEW" => ObjectKind::View,
+                        // MariaDB only
+                        "SEQUENCE" => ObjectKind::Sequence,
+                        "FUNCTION" => ObjectKind::Function,
+                        "PROCEDURE" => ObjectKind::Procedure,
+                        "TRIGGER" => ObjectKind::Trigger,
+                        _ => ObjectKind::Table,
+                    },
+                    table_name: row.get("table_name"),
+                    arguments: None,
+                }
+            })
+            .collect();
+        Ok(objects)
+    }
+
+    async fn get_object_ddl(
+        &self,
+        schema: Option<String>,
+        object: DatabaseObject,
+    ) -> Result<String, DatabaseError> {
+        // SHOW CREATE puts the statement in the second column, or the third for routines and triggers
+        let (keyword, column) = match object.kind {
+            ObjectKind::Table => ("TABLE", 1),
+            ObjectKind::View => ("VIEW", 1),
+            ObjectKind::Sequence => ("SEQUENCE", 1),
+            ObjectKind::Function => ("FUNCTION", 2),
+            ObjectKind::Procedure => ("PROCEDURE", 2),
+            ObjectKind::Trigger => ("TRIGGER", 2),
+            _ => {
+                return Err(DatabaseError::new(format!(
+                    "MySQL has no {:?} objects",
+                    object.kind
+                )))
+            }
+        };
+        let query = format!(
+            "SHOW CREATE {} {}",
+            keyword,
+            qualified_name(SqlDialect::Mysql, schema.as_deref(), &object.name)
+        );
+        let row = sqlx::query(&query)
+            .fetch_one(&self.pool())
+            .await
+            .map_err(DatabaseError::from)?;
+
+        // NULL when the user lacks the privileges to see a routine's body
+        let ddl: Option<Vec<u8>> = row.try_get(column).map_err(DatabaseError::from)?;
+        let ddl = ddl.ok_or_else(|| {
+            DatabaseError::with_kind(
+                DatabaseErrorKind::Permission,
+                format!("Not allowed to see the definition of {}", object.name),
+            )
+        })?;
+        Ok(format!("{};", String::from_utf8_lossy(&ddl)))
+    }
+
     async fn get_table_data(
         &self,
+        schema: Option<String>,
         table_name: String,
         limit: i64,
         offset: i64,
         filters: Vec<Filter>,
         sorts: Vec<Sort>,
     ) -> Result<QueryResult, DatabaseError> {
-        // MySQL doesn't need explicit casting as much as Postgres, but we might need some.
-        // For now, let's try without complex type mapping for filters.
-
-        let mut where_clauses = Vec::new();
-        let mut query_params = Vec::new();
-        // MySQL uses ? for parameters
-
-        for filter in &filters {
-            if !filter
-                .field
-                .chars()
-                .all(|c| c.is_alphanumeric() || c == '_')
-            {
-                continue;
-            }
-
-            let field = format!("`{}`", filter.field);
+        let TableQuery {
+            from,
+            order_by,
+            params: query_params,
+        } = self.table_query(schema.as_deref(), &table_name, &filters, &sorts);
 
-            match filter.operator.as_str() {
-                "=" | ">=" | "<=" | ">" | "<" => {
-                    where_clauses.push(format!("{} {} ?", field, filter.operator));
-                    query_params.push(filter.value.clone());
-                }
-                "contain" => {
-                    where_clauses.push(format!("{} LIKE ?", field));
-                    query_params.push(format!("%{}%", filter.value));
-                }
-                "start with" => {
-                    where_clauses.push(format!("{} LIKE ?", field));
-                    query_params.push(format!("{}%", filter.value));
-                }
-                "end with" => {
-                    where_clauses.push(format!("{} LIKE ?", field));
-                    query_params.push(format!("%{}", filter.value));
-                }
-                "not null" => {
-                    where_clauses.push(format!("{} IS NOT NULL", field));
-                }
-                "is null" => {
-                    where_clauses.push(format!("{} IS NULL", field));
-                }
-                _ => {}
-            }
-        }
-
-        let where_sql = if where_clauses.is_empty() {
-            String::new()
-        } else {
-            format!("WHERE {}", where_clauses.join(" AND "))
-        };
-
-        let mut order_clauses = Vec::new();
-        for sort in &sorts {
-            if !sort.field.chars().all(|c| c.is_alphanumeric() || c == '_') {
-                continue;
-            }
-            let direction = if sort.order.to_uppercase() == "DESC" {
-                "DESC"
-            } else {
-                "ASC"
-            };
-            order_clauses.push(format!("`{}` {}", sort.field, direction));
-        }
-
-        let order_sql = if order_clauses.is_empty() {
-            String::new()
-        } else {
-            format!("ORDER BY {}", order_clauses.join(", "))
-        };
-
-        let count_query = format!("SELECT COUNT(*) FROM `{}` {}", table_name, where_sql);
+        let count_query = format!("SELECT COUNT(*) FROM {}", from);
         let mut count_q = sqlx::query_as::<_, (i64,)>(&count_query);
         for param in &query_params {
             count_q = count_q.bind(param);
         }
         let count_row = count_q
-            .fetch_one(&self.pool)
+            .fetch_one(&self.pool())
             .await
             .map_err(DatabaseError::from)?;
         let total_rows = count_row.0;
 
-        let query = format!(
-            "SELECT * FROM `{}` {} {} LIMIT ? OFFSET ?",
-            table_name, where_sql, order_sql
-        );
+        let query = format!("SELECT * FROM {} {} LIMIT ? OFFSET ?", from, order_by);
 
         let mut q = sqlx::query(&query);
         for param in &query_params {
@@ -221,7 +504,10 @@ impl DatabaseProvider for MysqlProvider {
         }
         q = q.bind(limit).bind(offset);
 
-        let rows = q.fetch_all(&self.pool).await.map_err(DatabaseError::from)?;
+        let rows = q
+            .fetch_all(&self.pool())
+            .await
+            .map_err(DatabaseError::from)?;
 
         let columns = if let Some(first_row) = rows.first() {
             first_row
@@ -240,36 +526,92 @@ impl DatabaseProvider for MysqlProvider {
             columns,
             rows: result_rows,
             total_rows: Some(total_rows),
+            ..Default::default()
         })
     }
 
+    async fn stream_table_data(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        filters: Vec<Filter>,
+        sorts: Vec<Sort>,
+        query_id: Option<String>,
+        on_event: &(dyn Fn(QueryStreamEvent) -> bool + Send + Sync),
+    ) -> Result<(), DatabaseError> {
+        let TableQuery {
+            from,
+            order_by,
+            params,
+        } = self.table_query(schema.as_deref(), &table_name, &filters, &sorts);
+        let query = format!("SELECT * FROM {} {}", from, order_by);
+
+        let mut conn = self.pool().acquire().await.map_err(DatabaseError::from)?;
+        let guard = self.track_query(&mut conn, query_id).await?;
+
+        let statement = (&mut *conn)
+            .prepare(&query)
+            .await
+            .map_err(DatabaseError::from)?;
+        let mut q = statement.query();
+        for param in &params {
+            q = q.bind(param);
+        }
+        let rows = q.fetch(&mut *conn);
+        stream_rows(
+            rows,
+            statement.columns(),
+            row_to_values,
+            DEFAULT_STREAM_BATCH_SIZE,
+            usize::MAX,
+            on_event,
+        )
+        .await
+        .map_err(|e| map_cancelled(&guard, e))
+    }
+
     async fn get_table_structure(
         &self,
+        schema: Option<String>,
         table_name: String,
     ) -> Result<Vec<ColumnDefinition>, DatabaseError> {
         let query = "
             SELECT 
-                COLUMN_NAME as column_name,
-                COLUMN_TYPE as data_type,
-                IS_NULLABLE as is_nullable,
-                COLUMN_DEFAULT as column_default,
-                COLUMN_COMMENT as comment,
-                CAST(NULL AS CHAR) as foreign_key
-            FROM information_schema.COLUMNS
-            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?
-            ORDER BY ORDINAL_POSITION;
+                c.COLUMN_NAME as column_name,
+                c.COLUMN_TYPE as data_type,
+                c.IS_NULLABLE as is_nullable,
+                c.COLUMN_DEFAULT as column_default,
+                c.COLUMN_COMMENT as comment,
+                (
+                    -- Same form as Postgres: table(column), qualified when in another schema
+                    SELECT CAST(CONCAT(
+                        IF(k.REFERENCED_TABLE_SCHEMA = c.TABLE_SCHEMA, '',
+                           CONCAT(k.REFERENCED_TABLE_SCHEMA, '.')),
+                        k.REFERENCED_TABLE_NAME, '(', k.REFERENCED_COLUMN_NAME, ')'
+                    ) AS CHAR)
+                    FROM information_schema.KEY_COLUMN_USAGE k
+                    WHERE k.TABLE_SCHEMA = c.TABLE_SCHEMA
+                      AND k.TABLE_NAME = c.TABLE_NAME
+                      AND k.COLUMN_NAME = c.COLUMN_NAME
+                      AND k.REFERENCED_TABLE_NAME IS NOT NULL
+                    ORDER BY k.CONSTRAINT_NAME
+                    LIMIT 1
+                ) as foreign_key
+            FROM information_schema.COLUMNS c
+            WHERE c.TABLE_SCHEMA = COALESCE(?, DATABASE()) AND c.TABLE_NAME = ?
+            ORDER BY c.ORDINAL_POSITION;
         ";
 
-        // Note: Foreign key retrieval in MySQL is a bit more complex, often requiring joining KEY_COLUMN_USAGE.
-        // For simplicity in this first pass, we might skip detailed FK info or add it if easy.
-        // Let's try to get basic structure first.
-
         let rows = sqlx::query(query)
+            .bind(&schema)
             .bind(&table_name)
-            .fetch_all(&self.pool)
+            .fetch_all(&self.pool())
             .await
-            .map_err(|e| DatabaseError {
-                message: format!("Failed to get structure for {}: {}", table_name, e),
+            .map_err(|e| {
+                DatabaseError::with_context(
+                    e,
+                    format!("Failed to get structure for {}", table_name),
+                )
             })?;
 
         let mut results = Vec::new();
@@ -289,7 +631,7 @@ impl DatabaseProvider for MysqlProvider {
                     let bytes: Option<Vec<u8>> = row.get("comment");
                     bytes.map(|b| String::from_utf8_lossy(&b).to_string())
                 },
-                foreign_key: None, // TODO: Implement FK for MySQL
+                foreign_key: row.get("foreign_key"),
             });
         }
 
@@ -298,15 +640,19 @@ impl DatabaseProvider for MysqlProvider {
 
     async fn get_table_indexes(
         &self,
+        schema: Option<String>,
         table_name: String,
     ) -> Result<Vec<IndexDefinition>, DatabaseError> {
-        let query = format!("SHOW INDEX FROM `{}`", table_name);
+        let query = format!(
+            "SHOW INDEX FROM {}",
+            qualified_name(SqlDialect::Mysql, schema.as_deref(), &table_name)
+        );
 
         let rows = sqlx::query(&query)
-            .fetch_all(&self.pool)
+            .fetch_all(&self.pool())
             .await
-            .map_err(|e| DatabaseError {
-                message: format!("Failed to get indexes for {}: {}", table_name, e),
+            .map_err(|e| {
+                DatabaseError::with_context(e, format!("Failed to get indexes for {}", table_name))
             })?;
 
         let mut results = Vec::new();
@@ -320,7 +666,16 @@ impl DatabaseProvider for MysqlProvider {
         for row in rows {
             let index_name: String = row.get("Key_name");
             let non_unique: i32 = row.get("Non_unique");
-            let column_name: String = row.get("Column_name");
+            // Functional key parts have no column, only an expression
+            let column_name: String = match row.get::<Option<String>, _>("Column_name") {
+                Some(column_name) => column_name,
+                None => row
+                    .try_get::<Option<Vec<u8>>, _>("Expression")
+                    .ok()
+                    .flatten()
+                    .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+                    .unwrap_or_else(|| "(expression)".to_string()),
+            };
             let index_type_bytes: Vec<u8> = row.get("Index_type");
             let index_type = String::from_utf8_lossy(&index_type_bytes).to_string();
             let comment_bytes: Vec<u8> = row.get("Index_comment");
@@ -333,7 +688,7 @@ impl DatabaseProvider for MysqlProvider {
                     index_algorithm: index_type,
                     is_unique: non_unique == 0,
                     is_primary: index_name == "PRIMARY",
-                    column_names: String::new(),
+                    column_names: Vec::new(),
                     condition: None,
                     comment: if comment.is_empty() {
                         None
@@ -342,10 +697,7 @@ impl DatabaseProvider for MysqlProvider {
                     },
                 });
 
-            if !entry.column_names.is_empty() {
-                entry.column_names.push_str(", ");
-            }
-            entry.column_names.push_str(&column_name);
+            entry.column_names.push(column_name);
         }
 
         for (_, index) in index_map {
@@ -355,37 +707,441 @@ impl DatabaseProvider for MysqlProvider {
         Ok(results)
     }
 
-    async fn execute_query(&self, query: String) -> Result<QueryResult, DatabaseError> {
-        let rows = sqlx::query(&query)
-            .fetch_all(&self.pool)
+    async fn get_table_constraints(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+    ) -> Result<Vec<ConstraintDefinition>, DatabaseError> {
+        // One row per constraint column, in key order
+        let query = "
+            SELECT
+                CAST(tc.CONSTRAINT_NAME AS CHAR) AS constraint_name,
+                CAST(tc.CONSTRAINT_TYPE AS CHAR) AS constraint_type,
+                CAST(k.COLUMN_NAME AS CHAR) AS column_name,
+                CAST(k.REFERENCED_TABLE_SCHEMA AS CHAR) AS referenced_schema,
+                CAST(k.REFERENCED_TABLE_NAME AS CHAR) AS referenced_table,
+                CAST(k.REFERENCED_COLUMN_NAME AS CHAR) AS referenced_column,
+                CAST(rc.UPDATE_RULE AS CHAR) AS on_update,
+                CAST(rc.DELETE_RULE AS CHAR) AS on_delete
+            FROM information_schema.TABLE_CONSTRAINTS tc
+            LEFT JOIN information_schema.KEY_COLUMN_USAGE k
+              ON k.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA
+             AND k.CONSTRAINT_NAME = tc.CONSTRAINT_NAME
+             AND k.TABLE_NAME = tc.TABLE_NAME
+            LEFT JOIN information_schema.REFERENTIAL_CONSTRAINTS rc
+              ON rc.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA
+             AND rc.CONSTRAINT_NAME = tc.CONSTRAINT_NAME
+             AND rc.TABLE_NAME = tc.TABLE_NAME
+            WHERE tc.TABLE_SCHEMA = COALESCE(?, DATABASE()) AND tc.TABLE_NAME = ?
+            ORDER BY FIELD(tc.CONSTRAINT_TYPE, 'PRIMARY KEY', 'UNIQUE', 'FOREIGN KEY', 'CHECK'),
+                     tc.CONSTRAINT_NAME, k.ORDINAL_POSITION;
+        ";
+
+        let rows = sqlx::query(query)
+            .bind(&schema)
+            .bind(&table_name)
+            .fetch_all(&self.pool())
+            .await
+            .map_err(|e| {
+                DatabaseError::with_context(
+                    e,
+                    format!("Failed to get constraints for {}", table_name),
+                )
+            })?;
+
+        // Servers before MySQL 8.0.16 have no CHECK_CONSTRAINTS table (and no checks)
+        let check_rows = match sqlx::query(
+            "SELECT CAST(CONSTRAINT_NAME AS CHAR) AS constraint_name,
+                    CAST(CHECK_CLAUSE AS CHAR) AS check_clause
+             FROM information_schema.CHECK_CONSTRAINTS
+             WHERE CONSTRAINT_SCHEMA = COALESCE(?, DATABASE());",
+        )
+        .bind(&schema)
+        .fetch_all(&self.pool())
+        .await
+        {
+            Ok(rows) => rows,
+            Err(sqlx::Error::Database(e))
+                if e.try_downcast_ref::<MySqlDatabaseError>()
+                    .is_some_and(|e| e.number() == ER_UNKNOWN_TABLE) =>
+            {
+                Vec::new()
+            }
+            Err(e) => {
+                return Err(DatabaseError::with_context(
+                    e,
+                    format!("Failed to get CHECK constraints for {}", table_name),
+                ))
+            }
+        };
+        let check_clauses: HashMap<String, String> = check_rows
+            .iter()
+            .map(|row| (row.get("constraint_name"), row.get("check_clause")))
+            .collect();
+
+        let mut results: Vec<ConstraintDefinition> = Vec::new();
+        for row in rows {
+            let constraint_name: String = row.get("constraint_name");
+            let column_name: Option<String> = row.get("column_name");
+            let referenced_column: Option<String> = row.get("referenced_column");
+
+            let constraint = match results.last_mut() {
+                Some(last) if last.constraint_name == constraint_name => last,
+                _ => {
+                    let constraint_type: String = row.get("constraint_type");
+                    let kind = match constraint_type.as_str() {
+                        "PRIMARY KEY" => ConstraintKind::PrimaryKey,
+                        "UNIQUE" => ConstraintKind::Unique,
+                        "FOREIGN KEY" => ConstraintKind::ForeignKey,
+                        _ => ConstraintKind::Check,
+                    };
+                    results.push(ConstraintDefinition {
+                        check_clause: match kind {
+                            ConstraintKind::Check => check_clauses.get(&constraint_name).cloned(),
+                            _ => None,
+                        },
+                        constraint_name,
+                        kind,
+                        column_names: Vec::new(),
+                        referenced_schema: row.get("referenced_schema"),
+                        referenced_table: row.get("referenced_table"),
+                        referenced_columns: Vec::new(),
+                        on_update: row.get("on_update"),
+                        on_delete: row.get("on_delete"),
+                        is_deferrable: false,
+                        initially_deferred: false,
+                    });
+                    results.last_mut().unwrap()
+                }
+            };
+            constraint.column_names.extend(column_name);
+            constraint.referenced_columns.extend(referenced_column);
+        }
+
+        Ok(results)
+    }
+
+    async fn get_incoming_foreign_keys(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+    ) -> Result<Vec<IncomingForeignKey>, DatabaseError> {
+        let query = "
+            SELECT
+                CAST(k.CONSTRAINT_NAME AS CHAR) AS constraint_name,
+                CAST(k.TABLE_SCHEMA AS CHAR) AS table_schema,
+                CAST(k.TABLE_NAME AS CHAR) AS table_name,
+                CAST(k.COLUMN_NAME AS CHAR) AS column_name,
+                CAST(k.REFERENCED_COLUMN_NAME AS CHAR) AS referenced_column,
+                CAST(rc.UPDATE_RULE AS CHAR) AS on_update,
+                CAST(rc.DELETE_RULE AS CHAR) AS on_delete
+            FROM information_schema.KEY_COLUMN_USAGE k
+            JOIN information_schema.REFERENTIAL_CONSTRAINTS rc
+              ON rc.CONSTRAINT_SCHEMA = k.CONSTRAINT_SCHEMA
+             AND rc.CONSTRAINT_NAME = k.CONSTRAINT_NAME
+             AND rc.TABLE_NAME = k.TABLE_NAME
+            WHERE k.REFERENCED_TABLE_SCHEMA = COALESCE(?, DATABASE())
+              AND k.REFERENCED_TABLE_NAME = ?
+            ORDER BY k.TABLE_SCHEMA, k.TABLE_NAME, k.CONSTRAINT_NAME, k.ORDINAL_POSITION;
+        ";
+
+        let rows = sqlx::query(query)
+            .bind(&schema)
+            .bind(&table_name)
+            .fetch_all(&self.pool())
+            .await
+            .map_err(|e| {
+                DatabaseError::with_context(
+                    e,
+                    format!("Failed to get references to {}", table_name),
+                )
+            })?;
+
+        let mut results: Vec<IncomingForeignKey> = Vec::new();
+        for row in rows {
+            let constraint_name: String = row.get("constraint_name");
+            let schema: Option<String> = row.get("table_schema");
+            let table_name: String = row.get("table_name");
+
+            let key = match results.last_mut() {
+                Some(last)
+                    if last.constraint_name == constraint_name
+                        && last.schema == schema
+                        && last.table_name == table_name =>
+                {
+                    last
+                }
+                _ => {
+                    results.push(IncomingForeignKey {
+                        constraint_name,
+                        schema,
+                        table_name,
+                        column_names: Vec::new(),
+                        referenced_columns: Vec::new(),
+                        on_update: row.get("on_update"),
+                        on_delete: row.get("on_delete"),
+                    });
+                    results.last_mut().unwrap()
+                }
+            };
+            key.column_names.push(row.get("column_name"));
+            key.referenced_columns.push(row.get("referenced_column"));
+        }
+
+        Ok(results)
+    }
+
+    async fn execute_query(
+        &self,
+        query: String,
+        query_id: Option<String>,
+        session_id: Option<String>,
+    ) -> Result<QueryResult, DatabaseError> {
+        let mut conn = self
+            .sessions
+            .acquire(&self.pool(), session_id.as_deref())
+            .await?;
+        let guard = self.track_query(&mut conn, query_id).await?;
+
+        run_statement(&mut conn, &query)
+            .await
+            .map_err(|e| map_cancelled(&guard, e))
+    }
+
+    async fn execute_query_stream(
+        &self,
+        query: String,
+        query_id: Option<String>,
+        session_id: Option<String>,
+        batch_size: usize,
+        max_rows: usize,
+        on_event: &(dyn Fn(QueryStreamEvent) -> bool + Send + Sync),
+    ) -> Result<(), DatabaseError> {
+        let mut conn = self
+            .sessions
+            .acquire(&self.pool(), session_id.as_deref())
+            .await?;
+        let guard = self.track_query(&mut conn, query_id).await?;
+
+        let statement = (&mut *conn)
+            .prepare(&query)
             .await
             .map_err(DatabaseError::from)?;
+        let rows = statement.query().fetch(&mut *conn);
+        stream_rows(
+            rows,
+            statement.columns(),
+            row_to_values,
+            batch_size,
+            max_rows,
+            on_event,
+        )
+        .await
+        .map_err(|e| map_cancelled(&guard, e))
+    }
 
-        let columns = if let Some(first_row) = rows.first() {
-            first_row
-                .columns()
-                .iter()
-                .map(|col| col.name().to_string())
-                .collect()
-        } else {
-            Vec::new()
+    async fn execute_script(
+        &self,
+        statements: Vec<Statement>,
+        stop_on_error: bool,
+        query_id: Option<String>,
+        session_id: Option<String>,
+    ) -> Result<Vec<StatementResult>, DatabaseError> {
+        let mut conn = self
+            .sessions
+            .acquire(&self.pool(), session_id.as_deref())
+            .await?;
+        let guard = self.track_query(&mut conn, query_id).await?;
+
+        let mut results = Vec::new();
+        for statement in statements {
+            let started = Instant::now();
+            let outcome = run_statement(&mut conn, &statement.sql)
+                .await
+                .map_err(|e| map_cancelled(&guard, e));
+
+            let stop = match &outcome {
+                Err(err) => stop_on_error || err.kind == DatabaseErrorKind::Cancelled,
+                Ok(_) => false,
+            };
+            results.push(StatementResult::new(statement, outcome, started.elapsed()));
+            if stop {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn cancel_query(&self, query_id: String) -> Result<bool, DatabaseError> {
+        let Some(backend_id) = self.running.mark_cancelled(&query_id) else {
+            return Ok(false);
         };
+        // KILL QUERY does not accept placeholders; the id is numeric
+        sqlx::query(&format!("KILL QUERY {}", backend_id))
+            .execute(&self.pool())
+            .await
+            .map_err(DatabaseError::from)?;
+        Ok(true)
+    }
 
-        let result_rows: Vec<Vec<serde_json::Value>> =
-            rows.into_iter().map(row_to_values).collect();
+    async fn begin_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.begin(&self.pool(), session_id).await
+    }
 
-        Ok(QueryResult {
-            columns,
-            rows: result_rows,
-            total_rows: None,
-        })
+    async fn commit_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.commit(session_id).await
+    }
+
+    async fn rollback_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.rollback(session_id).await
+    }
+
+    async fn create_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.savepoint(session_id, name).await
+    }
+
+    async fn rollback_to_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.rollback_to_savepoint(session_id, name).await
+    }
+
+    async fn release_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.release_savepoint(session_id, name).await
+    }
+
+    fn transaction_state(&self, session_id: &str) -> TransactionState {
+        self.sessions.state(session_id)
+    }
+
+    async fn execute_dml(&self, statements: Vec<DmlStatement>) -> Result<u64, DatabaseError> {
+        let mut tx = self.pool().begin().await.map_err(DatabaseError::from)?;
+        let mut rows_affected = 0;
+        for statement in &statements {
+            let mut query = sqlx::query(&statement.sql);
+            for param in &statement.params {
+                query = bind_value(query, param);
+            }
+            let affected = query
+                .execute(&mut *tx)
+                .await
+                .map_err(DatabaseError::from)?
+                .rows_affected();
+            if let Some(err) = dml::row_count_error(statement, affected) {
+                return Err(err);
+            }
+            rows_affected += affected;
+        }
+        tx.commit().await.map_err(DatabaseError::from)?;
+        Ok(rows_affected)
+    }
+
+    async fn bulk_insert(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        columns: Vec<String>,
+        rows: Vec<Vec<Value>>,
+    ) -> Result<u64, DatabaseError> {
+        let table = qualified_name(SqlDialect::Mysql, schema.as_deref(), &table_name);
+        let columns: Vec<String> = columns
+            .iter()
+            .map(|c| quote_identifier(SqlDialect::Mysql, c))
+            .collect();
+        let row_sql = format!("({})", vec!["?"; columns.len()].join(", "));
+        let rows_per_statement = (MAX_BIND_PARAMS / columns.len().max(1)).max(1);
+
+        let mut tx = self.pool().begin().await.map_err(DatabaseError::from)?;
+        let mut rows_affected = 0;
+        for chunk in rows.chunks(rows_per_statement) {
+            let sql = format!(
+                "INSERT INTO {} ({}) VALUES {}",
+                table,
+                columns.join(", "),
+                vec![row_sql.as_str(); chunk.len()].join(", ")
+            );
+            let mut query = sqlx::query(&sql);
+            for value in chunk.iter().flatten() {
+                query = bind_value(query, value);
+            }
+            rows_affected += query
+                .execute(&mut *tx)
+                .await
+                .map_err(DatabaseError::from)?
+                .rows_affected();
+        }
+        tx.commit().await.map_err(DatabaseError::from)?;
+        Ok(rows_affected)
+    }
+
+    async fn switch_database(&self, database: String) -> Result<String, DatabaseError> {
+        // Moving a pinned connection would silently run the rest of its
+        // transaction against another database
+        if self.sessions.has_open() {
+            return Err(DatabaseError::with_kind(
+                DatabaseErrorKind::Transaction,
+                "Commit or roll back open transactions before switching databases",
+            ));
+        }
+
+        // Same host, port and credentials; through the tunnel if there is one
+        let old_pool = self.pool();
+        let options = old_pool
+            .connect_options()
+            .as_ref()
+            .clone()
+            .database(&database);
+        let pool = pool_options(&self.session_variables)
+            .connect_with(options)
+            .await
+            .map_err(DatabaseError::from)?;
+
+        *self.pool.write().unwrap() = pool;
+        // Queries still running on the old pool finish before it closes
+        tokio::spawn(async move { old_pool.close().await });
+
+        self.current_database().await
+    }
+
+    async fn current_database(&self) -> Result<String, DatabaseError> {
+        let database: Option<String> = sqlx::query_scalar("SELECT DATABASE()")
+            .fetch_one(&self.pool())
+            .await
+            .map_err(DatabaseError::from)?;
+        Ok(database.unwrap_or_default())
+    }
+
+    async fn current_schema(&self) -> Result<Option<String>, DatabaseError> {
+        // A MySQL schema is a database
+        let database = self.current_database().await?;
+        Ok(Some(database).filter(|d| !d.is_empty()))
     }
 
     async fn get_database_schema(&self) -> Result<HashMap<String, Vec<String>>, DatabaseError> {
         let rows = sqlx::query(
             "SELECT TABLE_NAME, COLUMN_NAME FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE();",
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&self.pool())
         .await
         .map_err(DatabaseError::from)?;
 
@@ -395,16 +1151,15 @@ impl DatabaseProvider for MysqlProvider {
             let table_name: String = row.get("TABLE_NAME");
             let column_name: String = row.get("COLUMN_NAME");
 
-            schema
-                .entry(table_name)
-                .or_insert_with(Vec::new)
-                .push(column_name);
+            schema.entry(table_name).or_default().push(column_name);
         }
 
         Ok(schema)
     }
 
-    async fn close(&self) {
-        self.pool.close().await;
+    async fn close(&self) -> Result<(), DatabaseError> {
+        let rolled_back = self.sessions.rollback_all().await;
+        self.pool().close().await;
+        rolled_back
     }
 }
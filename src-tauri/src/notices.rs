@@ -0,0 +1,69 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::instrument::WithSubscriber;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+// sqlx does not hand Postgres NoticeResponse messages to the caller; it only
+// emits them as tracing events. While a statement runs, a collector is made
+// the default subscriber for its future and keeps those events.
+const NOTICE_TARGET: &str = "sqlx::postgres::notice";
+
+/// Runs `fut` and returns its output along with any notices the server sent meanwhile.
+pub async fn collect<F: Future>(fut: F) -> (F::Output, Vec<String>) {
+    let collector = NoticeCollector::default();
+    let notices = collector.notices.clone();
+    let output = fut.with_subscriber(collector).await;
+    let notices = std::mem::take(&mut *notices.lock().unwrap());
+    (output, notices)
+}
+
+#[derive(Default)]
+struct NoticeCollector {
+    notices: Arc<Mutex<Vec<String>>>,
+    next_span_id: AtomicU64,
+}
+
+struct MessageVisitor(Option<String>);
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" && self.0.is_none() {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+impl Subscriber for NoticeCollector {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() == NOTICE_TARGET
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(self.next_span_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut visitor = MessageVisitor(None);
+        event.record(&mut visitor);
+        if let Some(message) = visitor.0 {
+            self.notices.lock().unwrap().push(message);
+        }
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
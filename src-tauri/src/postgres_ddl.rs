@@ -0,0 +1,388 @@
+use crate::db::{DatabaseError, DatabaseErrorKind, DatabaseObject, ObjectKind};
+use crate::dml::{qualified_name, quote_identifier};
+use crate::sql_splitter::SqlDialect;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+
+// Postgres has no SHOW CREATE, so table and type definitions are pieced
+// together from pg_catalog. Views, routines and triggers have pg_get_*def
+// functions that return their source directly.
+
+/// Returns the statements that would recreate the object.
+pub async fn object_ddl(
+    pool: &PgPool,
+    schema: Option<String>,
+    object: &DatabaseObject,
+) -> Result<String, DatabaseError> {
+    let schema: String = sqlx::query_scalar("SELECT COALESCE($1, current_schema())")
+        .bind(&schema)
+        .fetch_one(pool)
+        .await
+        .map_err(DatabaseError::from)?;
+    let name = qualified_name(SqlDialect::Postgres, Some(&schema), &object.name);
+
+    match object.kind {
+        ObjectKind::Table => table_ddl(pool, &name).await,
+        ObjectKind::View => {
+            let definition = view_definition(pool, &name).await?;
+            Ok(format!(
+                "CREATE OR REPLACE VIEW {} AS\n{}",
+                name, definition
+            ))
+        }
+        ObjectKind::MaterializedView => {
+            let definition = view_definition(pool, &name).await?;
+            Ok(format!(
+                "CREATE MATERIALIZED VIEW {} AS\n{}",
+                name, definition
+            ))
+        }
+        ObjectKind::Sequence => sequence_ddl(pool, &name).await,
+        ObjectKind::Function | ObjectKind::Procedure => routine_ddl(pool, &schema, object).await,
+        ObjectKind::Trigger => trigger_ddl(pool, &schema, object).await,
+        ObjectKind::Type => type_ddl(pool, &name).await,
+        ObjectKind::Extension => extension_ddl(pool, &object.name).await,
+    }
+}
+
+fn not_found(object: &DatabaseObject) -> DatabaseError {
+    DatabaseError::with_kind(
+        DatabaseErrorKind::UndefinedObject,
+        format!("{} does not exist", object.name),
+    )
+}
+
+async fn table_ddl(pool: &PgPool, name: &str) -> Result<String, DatabaseError> {
+    let table = sqlx::query(
+        "SELECT c.relkind::text AS relkind,
+                pg_get_userbyid(c.relowner) AS owner,
+                obj_description(c.oid, 'pg_class') AS comment,
+                CASE WHEN c.relkind = 'p' THEN pg_get_partkeydef(c.oid) END AS partition_key
+         FROM pg_class c WHERE c.oid = $1::regclass",
+    )
+    .bind(name)
+    .fetch_one(pool)
+    .await
+    .map_err(DatabaseError::from)?;
+
+    let columns = sqlx::query(
+        "SELECT quote_ident(a.attname) AS name,
+                format_type(a.atttypid, a.atttypmod) AS data_type,
+                a.attnotnull AS not_null,
+                a.attidentity::text AS identity,
+                a.attgenerated::text AS generated,
+                pg_get_expr(d.adbin, d.adrelid) AS default_value,
+                col_description(a.attrelid, a.attnum) AS comment
+         FROM pg_attribute a
+         LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+         WHERE a.attrelid = $1::regclass AND a.attnum > 0 AND NOT a.attisdropped
+         ORDER BY a.attnum",
+    )
+    .bind(name)
+    .fetch_all(pool)
+    .await
+    .map_err(DatabaseError::from)?;
+
+    // NOT NULL constraints (Postgres 18+) are already part of the column lines.
+    // Foreign keys follow as ALTER TABLE, like pg_dump, so tables referencing
+    // each other can be created before any of the keys.
+    let constraints = sqlx::query(
+        "SELECT quote_ident(conname) AS name, pg_get_constraintdef(oid, true) AS definition,
+                contype = 'f' AS is_foreign_key
+         FROM pg_constraint
+         WHERE conrelid = $1::regclass AND contype <> 'n'
+         ORDER BY array_position(ARRAY['p', 'u', 'x', 'c', 'f'], contype::text), conname",
+    )
+    .bind(name)
+    .fetch_all(pool)
+    .await
+    .map_err(DatabaseError::from)?;
+
+    // Indexes backing a constraint are created by the constraint itself
+    let indexes: Vec<String> = sqlx::query_scalar(
+        "SELECT pg_get_indexdef(i.indexrelid)
+         FROM pg_index i JOIN pg_class c ON c.oid = i.indexrelid
+         WHERE i.indrelid = $1::regclass
+           AND NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conindid = i.indexrelid)
+         ORDER BY c.relname",
+    )
+    .bind(name)
+    .fetch_all(pool)
+    .await
+    .map_err(DatabaseError::from)?;
+
+    let mut lines: Vec<String> = columns.iter().map(column_line).collect();
+    let mut foreign_keys = Vec::new();
+    for row in &constraints {
+        let constraint: String = row.get("name");
+        let definition: String = row.get("definition");
+        if row.get("is_foreign_key") {
+            foreign_keys.push(format!(
+                "ALTER TABLE {} ADD CONSTRAINT {} {};",
+                name, constraint, definition
+            ));
+        } else {
+            lines.push(format!("CONSTRAINT {} {}", constraint, definition));
+        }
+    }
+
+    let relkind: String = table.get("relkind");
+    let mut create = format!(
+        "CREATE {}TABLE {} (\n    {}\n)",
+        if relkind == "f" { "FOREIGN " } else { "" },
+        name,
+        lines.join(",\n    ")
+    );
+    if let Some(partition_key) = table.get::<Option<String>, _>("partition_key") {
+        create.push_str(&format!(" PARTITION BY {}", partition_key));
+    }
+    create.push(';');
+
+    let mut comments = Vec::new();
+    if let Some(comment) = table.get::<Option<String>, _>("comment") {
+        comments.push(format!(
+            "COMMENT ON TABLE {} IS {};",
+            name,
+            quote_literal(&comment)
+        ));
+    }
+    for column in &columns {
+        if let Some(comment) = column.get::<Option<String>, _>("comment") {
+            let column_name: String = column.get("name");
+            comments.push(format!(
+                "COMMENT ON COLUMN {}.{} IS {};",
+                name,
+                column_name,
+                quote_literal(&comment)
+            ));
+        }
+    }
+
+    let owner: String = table.get("owner");
+    let sections = [
+        create,
+        indexes
+            .iter()
+            .map(|index| format!("{};", index))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        foreign_keys.join("\n"),
+        comments.join("\n"),
+        format!(
+            "ALTER TABLE {} OWNER TO {};",
+            name,
+            quote_identifier(SqlDialect::Postgres, &owner)
+        ),
+    ];
+    Ok(sections
+        .into_iter()
+        .filter(|section| !section.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n"))
+}
+
+fn column_line(row: &PgRow) -> String {
+    let name: String = row.get("name");
+    let data_type: String = row.get("data_type");
+    let identity: String = row.get("identity");
+    let generated: String = row.get("generated");
+    let default_value: Option<String> = row.get("default_value");
+
+    let mut line = format!("{} {}", name, data_type);
+    match (identity.as_str(), generated.as_str(), default_value) {
+        ("a", _, _) => line.push_str(" GENERATED ALWAYS AS IDENTITY"),
+        ("d", _, _) => line.push_str(" GENERATED BY DEFAULT AS IDENTITY"),
+        (_, "s", Some(expression)) => {
+            line.push_str(&format!(" GENERATED ALWAYS AS ({}) STORED", expression))
+        }
+        (_, _, Some(default_value)) => line.push_str(&format!(" DEFAULT {}", default_value)),
+        _ => {}
+    }
+    if row.get::<bool, _>("not_null") {
+        line.push_str(" NOT NULL");
+    }
+    line
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+async fn view_definition(pool: &PgPool, name: &str) -> Result<String, DatabaseError> {
+    sqlx::query_scalar("SELECT pg_get_viewdef($1::regclass, true)")
+        .bind(name)
+        .fetch_one(pool)
+        .await
+        .map_err(DatabaseError::from)
+}
+
+async fn sequence_ddl(pool: &PgPool, name: &str) -> Result<String, DatabaseError> {
+    let row = sqlx::query(
+        "SELECT format_type(seqtypid, NULL) AS data_type, seqstart, seqincrement,
+                seqmin, seqmax, seqcache, seqcycle
+         FROM pg_sequence WHERE seqrelid = $1::regclass",
+    )
+    .bind(name)
+    .fetch_one(pool)
+    .await
+    .map_err(DatabaseError::from)?;
+
+    Ok(format!(
+        "CREATE SEQUENCE {} AS {}\n    INCREMENT BY {}\n    MINVALUE {}\n    MAXVALUE {}\n    START WITH {}\n    CACHE {}\n    {}CYCLE;",
+        name,
+        row.get::<String, _>("data_type"),
+        row.get::<i64, _>("seqincrement"),
+        row.get::<i64, _>("seqmin"),
+        row.get::<i64, _>("seqmax"),
+        row.get::<i64, _>("seqstart"),
+        row.get::<i64, _>("seqcache"),
+        if row.get::<bool, _>("seqcycle") { "" } else { "NO " },
+    ))
+}
+
+async fn routine_ddl(
+    pool: &PgPool,
+    schema: &str,
+    object: &DatabaseObject,
+) -> Result<String, DatabaseError> {
+    // Without an argument list the name has to be unambiguous
+    let definitions: Vec<String> = sqlx::query_scalar(
+        "SELECT pg_get_functiondef(p.oid)
+         FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace
+         WHERE n.nspname = $1 AND p.proname = $2 AND p.prokind IN ('f', 'p', 'w')
+           AND ($3::text IS NULL OR pg_get_function_identity_arguments(p.oid) = $3)",
+    )
+    .bind(schema)
+    .bind(&object.name)
+    .bind(&object.arguments)
+    .fetch_all(pool)
+    .await
+    .map_err(DatabaseError::from)?;
+
+    match definitions.len() {
+        0 => Err(not_found(object)),
+        1 => Ok(format!("{};", definitions[0].trim_end())),
+        _ => Err(DatabaseError::new(format!(
+            "{} is overloaded; pass its argument list to pick one",
+            object.name
+        ))),
+    }
+}
+
+async fn trigger_ddl(
+    pool: &PgPool,
+    schema: &str,
+    object: &DatabaseObject,
+) -> Result<String, DatabaseError> {
+    let table_name = object.table_name.as_deref().ok_or_else(|| {
+        DatabaseError::new(format!("Trigger {} needs the table it is on", object.name))
+    })?;
+    let definition: Option<String> = sqlx::query_scalar(
+        "SELECT pg_get_triggerdef(oid, true) FROM pg_trigger
+         WHERE tgrelid = $1::regclass AND tgname = $2",
+    )
+    .bind(qualified_name(
+        SqlDialect::Postgres,
+        Some(schema),
+        table_name,
+    ))
+    .bind(&object.name)
+    .fetch_optional(pool)
+    .await
+    .map_err(DatabaseError::from)?;
+
+    definition
+        .map(|definition| format!("{};", definition))
+        .ok_or_else(|| not_found(object))
+}
+
+async fn type_ddl(pool: &PgPool, name: &str) -> Result<String, DatabaseError> {
+    let row = sqlx::query(
+        "SELECT t.typtype::text AS typtype,
+                format_type(t.typbasetype, t.typtypmod) AS base_type,
+                t.typnotnull AS not_null,
+                t.typdefault AS default_value,
+                (SELECT array_agg(quote_literal(enumlabel) ORDER BY enumsortorder)
+                 FROM pg_enum WHERE enumtypid = t.oid) AS labels,
+                (SELECT array_agg(quote_ident(a.attname) || ' ' || format_type(a.atttypid, a.atttypmod)
+                                  ORDER BY a.attnum)
+                 FROM pg_attribute a
+                 WHERE a.attrelid = t.typrelid AND a.attnum > 0 AND NOT a.attisdropped) AS attributes,
+                (SELECT array_agg('CONSTRAINT ' || quote_ident(conname) || ' '
+                                  || pg_get_constraintdef(oid, true) ORDER BY conname)
+                 FROM pg_constraint WHERE contypid = t.oid) AS checks,
+                (SELECT format_type(rngsubtype, NULL) FROM pg_range WHERE rngtypid = t.oid) AS subtype
+         FROM pg_type t WHERE t.oid = $1::regtype",
+    )
+    .bind(name)
+    .fetch_one(pool)
+    .await
+    .map_err(DatabaseError::from)?;
+
+    let list = |column: &str| -> String {
+        row.get::<Option<Vec<String>>, _>(column)
+            .unwrap_or_default()
+            .join(",\n    ")
+    };
+    let typtype: String = row.get("typtype");
+    let ddl = match typtype.as_str() {
+        "e" => format!("CREATE TYPE {} AS ENUM (\n    {}\n);", name, list("labels")),
+        "c" => format!("CREATE TYPE {} AS (\n    {}\n);", name, list("attributes")),
+        "r" => format!(
+            "CREATE TYPE {} AS RANGE (SUBTYPE = {});",
+            name,
+            row.get::<String, _>("subtype")
+        ),
+        "d" => {
+            let mut ddl = format!(
+                "CREATE DOMAIN {} AS {}",
+                name,
+                row.get::<String, _>("base_type")
+            );
+            if let Some(default_value) = row.get::<Option<String>, _>("default_value") {
+                ddl.push_str(&format!("\n    DEFAULT {}", default_value));
+            }
+            if row.get::<bool, _>("not_null") {
+                ddl.push_str("\n    NOT NULL");
+            }
+            let checks = list("checks");
+            if !checks.is_empty() {
+                ddl.push_str(&format!("\n    {}", checks));
+            }
+            ddl.push(';');
+            ddl
+        }
+        _ => {
+            return Err(DatabaseError::new(format!(
+                "Cannot reconstruct the definition of type {}",
+                name
+            )))
+        }
+    };
+    Ok(ddl)
+}
+
+async fn extension_ddl(pool: &PgPool, name: &str) -> Result<String, DatabaseError> {
+    let row = sqlx::query(
+        "SELECT n.nspname, e.extversion FROM pg_extension e
+         JOIN pg_namespace n ON n.oid = e.extnamespace
+         WHERE e.extname = $1",
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await
+    .map_err(DatabaseError::from)?
+    .ok_or_else(|| {
+        DatabaseError::with_kind(
+            DatabaseErrorKind::UndefinedObject,
+            format!("Extension {} is not installed", name),
+        )
+    })?;
+
+    Ok(format!(
+        "CREATE EXTENSION IF NOT EXISTS {} WITH SCHEMA {} VERSION {};",
+        quote_identifier(SqlDialect::Postgres, name),
+        quote_identifier(SqlDialect::Postgres, &row.get::<String, _>("nspname")),
+        quote_literal(&row.get::<String, _>("extversion")),
+    ))
+}
@@ -1,22 +1,192 @@
 use crate::database_provider::DatabaseProvider;
-use crate::db::{ColumnDefinition, DatabaseError, Filter, IndexDefinition, QueryResult, Sort};
+use crate::db::{
+    collect_results, stream_rows, ColumnDefinition, ConstraintDefinition, ConstraintKind,
+    DatabaseError, DatabaseErrorKind, DatabaseObject, Filter, IncomingForeignKey, IndexDefinition,
+    ObjectKind, QueryResult, QueryStreamEvent, Sort, StatementResult, TableQuery,
+    DEFAULT_STREAM_BATCH_SIZE,
+};
+use crate::dml::{self, qualified_name, quote_identifier, DmlStatement};
+use crate::notices;
+use crate::postgres_ddl;
+use crate::query_registry::{map_cancelled, QueryGuard, QueryRegistry};
+use crate::session::{Sessions, TransactionState};
+use crate::sql_splitter::{SqlDialect, Statement};
 use async_trait::async_trait;
-use sqlx::postgres::{PgPoolOptions, PgRow};
-use sqlx::{Column, Pool, Postgres, Row, TypeInfo};
+use serde_json::Value;
+use sqlx::postgres::{PgConnectOptions, PgConnection, PgPoolOptions, PgRow};
+use sqlx::{Column, Executor, Pool, Postgres, Row, Statement as _, Type, TypeInfo};
 use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Instant;
 
 pub struct PostgresProvider {
-    pool: Pool<Postgres>,
+    /// Swapped out when switching databases.
+    pool: RwLock<Pool<Postgres>>,
+    sessions: Sessions<Postgres>,
+    running: QueryRegistry,
 }
 
 impl PostgresProvider {
-    pub async fn new(connection_string: &str) -> Result<Self, DatabaseError> {
+    pub async fn new(options: PgConnectOptions) -> Result<Self, DatabaseError> {
         let pool = PgPoolOptions::new()
             .max_connections(5)
-            .connect(connection_string)
+            .connect_with(options)
             .await
             .map_err(DatabaseError::from)?;
-        Ok(Self { pool })
+        Ok(Self {
+            pool: RwLock::new(pool),
+            sessions: Sessions::default(),
+            running: QueryRegistry::default(),
+        })
+    }
+
+    fn pool(&self) -> Pool<Postgres> {
+        self.pool.read().unwrap().clone()
+    }
+
+    /// Records which backend runs the query so `cancel_query` can reach it.
+    async fn track_query(
+        &self,
+        conn: &mut PgConnection,
+        query_id: Option<String>,
+    ) -> Result<Option<QueryGuard<'_>>, DatabaseError> {
+        let Some(query_id) = query_id else {
+            return Ok(None);
+        };
+        let backend_id: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+            .fetch_one(conn)
+            .await
+            .map_err(DatabaseError::from)?;
+        self.running
+            .register(&query_id, backend_id as u64)
+            .map(Some)
+            .ok_or_else(|| {
+                DatabaseError::new(format!("A query with id {} is already running", query_id))
+            })
+    }
+
+    /// The FROM/WHERE and ORDER BY parts shared by paging and exporting a table.
+    async fn table_query(
+        &self,
+        schema: Option<&str>,
+        table_name: &str,
+        filters: &[Filter],
+        sorts: &[Sort],
+    ) -> Result<TableQuery, DatabaseError> {
+        let table = qualified_name(SqlDialect::Postgres, schema, table_name);
+
+        // Get column types for casting
+        let type_rows = sqlx::query(
+            "SELECT a.attname AS column_name, t.typname AS udt_name
+             FROM pg_attribute a
+             JOIN pg_type t ON t.oid = a.atttypid
+             WHERE a.attrelid = $1::regclass AND a.attnum > 0 AND NOT a.attisdropped",
+        )
+        .bind(&table)
+        .fetch_all(&self.pool())
+        .await
+        .map_err(DatabaseError::from)?;
+
+        let mut column_types = HashMap::new();
+        for row in type_rows {
+            let column_name: String = row.get("column_name");
+            let data_type: String = row.get("udt_name");
+            column_types.insert(column_name, data_type);
+        }
+
+        let mut where_clauses = Vec::new();
+        let mut query_params = Vec::new();
+        let mut param_index = 1;
+
+        for filter in filters {
+            let field = quote_identifier(SqlDialect::Postgres, &filter.field);
+            let col_type = column_types
+                .get(&filter.field)
+                .map(|s| s.as_str())
+                .unwrap_or("text");
+
+            let cast_suffix = match col_type {
+                "uuid" => "::uuid",
+                "date" => "::date",
+                "timestamp" | "timestamptz" => match col_type {
+                    "timestamp" => "::timestamp",
+                    "timestamptz" => "::timestamptz",
+                    _ => "",
+                },
+                "bool" => "::boolean",
+                // Related-row filters compare integer keys
+                "int2" => "::smallint",
+                "int4" => "::integer",
+                "int8" => "::bigint",
+                "numeric" => "::numeric",
+                _ => "",
+            };
+
+            match filter.operator.as_str() {
+                "=" | ">=" | "<=" | ">" | "<" => {
+                    where_clauses.push(format!(
+                        "{} {} ${}{}",
+                        field, filter.operator, param_index, cast_suffix
+                    ));
+                    query_params.push(filter.value.clone());
+                    param_index += 1;
+                }
+                "contain" => {
+                    where_clauses.push(format!("{}::text ILIKE ${}", field, param_index));
+                    query_params.push(format!("%{}%", filter.value));
+                    param_index += 1;
+                }
+                "start with" => {
+                    where_clauses.push(format!("{}::text ILIKE ${}", field, param_index));
+                    query_params.push(format!("{}%", filter.value));
+                    param_index += 1;
+                }
+                "end with" => {
+                    where_clauses.push(format!("{}::text ILIKE ${}", field, param_index));
+                    query_params.push(format!("%{}", filter.value));
+                    param_index += 1;
+                }
+                "not null" => {
+                    where_clauses.push(format!("{} IS NOT NULL", field));
+                }
+                "is null" => {
+                    where_clauses.push(format!("{} IS NULL", field));
+                }
+                _ => {}
+            }
+        }
+
+        let where_sql = if where_clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", where_clauses.join(" AND "))
+        };
+
+        let mut order_clauses = Vec::new();
+        for sort in sorts {
+            let direction = if sort.order.to_uppercase() == "DESC" {
+                "DESC"
+            } else {
+                "ASC"
+            };
+            order_clauses.push(format!(
+                "{} {}",
+                quote_identifier(SqlDialect::Postgres, &sort.field),
+                direction
+            ));
+        }
+
+        let order_sql = if order_clauses.is_empty() {
+            String::new()
+        } else {
+            format!("ORDER BY {}", order_clauses.join(", "))
+        };
+
+        Ok(TableQuery {
+            from: format!("{} {}", table, where_sql),
+            order_by: order_sql,
+            params: query_params,
+        })
     }
 }
 
@@ -94,11 +264,43 @@ fn row_to_values(row: PgRow) -> Vec<serde_json::Value> {
     values
 }
 
+/// Runs a single statement, capturing its rows or affected-row count, the
+/// notices raised along the way and how long it took.
+async fn run_statement(conn: &mut PgConnection, sql: &str) -> Result<QueryResult, DatabaseError> {
+    let started = Instant::now();
+    let (result, notices) = notices::collect(async {
+        // Preparing first tells us the result columns even when no rows come back
+        let statement = conn.prepare(sql).await.map_err(DatabaseError::from)?;
+        let columns = statement
+            .columns()
+            .iter()
+            .map(|col| col.name().to_string())
+            .collect();
+        collect_results(
+            conn.fetch_many(statement.query()),
+            columns,
+            row_to_values,
+            |done| (done.rows_affected(), None),
+        )
+        .await
+    })
+    .await;
+
+    let mut result = result?;
+    result.notices = notices;
+    result.execution_time_ms = Some(started.elapsed().as_millis() as u64);
+    Ok(result)
+}
+
 #[async_trait]
 impl DatabaseProvider for PostgresProvider {
+    fn dialect(&self) -> SqlDialect {
+        SqlDialect::Postgres
+    }
+
     async fn list_databases(&self) -> Result<Vec<String>, DatabaseError> {
         let rows = sqlx::query("SELECT datname FROM pg_database WHERE datistemplate = false;")
-            .fetch_all(&self.pool)
+            .fetch_all(&self.pool())
             .await
             .map_err(DatabaseError::from)?;
 
@@ -106,11 +308,31 @@ impl DatabaseProvider for PostgresProvider {
         Ok(databases)
     }
 
-    async fn list_tables(&self) -> Result<Vec<String>, DatabaseError> {
+    async fn list_schemas(&self) -> Result<Vec<String>, DatabaseError> {
         let rows = sqlx::query(
-            "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public';",
+            "SELECT nspname FROM pg_namespace
+             WHERE nspname NOT IN ('pg_catalog', 'information_schema')
+               AND nspname NOT LIKE 'pg_toast%'
+               AND nspname NOT LIKE 'pg_temp_%'
+             ORDER BY nspname;",
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&self.pool())
+        .await
+        .map_err(DatabaseError::from)?;
+
+        let schemas: Vec<String> = rows.iter().map(|row| row.get("nspname")).collect();
+        Ok(schemas)
+    }
+
+    async fn list_tables(&self, schema: Option<String>) -> Result<Vec<String>, DatabaseError> {
+        // current_schema() is the first schema on the search_path that exists
+        let rows = sqlx::query(
+            "SELECT table_name FROM information_schema.tables
+             WHERE table_schema = COALESCE($1, current_schema())
+             ORDER BY table_name;",
+        )
+        .bind(&schema)
+        .fetch_all(&self.pool())
         .await
         .map_err(DatabaseError::from)?;
 
@@ -121,138 +343,118 @@ impl DatabaseProvider for PostgresProvider {
         Ok(tables)
     }
 
-    async fn get_table_data(
+    async fn list_objects(
         &self,
-        table_name: String,
-        limit: i64,
-        offset: i64,
-        filters: Vec<Filter>,
-        sorts: Vec<Sort>,
-    ) -> Result<QueryResult, DatabaseError> {
-        // Get column types for casting
-        let type_rows = sqlx::query(
-            "SELECT column_name, udt_name 
-             FROM information_schema.columns 
-             WHERE table_name = $1",
+        schema: Option<String>,
+    ) -> Result<Vec<DatabaseObject>, DatabaseError> {
+        // Objects that belong to an extension are listed under the extension only
+        let rows = sqlx::query(
+            "WITH ns AS (
+                 SELECT oid FROM pg_namespace WHERE nspname = COALESCE($1, current_schema())
+             ), ext AS (
+                 SELECT classid, objid FROM pg_depend WHERE deptype = 'e'
+             )
+             SELECT c.relname AS name, c.relkind::text AS kind,
+                    NULL::text AS table_name, NULL::text AS arguments
+             FROM pg_class c
+             WHERE c.relnamespace IN (SELECT oid FROM ns)
+               AND c.relkind IN ('r', 'p', 'f', 'v', 'm', 'S')
+               AND NOT c.relispartition
+               AND (c.oid, 'pg_class'::regclass) NOT IN (SELECT objid, classid FROM ext)
+             UNION ALL
+             SELECT p.proname, CASE p.prokind WHEN 'p' THEN 'procedure' ELSE 'function' END,
+                    NULL, pg_get_function_identity_arguments(p.oid)
+             FROM pg_proc p
+             WHERE p.pronamespace IN (SELECT oid FROM ns)
+               AND (p.oid, 'pg_proc'::regclass) NOT IN (SELECT objid, classid FROM ext)
+             UNION ALL
+             SELECT t.tgname, 'trigger', c.relname, NULL
+             FROM pg_trigger t JOIN pg_class c ON c.oid = t.tgrelid
+             WHERE c.relnamespace IN (SELECT oid FROM ns) AND NOT t.tgisinternal
+             UNION ALL
+             SELECT t.typname, 'type', NULL, NULL
+             FROM pg_type t LEFT JOIN pg_class c ON c.oid = t.typrelid
+             WHERE t.typnamespace IN (SELECT oid FROM ns)
+               AND (t.typtype IN ('e', 'd', 'r') OR c.relkind = 'c')
+               AND (t.oid, 'pg_type'::regclass) NOT IN (SELECT objid, classid FROM ext)
+             UNION ALL
+             SELECT e.extname, 'extension', NULL, NULL
+             FROM pg_extension e
+             WHERE e.extnamespace IN (SELECT oid FROM ns)
+             ORDER BY name;",
         )
-        .bind(&table_name)
-        .fetch_all(&self.pool)
+        .bind(&schema)
+        .fetch_all(&self.pool())
         .await
         .map_err(DatabaseError::from)?;
 
-        let mut column_types = HashMap::new();
-        for row in type_rows {
-            let column_name: String = row.get("column_name");
-            let data_type: String = row.get("udt_name");
-            column_types.insert(column_name, data_type);
-        }
-
-        let mut where_clauses = Vec::new();
-        let mut query_params = Vec::new();
-        let mut param_index = 1;
-
-        for filter in &filters {
-            if !filter
-                .field
-                .chars()
-                .all(|c| c.is_alphanumeric() || c == '_')
-            {
-                continue;
-            }
-
-            let field = format!("\"{}\"", filter.field);
-            let col_type = column_types
-                .get(&filter.field)
-                .map(|s| s.as_str())
-                .unwrap_or("text");
-
-            let cast_suffix = match col_type {
-                "uuid" => "::uuid",
-                "date" => "::date",
-                "timestamp" | "timestamptz" => match col_type {
-                    "timestamp" => "::timestamp",
-                    "timestamptz" => "::timestamptz",
-                    _ => "",
-                },
-                "bool" => "::boolean",
-                _ => "",
-            };
-
-            match filter.operator.as_str() {
-                "=" | ">=" | "<=" | ">" | "<" => {
-                    where_clauses.push(format!(
-                        "{} {} ${}{}",
-                        field, filter.operator, param_index, cast_suffix
-                    ));
-                    query_params.push(filter.value.clone());
-                    param_index += 1;
-                }
-                "contain" => {
-                    where_clauses.push(format!("{}::text ILIKE ${}", field, param_index));
-                    query_params.push(format!("%{}%", filter.value));
-                    param_index += 1;
-                }
-                "start with" => {
-                    where_clauses.push(format!("{}::text ILIKE ${}", field, param_index));
-                    query_params.push(format!("{}%", filter.value));
-                    param_index += 1;
-                }
-                "end with" => {
-                    where_clauses.push(format!("{}::text ILIKE ${}", field, param_index));
-                    query_params.push(format!("%{}", filter.value));
-                    param_index += 1;
-                }
-                "not null" => {
-                    where_clauses.push(format!("{} IS NOT NULL", field));
-                }
-                "is null" => {
-                    where_clauses.push(format!("{} IS NULL", field));
+        let objects = rows
+            .iter()
+            .map(|row| {
+                let kind: String = row.get("kind");
+                DatabaseObject {
+                    name: row.get("name"),
+                    kind: match kind.as_str() {
+                        "v" => ObjectKind::View,
+                        "m" => ObjectKind::MaterializedView,
+                        "S" => ObjectKind::Sequence,
+                        "function" => ObjectKind::Function,
+                        "procedure" => ObjectKind::Procedure,
+                        "trigger" => ObjectKind::Trigger,
+                        "type" => ObjectKind::Type,
+                        "extension" => ObjectKind::Extension,
+                        // Plain, partitioned and foreign tables
+                        _ => ObjectKind::Table,
+                    },
+                    table_name: row.get("table_name"),
+                    arguments: row.get("arguments"),
                 }
-                _ => {}
-            }
-        }
-
-        let where_sql = if where_clauses.is_empty() {
-            String::new()
-        } else {
-            format!("WHERE {}", where_clauses.join(" AND "))
-        };
+            })
+            .collect();
+        Ok(objects)
+    }
 
-        let mut order_clauses = Vec::new();
-        for sort in &sorts {
-            if !sort.field.chars().all(|c| c.is_alphanumeric() || c == '_') {
-                continue;
-            }
-            let direction = if sort.order.to_uppercase() == "DESC" {
-                "DESC"
-            } else {
-                "ASC"
-            };
-            order_clauses.push(format!("\"{}\" {}", sort.field, direction));
-        }
+    async fn get_object_ddl(
+        &self,
+        schema: Option<String>,
+        object: DatabaseObject,
+    ) -> Result<String, DatabaseError> {
+        postgres_ddl::object_ddl(&self.pool(), schema, &object).await
+    }
 
-        let order_sql = if order_clauses.is_empty() {
-            String::new()
-        } else {
-            format!("ORDER BY {}", order_clauses.join(", "))
-        };
+    async fn get_table_data(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        limit: i64,
+        offset: i64,
+        filters: Vec<Filter>,
+        sorts: Vec<Sort>,
+    ) -> Result<QueryResult, DatabaseError> {
+        let TableQuery {
+            from,
+            order_by,
+            params: query_params,
+        } = self
+            .table_query(schema.as_deref(), &table_name, &filters, &sorts)
+            .await?;
 
-        let count_query = format!("SELECT COUNT(*) FROM \"{}\" {}", table_name, where_sql);
+        let count_query = format!("SELECT COUNT(*) FROM {}", from);
         let mut count_q = sqlx::query_as::<_, (i64,)>(&count_query);
         for param in &query_params {
             count_q = count_q.bind(param);
         }
         let count_row = count_q
-            .fetch_one(&self.pool)
+            .fetch_one(&self.pool())
             .await
             .map_err(DatabaseError::from)?;
         let total_rows = count_row.0;
 
+        let param_index = query_params.len() + 1;
         let query = format!(
-            "SELECT * FROM \"{}\" {} {} LIMIT ${} OFFSET ${}",
-            table_name,
-            where_sql,
-            order_sql,
+            "SELECT * FROM {} {} LIMIT ${} OFFSET ${}",
+            from,
+            order_by,
             param_index,
             param_index + 1
         );
@@ -263,7 +465,10 @@ impl DatabaseProvider for PostgresProvider {
         }
         q = q.bind(limit).bind(offset);
 
-        let rows = q.fetch_all(&self.pool).await.map_err(DatabaseError::from)?;
+        let rows = q
+            .fetch_all(&self.pool())
+            .await
+            .map_err(DatabaseError::from)?;
 
         let columns = if let Some(first_row) = rows.first() {
             first_row
@@ -282,11 +487,58 @@ impl DatabaseProvider for PostgresProvider {
             columns,
             rows: result_rows,
             total_rows: Some(total_rows),
+            ..Default::default()
         })
     }
 
+    async fn stream_table_data(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        filters: Vec<Filter>,
+        sorts: Vec<Sort>,
+        query_id: Option<String>,
+        on_event: &(dyn Fn(QueryStreamEvent) -> bool + Send + Sync),
+    ) -> Result<(), DatabaseError> {
+        let TableQuery {
+            from,
+            order_by,
+            params,
+        } = self
+            .table_query(schema.as_deref(), &table_name, &filters, &sorts)
+            .await?;
+        let query = format!("SELECT * FROM {} {}", from, order_by);
+
+        let mut conn = self.pool().acquire().await.map_err(DatabaseError::from)?;
+        let guard = self.track_query(&mut conn, query_id).await?;
+
+        // Declared as text, as unprepared binds of strings would be, so the
+        // filter casts still apply
+        let param_types = vec![<String as Type<Postgres>>::type_info(); params.len()];
+        let statement = (&mut *conn)
+            .prepare_with(&query, &param_types)
+            .await
+            .map_err(DatabaseError::from)?;
+        let mut q = statement.query();
+        for param in &params {
+            q = q.bind(param);
+        }
+        let rows = q.fetch(&mut *conn);
+        stream_rows(
+            rows,
+            statement.columns(),
+            row_to_values,
+            DEFAULT_STREAM_BATCH_SIZE,
+            usize::MAX,
+            on_event,
+        )
+        .await
+        .map_err(|e| map_cancelled(&guard, e))
+    }
+
     async fn get_table_structure(
         &self,
+        schema: Option<String>,
         table_name: String,
     ) -> Result<Vec<ColumnDefinition>, DatabaseError> {
         let query = "
@@ -297,13 +549,17 @@ impl DatabaseProvider for PostgresProvider {
                 pg_get_expr(d.adbin, d.adrelid) AS column_default,
                 col_description(a.attrelid, a.attnum) AS comment,
                 (
+                    -- The referenced column sits at the same position in confkey
                     SELECT 
                         confrelid::regclass::text || '(' || a2.attname || ')'
                     FROM pg_constraint c
-                    JOIN pg_attribute a2 ON a2.attnum = c.confkey[1] AND a2.attrelid = c.confrelid
+                    JOIN pg_attribute a2
+                      ON a2.attrelid = c.confrelid
+                     AND a2.attnum = c.confkey[array_position(c.conkey, a.attnum)]
                     WHERE c.conrelid = a.attrelid 
                       AND c.contype = 'f' 
-                      AND c.conkey[1] = a.attnum
+                      AND a.attnum = ANY(c.conkey)
+                    ORDER BY c.conname
                     LIMIT 1
                 ) AS foreign_key
             FROM pg_attribute a
@@ -314,18 +570,18 @@ impl DatabaseProvider for PostgresProvider {
             ORDER BY a.attnum;
         ";
 
-        let table_oid_str = if table_name.contains('.') {
-            table_name.clone()
-        } else {
-            format!("public.\"{}\"", table_name)
-        };
+        // Unqualified names resolve through the search_path
+        let table_oid_str = qualified_name(SqlDialect::Postgres, schema.as_deref(), &table_name);
 
         let rows = sqlx::query(query)
             .bind(&table_oid_str)
-            .fetch_all(&self.pool)
+            .fetch_all(&self.pool())
             .await
-            .map_err(|e| DatabaseError {
-                message: format!("Failed to get structure for {}: {}", table_name, e),
+            .map_err(|e| {
+                DatabaseError::with_context(
+                    e,
+                    format!("Failed to get structure for {}", table_name),
+                )
             })?;
 
         let mut results = Vec::new();
@@ -345,13 +601,10 @@ impl DatabaseProvider for PostgresProvider {
 
     async fn get_table_indexes(
         &self,
+        schema: Option<String>,
         table_name: String,
     ) -> Result<Vec<IndexDefinition>, DatabaseError> {
-        let table_oid_str = if table_name.contains('.') {
-            table_name.clone()
-        } else {
-            format!("public.\"{}\"", table_name)
-        };
+        let table_oid_str = qualified_name(SqlDialect::Postgres, schema.as_deref(), &table_name);
 
         let query = "
             SELECT 
@@ -363,9 +616,14 @@ impl DatabaseProvider for PostgresProvider {
                 pg_get_expr(ix.indpred, ix.indrelid) AS condition,
                 obj_description(i.oid, 'pg_class') AS comment,
                 (
-                    SELECT string_agg(a.attname, ', ' ORDER BY array_position(ix.indkey, a.attnum))
-                    FROM pg_attribute a
-                    WHERE a.attrelid = ix.indrelid AND a.attnum = ANY(ix.indkey)
+                    -- Expression parts have no attribute and show their text
+                    SELECT array_agg(
+                        COALESCE(a.attname::text, pg_get_indexdef(ix.indexrelid, k.n::int, true))
+                        ORDER BY k.n
+                    )
+                    FROM unnest(ix.indkey::int2[]) WITH ORDINALITY AS k(attnum, n)
+                    LEFT JOIN pg_attribute a
+                      ON a.attrelid = ix.indrelid AND a.attnum = k.attnum AND k.attnum > 0
                 ) as column_names
             FROM pg_index ix
             JOIN pg_class i ON i.oid = ix.indexrelid
@@ -376,10 +634,10 @@ impl DatabaseProvider for PostgresProvider {
 
         let rows = sqlx::query(query)
             .bind(&table_oid_str)
-            .fetch_all(&self.pool)
+            .fetch_all(&self.pool())
             .await
-            .map_err(|e| DatabaseError {
-                message: format!("Failed to get indexes for {}: {}", table_name, e),
+            .map_err(|e| {
+                DatabaseError::with_context(e, format!("Failed to get indexes for {}", table_name))
             })?;
 
         let mut results = Vec::new();
@@ -389,7 +647,9 @@ impl DatabaseProvider for PostgresProvider {
                 index_algorithm: row.get("index_algorithm"),
                 is_unique: row.get("is_unique"),
                 is_primary: row.get("is_primary"),
-                column_names: row.get("column_names"),
+                column_names: row
+                    .get::<Option<Vec<String>>, _>("column_names")
+                    .unwrap_or_default(),
                 condition: row.get("condition"),
                 comment: row.get("comment"),
             });
@@ -398,37 +658,424 @@ impl DatabaseProvider for PostgresProvider {
         Ok(results)
     }
 
-    async fn execute_query(&self, query: String) -> Result<QueryResult, DatabaseError> {
-        let rows = sqlx::query(&query)
-            .fetch_all(&self.pool)
+    async fn get_table_constraints(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+    ) -> Result<Vec<ConstraintDefinition>, DatabaseError> {
+        let table = qualified_name(SqlDialect::Postgres, schema.as_deref(), &table_name);
+
+        let query = "
+            SELECT
+                c.conname AS constraint_name,
+                c.contype::text AS contype,
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM unnest(c.conkey) WITH ORDINALITY AS k(attnum, n)
+                    JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.attnum
+                    ORDER BY k.n
+                ) AS column_names,
+                rn.nspname AS referenced_schema,
+                rc.relname AS referenced_table,
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM unnest(c.confkey) WITH ORDINALITY AS k(attnum, n)
+                    JOIN pg_attribute a ON a.attrelid = c.confrelid AND a.attnum = k.attnum
+                    ORDER BY k.n
+                ) AS referenced_columns,
+                CASE c.confupdtype
+                    WHEN 'a' THEN 'NO ACTION' WHEN 'r' THEN 'RESTRICT' WHEN 'c' THEN 'CASCADE'
+                    WHEN 'n' THEN 'SET NULL' WHEN 'd' THEN 'SET DEFAULT'
+                END AS on_update,
+                CASE c.confdeltype
+                    WHEN 'a' THEN 'NO ACTION' WHEN 'r' THEN 'RESTRICT' WHEN 'c' THEN 'CASCADE'
+                    WHEN 'n' THEN 'SET NULL' WHEN 'd' THEN 'SET DEFAULT'
+                END AS on_delete,
+                c.condeferrable AS is_deferrable,
+                c.condeferred AS initially_deferred,
+                CASE c.contype
+                    WHEN 'c' THEN pg_get_expr(c.conbin, c.conrelid, true)
+                    WHEN 'x' THEN pg_get_constraintdef(c.oid, true)
+                END AS check_clause
+            FROM pg_constraint c
+            LEFT JOIN pg_class rc ON rc.oid = c.confrelid
+            LEFT JOIN pg_namespace rn ON rn.oid = rc.relnamespace
+            WHERE c.conrelid = $1::regclass AND c.contype IN ('p', 'u', 'f', 'c', 'x')
+            ORDER BY array_position(ARRAY['p', 'u', 'f', 'c', 'x'], c.contype::text), c.conname;
+        ";
+
+        let rows = sqlx::query(query)
+            .bind(&table)
+            .fetch_all(&self.pool())
+            .await
+            .map_err(|e| {
+                DatabaseError::with_context(
+                    e,
+                    format!("Failed to get constraints for {}", table_name),
+                )
+            })?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            let contype: String = row.get("contype");
+            results.push(ConstraintDefinition {
+                constraint_name: row.get("constraint_name"),
+                kind: match contype.as_str() {
+                    "p" => ConstraintKind::PrimaryKey,
+                    "u" => ConstraintKind::Unique,
+                    "f" => ConstraintKind::ForeignKey,
+                    "c" => ConstraintKind::Check,
+                    _ => ConstraintKind::Exclusion,
+                },
+                column_names: row.get("column_names"),
+                referenced_schema: row.get("referenced_schema"),
+                referenced_table: row.get("referenced_table"),
+                referenced_columns: row.get("referenced_columns"),
+                on_update: row.get("on_update"),
+                on_delete: row.get("on_delete"),
+                is_deferrable: row.get("is_deferrable"),
+                initially_deferred: row.get("initially_deferred"),
+                check_clause: row.get("check_clause"),
+            });
+        }
+
+        Ok(results)
+    }
+
+    async fn get_incoming_foreign_keys(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+    ) -> Result<Vec<IncomingForeignKey>, DatabaseError> {
+        let table = qualified_name(SqlDialect::Postgres, schema.as_deref(), &table_name);
+
+        let query = "
+            SELECT
+                c.conname AS constraint_name,
+                n.nspname AS schema,
+                cl.relname AS table_name,
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM unnest(c.conkey) WITH ORDINALITY AS k(attnum, n)
+                    JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.attnum
+                    ORDER BY k.n
+                ) AS column_names,
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM unnest(c.confkey) WITH ORDINALITY AS k(attnum, n)
+                    JOIN pg_attribute a ON a.attrelid = c.confrelid AND a.attnum = k.attnum
+                    ORDER BY k.n
+                ) AS referenced_columns,
+                CASE c.confupdtype
+                    WHEN 'a' THEN 'NO ACTION' WHEN 'r' THEN 'RESTRICT' WHEN 'c' THEN 'CASCADE'
+                    WHEN 'n' THEN 'SET NULL' WHEN 'd' THEN 'SET DEFAULT'
+                END AS on_update,
+                CASE c.confdeltype
+                    WHEN 'a' THEN 'NO ACTION' WHEN 'r' THEN 'RESTRICT' WHEN 'c' THEN 'CASCADE'
+                    WHEN 'n' THEN 'SET NULL' WHEN 'd' THEN 'SET DEFAULT'
+                END AS on_delete
+            FROM pg_constraint c
+            JOIN pg_class cl ON cl.oid = c.conrelid
+            JOIN pg_namespace n ON n.oid = cl.relnamespace
+            WHERE c.confrelid = $1::regclass AND c.contype = 'f'
+            ORDER BY n.nspname, cl.relname, c.conname;
+        ";
+
+        let rows = sqlx::query(query)
+            .bind(&table)
+            .fetch_all(&self.pool())
+            .await
+            .map_err(|e| {
+                DatabaseError::with_context(
+                    e,
+                    format!("Failed to get references to {}", table_name),
+                )
+            })?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(IncomingForeignKey {
+                constraint_name: row.get("constraint_name"),
+                schema: row.get("schema"),
+                table_name: row.get("table_name"),
+                column_names: row.get("column_names"),
+                referenced_columns: row.get("referenced_columns"),
+                on_update: row.get("on_update"),
+                on_delete: row.get("on_delete"),
+            });
+        }
+
+        Ok(results)
+    }
+
+    async fn execute_query(
+        &self,
+        query: String,
+        query_id: Option<String>,
+        session_id: Option<String>,
+    ) -> Result<QueryResult, DatabaseError> {
+        let mut conn = self
+            .sessions
+            .acquire(&self.pool(), session_id.as_deref())
+            .await?;
+        let guard = self.track_query(&mut conn, query_id).await?;
+
+        run_statement(&mut conn, &query)
+            .await
+            .map_err(|e| map_cancelled(&guard, e))
+    }
+
+    async fn execute_query_stream(
+        &self,
+        query: String,
+        query_id: Option<String>,
+        session_id: Option<String>,
+        batch_size: usize,
+        max_rows: usize,
+        on_event: &(dyn Fn(QueryStreamEvent) -> bool + Send + Sync),
+    ) -> Result<(), DatabaseError> {
+        let mut conn = self
+            .sessions
+            .acquire(&self.pool(), session_id.as_deref())
+            .await?;
+        let guard = self.track_query(&mut conn, query_id).await?;
+
+        let statement = (&mut *conn)
+            .prepare(&query)
             .await
             .map_err(DatabaseError::from)?;
+        let rows = statement.query().fetch(&mut *conn);
+        stream_rows(
+            rows,
+            statement.columns(),
+            row_to_values,
+            batch_size,
+            max_rows,
+            on_event,
+        )
+        .await
+        .map_err(|e| map_cancelled(&guard, e))
+    }
 
-        let columns = if let Some(first_row) = rows.first() {
-            first_row
-                .columns()
-                .iter()
-                .map(|col| col.name().to_string())
-                .collect()
-        } else {
-            Vec::new()
+    async fn execute_script(
+        &self,
+        statements: Vec<Statement>,
+        stop_on_error: bool,
+        query_id: Option<String>,
+        session_id: Option<String>,
+    ) -> Result<Vec<StatementResult>, DatabaseError> {
+        let mut conn = self
+            .sessions
+            .acquire(&self.pool(), session_id.as_deref())
+            .await?;
+        let guard = self.track_query(&mut conn, query_id).await?;
+
+        let mut results = Vec::new();
+        for statement in statements {
+            let started = Instant::now();
+            let outcome = run_statement(&mut conn, &statement.sql)
+                .await
+                .map_err(|e| map_cancelled(&guard, e));
+
+            let stop = match &outcome {
+                Err(err) => stop_on_error || err.kind == DatabaseErrorKind::Cancelled,
+                Ok(_) => false,
+            };
+            results.push(StatementResult::new(statement, outcome, started.elapsed()));
+            if stop {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn cancel_query(&self, query_id: String) -> Result<bool, DatabaseError> {
+        let Some(backend_id) = self.running.mark_cancelled(&query_id) else {
+            return Ok(false);
         };
+        sqlx::query("SELECT pg_cancel_backend($1)")
+            .bind(backend_id as i32)
+            .execute(&self.pool())
+            .await
+            .map_err(DatabaseError::from)?;
+        Ok(true)
+    }
 
-        let result_rows: Vec<Vec<serde_json::Value>> =
-            rows.into_iter().map(row_to_values).collect();
+    async fn begin_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.begin(&self.pool(), session_id).await
+    }
 
-        Ok(QueryResult {
-            columns,
-            rows: result_rows,
-            total_rows: None,
-        })
+    async fn commit_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.commit(session_id).await
+    }
+
+    async fn rollback_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.rollback(session_id).await
+    }
+
+    async fn create_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.savepoint(session_id, name).await
+    }
+
+    async fn rollback_to_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.rollback_to_savepoint(session_id, name).await
+    }
+
+    async fn release_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.release_savepoint(session_id, name).await
+    }
+
+    fn transaction_state(&self, session_id: &str) -> TransactionState {
+        self.sessions.state(session_id)
+    }
+
+    async fn execute_dml(&self, statements: Vec<DmlStatement>) -> Result<u64, DatabaseError> {
+        let mut tx = self.pool().begin().await.map_err(DatabaseError::from)?;
+        let mut rows_affected = 0;
+        for statement in &statements {
+            let mut query = sqlx::query(&statement.sql);
+            for param in &statement.params {
+                query = query.bind(dml::value_as_text(param));
+            }
+            let affected = query
+                .execute(&mut *tx)
+                .await
+                .map_err(DatabaseError::from)?
+                .rows_affected();
+            if let Some(err) = dml::row_count_error(statement, affected) {
+                return Err(err);
+            }
+            rows_affected += affected;
+        }
+        tx.commit().await.map_err(DatabaseError::from)?;
+        Ok(rows_affected)
+    }
+
+    async fn bulk_insert(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        columns: Vec<String>,
+        rows: Vec<Vec<Value>>,
+    ) -> Result<u64, DatabaseError> {
+        let columns: Vec<String> = columns
+            .iter()
+            .map(|c| quote_identifier(SqlDialect::Postgres, c))
+            .collect();
+        let statement = format!(
+            "COPY {} ({}) FROM STDIN WITH (FORMAT csv)",
+            qualified_name(SqlDialect::Postgres, schema.as_deref(), &table_name),
+            columns.join(", ")
+        );
+
+        // In CSV format an unquoted empty field is NULL and a quoted one is ''
+        let mut data = String::new();
+        for row in &rows {
+            let fields: Vec<String> = row
+                .iter()
+                .map(|value| match dml::value_as_text(value) {
+                    Some(text) => format!("\"{}\"", text.replace('"', "\"\"")),
+                    None => String::new(),
+                })
+                .collect();
+            data.push_str(&fields.join(","));
+            data.push('\n');
+        }
+
+        // COPY runs as one statement, so the rows go in together or not at all
+        let mut conn = self.pool().acquire().await.map_err(DatabaseError::from)?;
+        let mut copy = conn
+            .copy_in_raw(&statement)
+            .await
+            .map_err(DatabaseError::from)?;
+        if let Err(e) = copy.send(data.into_bytes()).await {
+            let _ = copy.abort(e.to_string()).await;
+            return Err(DatabaseError::from(e));
+        }
+        copy.finish().await.map_err(DatabaseError::from)
+    }
+
+    async fn switch_database(&self, database: String) -> Result<String, DatabaseError> {
+        // A transaction is bound to its connection's database and cannot follow
+        if self.sessions.has_open() {
+            return Err(DatabaseError::with_kind(
+                DatabaseErrorKind::Transaction,
+                "Commit or roll back open transactions before switching databases",
+            ));
+        }
+
+        // Same host, port and credentials; through the tunnel if there is one
+        let old_pool = self.pool();
+        let options = old_pool
+            .connect_options()
+            .as_ref()
+            .clone()
+            .database(&database);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await
+            .map_err(DatabaseError::from)?;
+        *self.pool.write().unwrap() = pool;
+        // Queries still running on the old pool finish before it closes
+        tokio::spawn(async move { old_pool.close().await });
+
+        self.current_database().await
+    }
+
+    async fn current_database(&self) -> Result<String, DatabaseError> {
+        sqlx::query_scalar("SELECT current_database()")
+            .fetch_one(&self.pool())
+            .await
+            .map_err(DatabaseError::from)
+    }
+
+    async fn current_schema(&self) -> Result<Option<String>, DatabaseError> {
+        sqlx::query_scalar("SELECT current_schema()")
+            .fetch_one(&self.pool())
+            .await
+            .map_err(DatabaseError::from)
     }
 
     async fn get_database_schema(&self) -> Result<HashMap<String, Vec<String>>, DatabaseError> {
+        // Tables reachable through the search_path are listed by bare name,
+        // the rest qualified with their schema
         let rows = sqlx::query(
-            "SELECT table_name, column_name FROM information_schema.columns WHERE table_schema = 'public';",
+            "SELECT CASE WHEN pg_table_is_visible(c.oid) THEN c.relname
+                         ELSE n.nspname || '.' || c.relname END AS table_name,
+                    a.attname AS column_name
+             FROM pg_class c
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped
+             WHERE c.relkind IN ('r', 'v', 'm', 'f', 'p')
+               AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+               AND n.nspname NOT LIKE 'pg_toast%'
+               AND n.nspname NOT LIKE 'pg_temp_%'
+             ORDER BY table_name, a.attnum;",
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&self.pool())
         .await
         .map_err(DatabaseError::from)?;
 
@@ -438,16 +1085,15 @@ impl DatabaseProvider for PostgresProvider {
             let table_name: String = row.get("table_name");
             let column_name: String = row.get("column_name");
 
-            schema
-                .entry(table_name)
-                .or_insert_with(Vec::new)
-                .push(column_name);
+            schema.entry(table_name).or_default().push(column_name);
         }
 
         Ok(schema)
     }
 
-    async fn close(&self) {
-        self.pool.close().await;
+    async fn close(&self) -> Result<(), DatabaseError> {
+        let rolled_back = self.sessions.rollback_all().await;
+        self.pool().close().await;
+        rolled_back
     }
 }
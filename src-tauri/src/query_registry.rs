@@ -0,0 +1,69 @@
+use crate::db::DatabaseError;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+struct RunningQuery {
+    backend_id: u64,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Tracks which server-side connection each in-flight query is running on,
+/// so it can be cancelled from another connection.
+#[derive(Default)]
+pub struct QueryRegistry {
+    running: Mutex<HashMap<String, RunningQuery>>,
+}
+
+impl QueryRegistry {
+    /// Returns `None` when a query with the same id is still running, as
+    /// both would otherwise share one entry.
+    pub fn register(&self, query_id: &str, backend_id: u64) -> Option<QueryGuard<'_>> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        match self.running.lock().unwrap().entry(query_id.to_string()) {
+            Entry::Occupied(_) => return None,
+            Entry::Vacant(entry) => {
+                entry.insert(RunningQuery {
+                    backend_id,
+                    cancelled: cancelled.clone(),
+                });
+            }
+        }
+        Some(QueryGuard {
+            registry: self,
+            query_id: query_id.to_string(),
+            cancelled,
+        })
+    }
+
+    /// Flags the query as cancelled and returns the backend it runs on.
+    pub fn mark_cancelled(&self, query_id: &str) -> Option<u64> {
+        let running = self.running.lock().unwrap();
+        running.get(query_id).map(|query| {
+            query.cancelled.store(true, Ordering::SeqCst);
+            query.backend_id
+        })
+    }
+}
+
+/// Removes the query from the registry when it finishes, however it finishes.
+pub struct QueryGuard<'a> {
+    registry: &'a QueryRegistry,
+    query_id: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Drop for QueryGuard<'_> {
+    fn drop(&mut self) {
+        self.registry.running.lock().unwrap().remove(&self.query_id);
+    }
+}
+
+/// Replaces the server's error with a cancellation error if we asked for it.
+pub fn map_cancelled(guard: &Option<QueryGuard<'_>>, err: DatabaseError) -> DatabaseError {
+    match guard {
+        Some(guard) if guard.cancelled.load(Ordering::SeqCst) => DatabaseError::cancelled(),
+        _ => err,
+    }
+}
@@ -0,0 +1,338 @@
+use crate::db::{DatabaseError, DatabaseErrorKind};
+use chrono::{DateTime, Utc};
+use sqlx::pool::PoolConnection;
+use sqlx::{Database, Executor, Pool, Transaction};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Whether a session (editor tab) has a transaction open, for the UI indicator.
+#[derive(serde::Serialize)]
+pub struct TransactionState {
+    pub session_id: String,
+    pub active: bool,
+    pub savepoints: Vec<String>,
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+pub struct PinnedTransaction<DB: Database> {
+    tx: Transaction<'static, DB>,
+    savepoints: Vec<String>,
+    started_at: DateTime<Utc>,
+}
+
+type SessionSlot<DB> = Arc<AsyncMutex<Option<PinnedTransaction<DB>>>>;
+
+/// Open transactions keyed by session id. Each one holds its own pooled
+/// connection until it is committed or rolled back, so statements issued by
+/// separate commands all land on the same connection.
+pub struct Sessions<DB: Database> {
+    open: Mutex<HashMap<String, SessionSlot<DB>>>,
+}
+
+impl<DB: Database> Default for Sessions<DB> {
+    fn default() -> Self {
+        Sessions {
+            open: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// A connection to run a statement on: the session's pinned transaction if it
+/// has one, otherwise any connection from the pool.
+pub enum SessionConnection<DB: Database> {
+    Pooled(PoolConnection<DB>),
+    Pinned(OwnedMutexGuard<Option<PinnedTransaction<DB>>>),
+}
+
+impl<DB: Database> Deref for SessionConnection<DB> {
+    type Target = DB::Connection;
+
+    fn deref(&self) -> &DB::Connection {
+        match self {
+            SessionConnection::Pooled(conn) => conn,
+            SessionConnection::Pinned(guard) => &guard.as_ref().expect("session is open").tx,
+        }
+    }
+}
+
+impl<DB: Database> DerefMut for SessionConnection<DB> {
+    fn deref_mut(&mut self) -> &mut DB::Connection {
+        match self {
+            SessionConnection::Pooled(conn) => conn,
+            SessionConnection::Pinned(guard) => &mut guard.as_mut().expect("session is open").tx,
+        }
+    }
+}
+
+/// A session id claimed by `begin`, released again unless the transaction
+/// was started.
+struct Claim<'a, DB: Database> {
+    open: &'a Mutex<HashMap<String, SessionSlot<DB>>>,
+    session_id: &'a str,
+    slot: SessionSlot<DB>,
+    kept: bool,
+}
+
+impl<DB: Database> Claim<'_, DB> {
+    fn keep(mut self) {
+        self.kept = true;
+    }
+}
+
+impl<DB: Database> Drop for Claim<'_, DB> {
+    fn drop(&mut self) {
+        if self.kept {
+            return;
+        }
+        let mut open = self.open.lock().unwrap();
+        if open
+            .get(self.session_id)
+            .is_some_and(|s| Arc::ptr_eq(s, &self.slot))
+        {
+            open.remove(self.session_id);
+        }
+    }
+}
+
+fn no_transaction(session_id: &str) -> DatabaseError {
+    DatabaseError::with_kind(
+        DatabaseErrorKind::Transaction,
+        format!("No transaction is open for session {}", session_id),
+    )
+}
+
+/// Savepoint names are interpolated into SQL, so only plain identifiers are accepted.
+fn is_valid_savepoint(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl<DB: Database> Sessions<DB>
+where
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    fn slot(&self, session_id: &str) -> Option<SessionSlot<DB>> {
+        self.open.lock().unwrap().get(session_id).cloned()
+    }
+
+    pub async fn acquire(
+        &self,
+        pool: &Pool<DB>,
+        session_id: Option<&str>,
+    ) -> Result<SessionConnection<DB>, DatabaseError> {
+        if let Some(slot) = session_id.and_then(|id| self.slot(id)) {
+            let guard = slot.lock_owned().await;
+            // The transaction may have ended while we waited for the lock
+            if guard.is_some() {
+                return Ok(SessionConnection::Pinned(guard));
+            }
+        }
+        let conn = pool.acquire().await.map_err(DatabaseError::from)?;
+        Ok(SessionConnection::Pooled(conn))
+    }
+
+    pub fn state(&self, session_id: &str) -> TransactionState {
+        let slot = self.slot(session_id);
+        let pinned = slot.as_ref().and_then(|slot| slot.try_lock().ok());
+        match pinned.as_deref() {
+            Some(Some(pinned)) => TransactionState {
+                session_id: session_id.to_string(),
+                active: true,
+                savepoints: pinned.savepoints.clone(),
+                started_at: Some(pinned.started_at),
+            },
+            // Busy running a statement, so it is certainly still open
+            None if slot.is_some() => TransactionState {
+                session_id: session_id.to_string(),
+                active: true,
+                savepoints: Vec::new(),
+                started_at: None,
+            },
+            _ => TransactionState {
+                session_id: session_id.to_string(),
+                active: false,
+                savepoints: Vec::new(),
+                started_at: None,
+            },
+        }
+    }
+
+    pub async fn begin(
+        &self,
+        pool: &Pool<DB>,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        // Claim the session before starting the transaction so a concurrent
+        // begin for the same id sees it; statements wait on the held lock
+        let slot: SessionSlot<DB> = Arc::new(AsyncMutex::new(None));
+        let mut guard = slot
+            .clone()
+            .try_lock_owned()
+            .expect("new session slot is unlocked");
+        {
+            let mut open = self.open.lock().unwrap();
+            let claimed = open
+                .entry(session_id.clone())
+                .or_insert_with(|| slot.clone());
+            // A begin abandoned midway leaves an empty, unlocked slot behind
+            if claimed.try_lock().is_ok_and(|pinned| pinned.is_none()) {
+                *claimed = slot.clone();
+            }
+            if !Arc::ptr_eq(claimed, &slot) {
+                return Err(DatabaseError::with_kind(
+                    DatabaseErrorKind::Transaction,
+                    format!("A transaction is already open for session {}", session_id),
+                ));
+            }
+        }
+
+        // Gives the slot back if beginning fails or the caller goes away
+        let claim = Claim {
+            open: &self.open,
+            session_id: &session_id,
+            slot,
+            kept: false,
+        };
+        let tx = pool.begin().await.map_err(DatabaseError::from)?;
+        *guard = Some(PinnedTransaction {
+            tx,
+            savepoints: Vec::new(),
+            started_at: Utc::now(),
+        });
+        claim.keep();
+        drop(guard);
+        Ok(self.state(&session_id))
+    }
+
+    /// Removes the session and hands back its transaction, waiting for any
+    /// statement still running on it.
+    async fn take(&self, session_id: &str) -> Result<PinnedTransaction<DB>, DatabaseError> {
+        let slot = self
+            .open
+            .lock()
+            .unwrap()
+            .remove(session_id)
+            .ok_or_else(|| no_transaction(session_id))?;
+        let pinned = slot.lock().await.take();
+        pinned.ok_or_else(|| no_transaction(session_id))
+    }
+
+    pub async fn commit(&self, session_id: String) -> Result<TransactionState, DatabaseError> {
+        let pinned = self.take(&session_id).await?;
+        pinned.tx.commit().await.map_err(DatabaseError::from)?;
+        Ok(self.state(&session_id))
+    }
+
+    pub async fn rollback(&self, session_id: String) -> Result<TransactionState, DatabaseError> {
+        let pinned = self.take(&session_id).await?;
+        pinned.tx.rollback().await.map_err(DatabaseError::from)?;
+        Ok(self.state(&session_id))
+    }
+
+    pub async fn savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        let sql = format!("SAVEPOINT {}", name);
+        self.run_savepoint_statement(&session_id, &name, &sql, |savepoints| {
+            savepoints.push(name.clone())
+        })
+        .await?;
+        Ok(self.state(&session_id))
+    }
+
+    pub async fn rollback_to_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        let sql = format!("ROLLBACK TO SAVEPOINT {}", name);
+        self.run_savepoint_statement(&session_id, &name, &sql, |savepoints| {
+            // Savepoints created after this one are gone; this one survives
+            if let Some(pos) = savepoints.iter().rposition(|s| *s == name) {
+                savepoints.truncate(pos + 1);
+            }
+        })
+        .await?;
+        Ok(self.state(&session_id))
+    }
+
+    pub async fn release_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        let sql = format!("RELEASE SAVEPOINT {}", name);
+        self.run_savepoint_statement(&session_id, &name, &sql, |savepoints| {
+            if let Some(pos) = savepoints.iter().rposition(|s| *s == name) {
+                savepoints.truncate(pos);
+            }
+        })
+        .await?;
+        Ok(self.state(&session_id))
+    }
+
+    /// Runs a savepoint statement in the session's transaction and, if it
+    /// succeeds, applies the matching change to the savepoint stack.
+    async fn run_savepoint_statement(
+        &self,
+        session_id: &str,
+        name: &str,
+        sql: &str,
+        update: impl FnOnce(&mut Vec<String>),
+    ) -> Result<(), DatabaseError> {
+        if !is_valid_savepoint(name) {
+            return Err(DatabaseError::new(format!(
+                "Invalid savepoint name: {}",
+                name
+            )));
+        }
+        let slot = self
+            .slot(session_id)
+            .ok_or_else(|| no_transaction(session_id))?;
+        let mut guard = slot.lock().await;
+        let pinned = guard.as_mut().ok_or_else(|| no_transaction(session_id))?;
+        // Executor::execute hands back a boxed future, which keeps the
+        // providers' futures Send despite the generic connection type
+        (&mut *pinned.tx)
+            .execute(sql)
+            .await
+            .map_err(DatabaseError::from)?;
+        update(&mut pinned.savepoints);
+        Ok(())
+    }
+
+    /// Whether any session has a transaction open, or one being begun. A
+    /// slot left empty and unlocked holds no transaction and doesn't count.
+    pub fn has_open(&self) -> bool {
+        self.open
+            .lock()
+            .unwrap()
+            .values()
+            .any(|slot| !slot.try_lock().is_ok_and(|pinned| pinned.is_none()))
+    }
+
+    /// Rolls back every open transaction, used when the connection is closed.
+    /// All of them are attempted; the first failure is returned.
+    pub async fn rollback_all(&self) -> Result<(), DatabaseError> {
+        let slots: Vec<(String, SessionSlot<DB>)> = self.open.lock().unwrap().drain().collect();
+        let mut result = Ok(());
+        for (session_id, slot) in slots {
+            if let Some(pinned) = slot.lock().await.take() {
+                if let Err(e) = pinned.tx.rollback().await {
+                    if result.is_ok() {
+                        result = Err(DatabaseError::with_context(
+                            e,
+                            format!("Failed to roll back session {}", session_id),
+                        ));
+                    }
+                }
+            }
+        }
+        result
+    }
+}
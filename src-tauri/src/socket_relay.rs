@@ -0,0 +1,86 @@
+// The drivers check a server certificate against the host they are given, but
+// connect to their Unix socket instead when one is set. Relaying such a socket
+// to the address actually reachable, e.g. the local end of an SSH tunnel, lets
+// verify-full check the certificate against the database server's own name.
+
+use std::path::{Path, PathBuf};
+
+/// Forwards connections on a private Unix socket to a TCP address until
+/// dropped.
+pub struct SocketRelay {
+    dir: PathBuf,
+    path: PathBuf,
+    #[cfg(unix)]
+    task: tokio::task::JoinHandle<()>,
+}
+
+#[cfg(unix)]
+impl SocketRelay {
+    /// Listens in a new directory only this user can enter. The socket is
+    /// named the way Postgres looks it up for `port`; MySQL takes its path.
+    pub fn start(host: &str, port: u16) -> Result<Self, String> {
+        use std::os::unix::fs::DirBuilderExt;
+
+        // Socket paths are limited to about 100 bytes, so keep the name short
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let dir = std::env::temp_dir().join(format!("sql-tauri-{}", &id[..12]));
+        std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&dir)
+            .map_err(|e| format!("Failed to create the relay directory: {}", e))?;
+        let path = dir.join(format!(".s.PGSQL.{}", port));
+        let listener = match tokio::net::UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&dir);
+                return Err(format!("Failed to bind the relay socket: {}", e));
+            }
+        };
+
+        let target = (host.to_string(), port);
+        let task = tokio::spawn(async move {
+            while let Ok((mut local, _)) = listener.accept().await {
+                let target = target.clone();
+                tokio::spawn(async move {
+                    // A failed connect closes the socket, which the driver
+                    // reports as a connection error
+                    if let Ok(mut remote) = tokio::net::TcpStream::connect(target).await {
+                        let _ = tokio::io::copy_bidirectional(&mut local, &mut remote).await;
+                    }
+                });
+            }
+        });
+
+        Ok(Self { dir, path, task })
+    }
+}
+
+#[cfg(not(unix))]
+impl SocketRelay {
+    pub fn start(_host: &str, _port: u16) -> Result<Self, String> {
+        Err(
+            "Checking the certificate against a name other than the host connected to \
+             needs Unix sockets, which this platform doesn't have"
+                .to_string(),
+        )
+    }
+}
+
+impl SocketRelay {
+    /// The directory holding the socket, as Postgres takes it.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(unix)]
+impl Drop for SocketRelay {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,521 @@
+#[derive(serde::Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SqlDialect {
+    Postgres,
+    Mysql,
+    Sqlite,
+}
+
+/// A single statement cut out of a script, with where it started.
+/// `offset` is a character index; `line` and `column` are 1-based.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct Statement {
+    pub sql: String,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Splits a script into statements on the delimiter (`;` unless changed by a
+/// MySQL `DELIMITER` directive), ignoring delimiters inside string literals,
+/// quoted identifiers, comments and Postgres dollar-quoted bodies.
+/// Statements that contain nothing but comments are dropped.
+pub fn split_statements(script: &str, dialect: SqlDialect) -> Vec<Statement> {
+    let chars: Vec<char> = script.chars().collect();
+    let mut splitter = Splitter {
+        chars: &chars,
+        dialect,
+        statements: Vec::new(),
+        delimiter: vec![';'],
+        start: None,
+        words: Vec::new(),
+        cursor: (0, 1, 1),
+    };
+    splitter.run();
+    splitter.statements
+}
+
+struct Splitter<'a> {
+    chars: &'a [char],
+    dialect: SqlDialect,
+    statements: Vec<Statement>,
+    delimiter: Vec<char>,
+    /// Index of the first character of the statement being collected.
+    start: Option<usize>,
+    /// Upper-cased keywords/identifiers of the current statement, used to
+    /// detect compound bodies (`BEGIN ... END`) that contain semicolons.
+    words: Vec<String>,
+    /// Last (index, line, column) resolved, so positions are computed incrementally.
+    cursor: (usize, usize, usize),
+}
+
+impl Splitter<'_> {
+    fn run(&mut self) {
+        let chars = self.chars;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+
+            if self.start.is_none() {
+                if c.is_whitespace() {
+                    i += 1;
+                    continue;
+                }
+                if self.dialect == SqlDialect::Mysql && self.at_delimiter_directive(i) {
+                    i = self.read_delimiter_directive(i);
+                    continue;
+                }
+            }
+
+            if self.starts_line_comment(i) {
+                i = self.skip_line_comment(i);
+                continue;
+            }
+            if c == '/' && chars.get(i + 1) == Some(&'*') {
+                i = self.skip_block_comment(i);
+                continue;
+            }
+
+            if self.start.is_none() {
+                self.start = Some(i);
+            }
+
+            if chars[i..].starts_with(&self.delimiter) && !self.in_compound_block() {
+                self.finish(i);
+                i += self.delimiter.len();
+                continue;
+            }
+
+            i = match c {
+                '\'' => self.skip_quoted(i, '\'', self.backslash_escapes(i)),
+                '"' => self.skip_quoted(i, '"', self.dialect == SqlDialect::Mysql),
+                '`' if self.dialect != SqlDialect::Postgres => self.skip_quoted(i, '`', false),
+                '[' if self.dialect == SqlDialect::Sqlite => self.skip_until(i + 1, ']'),
+                '$' if self.dialect == SqlDialect::Postgres => self.skip_dollar_quoted(i),
+                c if is_word_char(c) => self.read_word(i),
+                _ => i + 1,
+            };
+        }
+
+        self.finish(chars.len());
+    }
+
+    fn finish(&mut self, end: usize) {
+        if let Some(start) = self.start.take() {
+            let sql: String = self.chars[start..end].iter().collect();
+            let sql = sql.trim_end().to_string();
+            if !sql.is_empty() {
+                let (line, column) = self.position(start);
+                self.statements.push(Statement {
+                    sql,
+                    offset: start,
+                    line,
+                    column,
+                });
+            }
+        }
+        self.words.clear();
+    }
+
+    fn position(&mut self, index: usize) -> (usize, usize) {
+        let (mut at, mut line, mut column) = self.cursor;
+        while at < index {
+            if self.chars[at] == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+            at += 1;
+        }
+        self.cursor = (at, line, column);
+        (line, column)
+    }
+
+    fn starts_line_comment(&self, i: usize) -> bool {
+        let chars = self.chars;
+        match chars[i] {
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                // MySQL only treats "--" as a comment when followed by whitespace
+                self.dialect != SqlDialect::Mysql
+                    || chars.get(i + 2).is_none_or(|c| c.is_whitespace())
+            }
+            '#' => self.dialect == SqlDialect::Mysql,
+            _ => false,
+        }
+    }
+
+    fn skip_line_comment(&self, i: usize) -> usize {
+        let mut j = i;
+        while j < self.chars.len() && self.chars[j] != '\n' {
+            j += 1;
+        }
+        j
+    }
+
+    fn skip_block_comment(&self, i: usize) -> usize {
+        let chars = self.chars;
+        // Postgres allows nested block comments
+        let nests = self.dialect == SqlDialect::Postgres;
+        let mut depth = 0;
+        let mut j = i;
+        while j < chars.len() {
+            if chars[j] == '/' && chars.get(j + 1) == Some(&'*') && (depth == 0 || nests) {
+                depth += 1;
+                j += 2;
+            } else if chars[j] == '*' && chars.get(j + 1) == Some(&'/') {
+                depth -= 1;
+                j += 2;
+                if depth == 0 {
+                    return j;
+                }
+            } else {
+                j += 1;
+            }
+        }
+        j
+    }
+
+    /// Whether a backslash escapes the next character in a string starting at `i`.
+    fn backslash_escapes(&self, i: usize) -> bool {
+        match self.dialect {
+            SqlDialect::Mysql => true,
+            // E'...' escape string syntax
+            SqlDialect::Postgres => {
+                i > 0
+                    && matches!(self.chars[i - 1], 'e' | 'E')
+                    && (i < 2 || !is_word_char(self.chars[i - 2]))
+            }
+            SqlDialect::Sqlite => false,
+        }
+    }
+
+    /// Skips a quoted string or identifier where the quote is escaped by doubling it.
+    fn skip_quoted(&self, i: usize, quote: char, backslash: bool) -> usize {
+        let chars = self.chars;
+        let mut j = i + 1;
+        while j < chars.len() {
+            if backslash && chars[j] == '\\' {
+                j += 2;
+            } else if chars[j] == quote {
+                if chars.get(j + 1) == Some(&quote) {
+                    j += 2;
+                } else {
+                    return j + 1;
+                }
+            } else {
+                j += 1;
+            }
+        }
+        j
+    }
+
+    fn skip_until(&self, i: usize, end: char) -> usize {
+        let mut j = i;
+        while j < self.chars.len() {
+            if self.chars[j] == end {
+                return j + 1;
+            }
+            j += 1;
+        }
+        j
+    }
+
+    /// Skips a `$tag$ ... $tag$` body. A `$` that does not open a tag (such
+    /// as a `$1` placeholder or part of an identifier) is consumed on its own.
+    fn skip_dollar_quoted(&self, i: usize) -> usize {
+        let chars = self.chars;
+        if i > 0 && is_word_char(chars[i - 1]) {
+            return i + 1;
+        }
+
+        let mut j = i + 1;
+        if chars.get(j).is_some_and(|c| c.is_ascii_digit()) {
+            return i + 1;
+        }
+        while j < chars.len() && is_word_char(chars[j]) {
+            j += 1;
+        }
+        if chars.get(j) != Some(&'$') {
+            return i + 1;
+        }
+
+        let tag = &chars[i..=j];
+        let mut k = j + 1;
+        while k < chars.len() {
+            if chars[k..].starts_with(tag) {
+                return k + tag.len();
+            }
+            k += 1;
+        }
+        k
+    }
+
+    fn read_word(&mut self, i: usize) -> usize {
+        let mut j = i;
+        while j < self.chars.len() && is_word_char(self.chars[j]) {
+            j += 1;
+        }
+        let word: String = self.chars[i..j].iter().collect();
+        self.words.push(word.to_uppercase());
+        j
+    }
+
+    /// SQLite triggers and Postgres `BEGIN ATOMIC` function bodies contain
+    /// semicolon-terminated statements that belong to the enclosing one.
+    fn in_compound_block(&self) -> bool {
+        let words: Vec<&str> = self.words.iter().map(String::as_str).collect();
+        let body_start = match self.dialect {
+            SqlDialect::Sqlite => {
+                let is_trigger = matches!(
+                    words.as_slice(),
+                    ["CREATE", "TRIGGER", ..] | ["CREATE", "TEMP" | "TEMPORARY", "TRIGGER", ..]
+                );
+                if !is_trigger {
+                    return false;
+                }
+                words.iter().position(|w| *w == "BEGIN")
+            }
+            SqlDialect::Postgres => words
+                .windows(2)
+                .position(|pair| pair == ["BEGIN", "ATOMIC"]),
+            SqlDialect::Mysql => None,
+        };
+
+        let Some(body_start) = body_start else {
+            return false;
+        };
+        let mut depth = 0;
+        for word in &words[body_start..] {
+            match *word {
+                "BEGIN" | "CASE" => depth += 1,
+                "END" => depth -= 1,
+                _ => {}
+            }
+        }
+        depth > 0
+    }
+
+    fn at_delimiter_directive(&self, i: usize) -> bool {
+        let keyword: Vec<char> = "DELIMITER".chars().collect();
+        let chars = self.chars;
+        chars.len() > i + keyword.len()
+            && chars[i..i + keyword.len()]
+                .iter()
+                .zip(&keyword)
+                .all(|(a, b)| a.to_ascii_uppercase() == *b)
+            && chars[i + keyword.len()].is_whitespace()
+            && (i == 0 || chars[i - 1] == '\n' || chars[i - 1].is_whitespace())
+    }
+
+    /// Applies a `DELIMITER <token>` line and returns the index after it.
+    fn read_delimiter_directive(&mut self, i: usize) -> usize {
+        let end = self.skip_line_comment(i);
+        let line: String = self.chars[i + "DELIMITER".len()..end].iter().collect();
+        if let Some(token) = line.split_whitespace().next() {
+            self.delimiter = token.chars().collect();
+        }
+        end
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sqls(script: &str, dialect: SqlDialect) -> Vec<String> {
+        split_statements(script, dialect)
+            .into_iter()
+            .map(|s| s.sql)
+            .collect()
+    }
+
+    #[test]
+    fn splits_on_semicolons_and_keeps_trailing_statement() {
+        assert_eq!(
+            sqls("select 1; select 2;\n\nselect 3", SqlDialect::Postgres),
+            ["select 1", "select 2", "select 3"]
+        );
+        assert!(sqls(" \n\t;; ", SqlDialect::Postgres).is_empty());
+    }
+
+    #[test]
+    fn reports_character_offset_line_and_column() {
+        let script = "select 'é';\n  -- note\n  select 2;";
+        let statements = split_statements(script, SqlDialect::Postgres);
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            (
+                statements[0].offset,
+                statements[0].line,
+                statements[0].column
+            ),
+            (0, 1, 1)
+        );
+        let second = &statements[1];
+        assert_eq!((second.offset, second.line, second.column), (24, 3, 3));
+        // Offsets count characters, not bytes
+        let rest: String = script.chars().skip(second.offset).collect();
+        assert!(rest.starts_with("select 2"));
+    }
+
+    #[test]
+    fn ignores_delimiters_in_strings_and_identifiers() {
+        assert_eq!(
+            sqls(r#"select 'a;''b', "c;d"; select 2"#, SqlDialect::Postgres),
+            [r#"select 'a;''b', "c;d""#, "select 2"]
+        );
+        assert_eq!(
+            sqls("select `a;b`, [c;d]; select 2", SqlDialect::Sqlite),
+            ["select `a;b`, [c;d]", "select 2"]
+        );
+        assert_eq!(
+            sqls("select `a;b`; select 2", SqlDialect::Mysql),
+            ["select `a;b`", "select 2"]
+        );
+    }
+
+    #[test]
+    fn backslash_escapes_depend_on_dialect() {
+        let script = r"select 'a\';b'; select 2";
+        assert_eq!(
+            sqls(script, SqlDialect::Mysql),
+            [r"select 'a\';b'", "select 2"]
+        );
+        assert_eq!(
+            sqls(script, SqlDialect::Sqlite),
+            [r"select 'a\'", "b'; select 2"]
+        );
+        assert_eq!(
+            sqls(r"select E'a\';b'; select 2", SqlDialect::Postgres),
+            [r"select E'a\';b'", "select 2"]
+        );
+        assert_eq!(
+            sqls(r"select e'a\';b'; select 2", SqlDialect::Postgres),
+            [r"select e'a\';b'", "select 2"]
+        );
+        assert_eq!(
+            sqls(r#"select "a\";b"; select 2"#, SqlDialect::Mysql),
+            [r#"select "a\";b""#, "select 2"]
+        );
+    }
+
+    #[test]
+    fn skips_comments() {
+        assert_eq!(
+            sqls("select 1 -- a; b\n; select /* ; */ 2", SqlDialect::Postgres),
+            ["select 1 -- a; b", "select /* ; */ 2"]
+        );
+        assert_eq!(
+            sqls("select 1 # a; b\n; select 2", SqlDialect::Mysql),
+            ["select 1 # a; b", "select 2"]
+        );
+        // "--" without whitespace after it is an operator pair in MySQL
+        assert_eq!(
+            sqls("select 1--1; select 2", SqlDialect::Mysql),
+            ["select 1--1", "select 2"]
+        );
+    }
+
+    #[test]
+    fn block_comments_nest_only_in_postgres() {
+        let script = "select /* a /* b; */ c; */ 1; select 2";
+        assert_eq!(
+            sqls(script, SqlDialect::Postgres),
+            ["select /* a /* b; */ c; */ 1", "select 2"]
+        );
+        assert_eq!(
+            sqls(script, SqlDialect::Sqlite),
+            ["select /* a /* b; */ c", "*/ 1", "select 2"]
+        );
+    }
+
+    #[test]
+    fn drops_comment_only_statements() {
+        assert_eq!(
+            sqls(
+                "-- header\n;\n/* nothing */;\nselect 1;",
+                SqlDialect::Postgres
+            ),
+            ["select 1"]
+        );
+    }
+
+    #[test]
+    fn keeps_dollar_quoted_bodies_together() {
+        let script = "create function f() returns int as $$ select 1; $$ language sql;\n\
+                      do $body$ begin perform 1; end $body$;\n\
+                      select $1, a$b; select 2";
+        assert_eq!(
+            sqls(script, SqlDialect::Postgres),
+            [
+                "create function f() returns int as $$ select 1; $$ language sql",
+                "do $body$ begin perform 1; end $body$",
+                "select $1, a$b",
+                "select 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_begin_atomic_bodies_together() {
+        let script = "create function f() returns int language sql\n\
+                      begin atomic select 1; select case when true then 2 end; end;\n\
+                      select 3;";
+        assert_eq!(
+            sqls(script, SqlDialect::Postgres),
+            [
+                "create function f() returns int language sql\n\
+                 begin atomic select 1; select case when true then 2 end; end",
+                "select 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_sqlite_trigger_bodies_together() {
+        let script = "create temp trigger t after insert on a begin\n\
+                      insert into b values (1);\n\
+                      update c set x = case when new.y then 1 else 2 end;\n\
+                      end;\n\
+                      select 1;";
+        let statements = split_statements(script, SqlDialect::Sqlite);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].sql.ends_with("else 2 end;\nend"));
+        assert_eq!((statements[1].line, statements[1].column), (5, 1));
+        // Without a trigger, BEGIN is just a transaction
+        assert_eq!(
+            sqls("begin; select 1; end;", SqlDialect::Sqlite),
+            ["begin", "select 1", "end"]
+        );
+    }
+
+    #[test]
+    fn applies_mysql_delimiter_directives() {
+        let script = "DELIMITER //\n\
+                      CREATE PROCEDURE p() BEGIN SELECT 1; SELECT 2; END//\n\
+                      delimiter ;\n\
+                      SELECT 3;";
+        let statements = split_statements(script, SqlDialect::Mysql);
+        let sqls: Vec<&str> = statements.iter().map(|s| s.sql.as_str()).collect();
+        assert_eq!(
+            sqls,
+            [
+                "CREATE PROCEDURE p() BEGIN SELECT 1; SELECT 2; END",
+                "SELECT 3"
+            ]
+        );
+        assert_eq!((statements[0].offset, statements[0].line), (13, 2));
+        assert_eq!((statements[1].line, statements[1].column), (4, 1));
+        // Only MySQL knows the directive
+        assert_eq!(
+            split_statements("DELIMITER //\nselect 1", SqlDialect::Postgres).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,1098 @@
+use crate::database_provider::DatabaseProvider;
+use crate::db::{
+    collect_results, stream_rows, ColumnDefinition, ConstraintDefinition, ConstraintKind,
+    DatabaseError, DatabaseErrorKind, DatabaseObject, Filter, IncomingForeignKey, IndexDefinition,
+    ObjectKind, QueryResult, QueryStreamEvent, Sort, StatementResult, TableQuery,
+    DEFAULT_STREAM_BATCH_SIZE,
+};
+use crate::dml::{self, qualified_name, quote_identifier, DmlStatement};
+use crate::session::{Sessions, TransactionState};
+use crate::sql_splitter::{SqlDialect, Statement};
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::query::Query;
+use sqlx::sqlite::{
+    SqliteArguments, SqliteConnectOptions, SqliteConnection, SqlitePoolOptions, SqliteRow,
+};
+use sqlx::{Column, Executor, Pool, Row, Sqlite, Statement as _, TypeInfo, ValueRef};
+use std::collections::HashMap;
+use std::time::Instant;
+
+pub struct SqliteProvider {
+    pool: Pool<Sqlite>,
+    sessions: Sessions<Sqlite>,
+}
+
+impl SqliteProvider {
+    pub async fn new(file_path: &str) -> Result<Self, DatabaseError> {
+        let options = SqliteConnectOptions::new().filename(file_path);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await
+            .map_err(DatabaseError::from)?;
+        Ok(Self {
+            pool,
+            sessions: Sessions::default(),
+        })
+    }
+
+    /// A rowid alias (INTEGER PRIMARY KEY) has no index, only table_info.pk.
+    async fn primary_key_columns(
+        &self,
+        schema: Option<&str>,
+        table_name: &str,
+    ) -> Result<Vec<String>, DatabaseError> {
+        sqlx::query_scalar("SELECT name FROM pragma_table_info(?, ?) WHERE pk > 0 ORDER BY pk;")
+            .bind(table_name)
+            .bind(schema)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DatabaseError::from)
+    }
+
+    /// The FROM/WHERE and ORDER BY parts shared by paging and exporting a table.
+    fn table_query(
+        &self,
+        schema: Option<&str>,
+        table_name: &str,
+        filters: &[Filter],
+        sorts: &[Sort],
+    ) -> TableQuery {
+        let mut where_clauses = Vec::new();
+        let mut query_params = Vec::new();
+
+        for filter in filters {
+            let field = quote_identifier(SqlDialect::Sqlite, &filter.field);
+
+            match filter.operator.as_str() {
+                "=" | ">=" | "<=" | ">" | "<" => {
+                    where_clauses.push(format!("{} {} ?", field, filter.operator));
+                    query_params.push(filter.value.clone());
+                }
+                "contain" => {
+                    where_clauses.push(format!("{} LIKE ?", field));
+                    query_params.push(format!("%{}%", filter.value));
+                }
+                "start with" => {
+                    where_clauses.push(format!("{} LIKE ?", field));
+                    query_params.push(format!("{}%", filter.value));
+                }
+                "end with" => {
+                    where_clauses.push(format!("{} LIKE ?", field));
+                    query_params.push(format!("%{}", filter.value));
+                }
+                "not null" => {
+                    where_clauses.push(format!("{} IS NOT NULL", field));
+                }
+                "is null" => {
+                    where_clauses.push(format!("{} IS NULL", field));
+                }
+                _ => {}
+            }
+        }
+
+        let where_sql = if where_clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", where_clauses.join(" AND "))
+        };
+
+        let mut order_clauses = Vec::new();
+        for sort in sorts {
+            let direction = if sort.order.to_uppercase() == "DESC" {
+                "DESC"
+            } else {
+                "ASC"
+            };
+            order_clauses.push(format!(
+                "{} {}",
+                quote_identifier(SqlDialect::Sqlite, &sort.field),
+                direction
+            ));
+        }
+
+        let order_sql = if order_clauses.is_empty() {
+            String::new()
+        } else {
+            format!("ORDER BY {}", order_clauses.join(", "))
+        };
+
+        let table = qualified_name(SqlDialect::Sqlite, schema, table_name);
+
+        TableQuery {
+            from: format!("{} {}", table, where_sql),
+            order_by: order_sql,
+            params: query_params,
+        }
+    }
+}
+
+/// The catalog table of the given attached database.
+fn sqlite_master(schema: Option<&str>) -> String {
+    match schema {
+        Some(schema) => format!(
+            "{}.sqlite_master",
+            quote_identifier(SqlDialect::Sqlite, schema)
+        ),
+        None => "sqlite_master".to_string(),
+    }
+}
+
+/// The predicate after the `WHERE` keyword of a CREATE INDEX statement. The
+/// keyword is matched as a whole word outside quotes and comments, and on
+/// bytes so the slice stays on a char boundary.
+fn partial_index_condition(sql: &str) -> Option<String> {
+    let bytes = sql.as_bytes();
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || !b.is_ascii();
+    let skip_to = |from: usize, end: &[u8]| {
+        bytes[from..]
+            .windows(end.len())
+            .position(|w| w == end)
+            .map_or(bytes.len(), |p| from + p + end.len())
+    };
+
+    let mut i = 0;
+    while i < bytes.len() {
+        i = match bytes[i] {
+            // A doubled quote ends the literal and starts another one
+            quote @ (b'\'' | b'"' | b'`') => skip_to(i + 1, &[quote]),
+            b'[' => skip_to(i + 1, b"]"),
+            b'-' if bytes.get(i + 1) == Some(&b'-') => skip_to(i + 2, b"\n"),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => skip_to(i + 2, b"*/"),
+            _ if (i == 0 || !is_word(bytes[i - 1]))
+                && bytes.len() >= i + 5
+                && bytes[i..i + 5].eq_ignore_ascii_case(b"WHERE")
+                && !matches!(bytes.get(i + 5), Some(&b) if is_word(b)) =>
+            {
+                return Some(sql[i + 5..].trim().to_string()).filter(|c| !c.is_empty());
+            }
+            _ => i + 1,
+        };
+    }
+    None
+}
+
+/// The key parts of an index from the JSON array of their names, where
+/// parts over an expression have no name.
+fn index_columns(names: Option<String>) -> Vec<String> {
+    let names: Vec<Option<String>> = names
+        .and_then(|names| serde_json::from_str(&names).ok())
+        .unwrap_or_default();
+    names
+        .into_iter()
+        .map(|name| name.unwrap_or_else(|| "(expression)".to_string()))
+        .collect()
+}
+
+/// SQLite's default limit on bound parameters per statement.
+const MAX_BIND_PARAMS: usize = 32_766;
+
+fn bind_value<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    value: &Value,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(b) => query.bind(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => query.bind(i),
+            None => query.bind(n.as_f64()),
+        },
+        Value::String(s) => query.bind(s.clone()),
+        other => query.bind(other.to_string()),
+    }
+}
+
+fn row_to_values(row: SqliteRow) -> Vec<serde_json::Value> {
+    let mut values = Vec::new();
+    for (i, col) in row.columns().iter().enumerate() {
+        // Expression columns have no declared type, so fall back to the
+        // storage class of the actual value.
+        let declared = col.type_info().name().to_string();
+        let type_name = if declared == "NULL" {
+            match row.try_get_raw(i) {
+                Ok(raw) if !raw.is_null() => raw.type_info().name().to_string(),
+                _ => declared,
+            }
+        } else {
+            declared
+        };
+
+        let value = match type_name.as_str() {
+            "NULL" => serde_json::Value::Null,
+            "BOOLEAN" => {
+                let val: Option<bool> = row.try_get(i).unwrap_or(None);
+                serde_json::json!(val)
+            }
+            "INTEGER" => {
+                let val: Option<i64> = row.try_get(i).unwrap_or(None);
+                serde_json::json!(val)
+            }
+            "REAL" => {
+                let val: Option<f64> = row.try_get(i).unwrap_or(None);
+                serde_json::json!(val)
+            }
+            "TEXT" => {
+                let val: Option<String> = row.try_get(i).unwrap_or(None);
+                serde_json::json!(val)
+            }
+            "DATETIME" => {
+                if let Ok(val) = row.try_get::<Option<chrono::NaiveDateTime>, _>(i) {
+                    serde_json::json!(val)
+                } else {
+                    let val: Option<String> = row.try_get(i).unwrap_or(None);
+                    serde_json::json!(val)
+                }
+            }
+            "DATE" => {
+                if let Ok(val) = row.try_get::<Option<chrono::NaiveDate>, _>(i) {
+                    serde_json::json!(val)
+                } else {
+                    let val: Option<String> = row.try_get(i).unwrap_or(None);
+                    serde_json::json!(val)
+                }
+            }
+            "TIME" => {
+                if let Ok(val) = row.try_get::<Option<chrono::NaiveTime>, _>(i) {
+                    serde_json::json!(val)
+                } else {
+                    let val: Option<String> = row.try_get(i).unwrap_or(None);
+                    serde_json::json!(val)
+                }
+            }
+            _ => {
+                // NUMERIC affinity may hold either integers or reals
+                if let Ok(val) = row.try_get::<Option<i64>, _>(i) {
+                    serde_json::json!(val)
+                } else if let Ok(val) = row.try_get::<Option<f64>, _>(i) {
+                    serde_json::json!(val)
+                } else if let Ok(val) = row.try_get::<String, _>(i) {
+                    serde_json::Value::String(val)
+                } else {
+                    serde_json::Value::String(format!("Unsupported Type: {}", type_name))
+                }
+            }
+        };
+        values.push(value);
+    }
+    values
+}
+
+/// Runs a single statement, capturing its rows or affected-row count and how long it took.
+async fn run_statement(
+    conn: &mut SqliteConnection,
+    sql: &str,
+) -> Result<QueryResult, DatabaseError> {
+    let started = Instant::now();
+    // Preparing first tells us the result columns even when no rows come back
+    let statement = conn.prepare(sql).await.map_err(DatabaseError::from)?;
+    let columns = statement
+        .columns()
+        .iter()
+        .map(|col| col.name().to_string())
+        .collect();
+    let mut result = collect_results(
+        conn.fetch_many(statement.query()),
+        columns,
+        row_to_values,
+        |done| (done.rows_affected(), None),
+    )
+    .await?;
+    result.execution_time_ms = Some(started.elapsed().as_millis() as u64);
+    Ok(result)
+}
+
+#[async_trait]
+impl DatabaseProvider for SqliteProvider {
+    fn dialect(&self) -> SqlDialect {
+        SqlDialect::Sqlite
+    }
+
+    async fn list_databases(&self) -> Result<Vec<String>, DatabaseError> {
+        let rows = sqlx::query("SELECT name FROM pragma_database_list ORDER BY seq;")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DatabaseError::from)?;
+
+        let databases: Vec<String> = rows.iter().map(|row| row.get("name")).collect();
+        Ok(databases)
+    }
+
+    async fn list_schemas(&self) -> Result<Vec<String>, DatabaseError> {
+        // Schemas are the main database plus any attached ones
+        self.list_databases().await
+    }
+
+    async fn list_tables(&self, schema: Option<String>) -> Result<Vec<String>, DatabaseError> {
+        let query = format!(
+            "SELECT name FROM {} WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name;",
+            sqlite_master(schema.as_deref())
+        );
+        let rows = sqlx::query(&query)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DatabaseError::from)?;
+
+        let tables: Vec<String> = rows.iter().map(|row: &SqliteRow| row.get("name")).collect();
+        Ok(tables)
+    }
+
+    async fn list_objects(
+        &self,
+        schema: Option<String>,
+    ) -> Result<Vec<DatabaseObject>, DatabaseError> {
+        let query = format!(
+            "SELECT type, name, tbl_name FROM {}
+             WHERE type IN ('table', 'view', 'trigger') AND name NOT LIKE 'sqlite_%'
+             ORDER BY name;",
+            sqlite_master(schema.as_deref())
+        );
+        let rows = sqlx::query(&query)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DatabaseError::from)?;
+
+        let objects = rows
+            .iter()
+            .map(|row: &SqliteRow| {
+                let kind: String = row.get("type");
+                let (kind, table_name) = match kind.as_str() {
+                    "view" => (ObjectKind::View, None),
+                    "trigger" => (ObjectKind::Trigger, Some(row.get("tbl_name"))),
+                    _ => (ObjectKind::Table, None),
+                };
+                DatabaseObject {
+                    name: row.get("name"),
+                    kind,
+                    table_name,
+                    arguments: None,
+                }
+            })
+            .collect();
+        Ok(objects)
+    }
+
+    async fn get_object_ddl(
+        &self,
+        schema: Option<String>,
+        object: DatabaseObject,
+    ) -> Result<String, DatabaseError> {
+        let object_type = match object.kind {
+            ObjectKind::Table => "table",
+            ObjectKind::View => "view",
+            ObjectKind::Trigger => "trigger",
+            _ => {
+                return Err(DatabaseError::new(format!(
+                    "SQLite has no {:?} objects",
+                    object.kind
+                )))
+            }
+        };
+        // SQLite keeps the original CREATE statement; a table's indexes are
+        // stored separately (implicit ones have no SQL)
+        let query = format!(
+            "SELECT sql FROM {} WHERE sql IS NOT NULL
+               AND ((type = ?1 AND name = ?2) OR (?1 = 'table' AND type = 'index' AND tbl_name = ?2))
+             ORDER BY type = 'index', name;",
+            sqlite_master(schema.as_deref())
+        );
+        let statements: Vec<String> = sqlx::query_scalar(&query)
+            .bind(object_type)
+            .bind(&object.name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(DatabaseError::from)?;
+
+        if statements.is_empty() {
+            return Err(DatabaseError::with_kind(
+                DatabaseErrorKind::UndefinedObject,
+                format!("{} does not exist", object.name),
+            ));
+        }
+        Ok(statements
+            .iter()
+            .map(|sql| format!("{};", sql))
+            .collect::<Vec<_>>()
+            .join("\n\n"))
+    }
+
+    async fn get_table_data(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        limit: i64,
+        offset: i64,
+        filters: Vec<Filter>,
+        sorts: Vec<Sort>,
+    ) -> Result<QueryResult, DatabaseError> {
+        let TableQuery {
+            from,
+            order_by,
+            params: query_params,
+        } = self.table_query(schema.as_deref(), &table_name, &filters, &sorts);
+
+        let count_query = format!("SELECT COUNT(*) FROM {}", from);
+        let mut count_q = sqlx::query_as::<_, (i64,)>(&count_query);
+        for param in &query_params {
+            count_q = count_q.bind(param);
+        }
+        let count_row = count_q
+            .fetch_one(&self.pool)
+            .await
+            .map_err(DatabaseError::from)?;
+        let total_rows = count_row.0;
+
+        let query = format!("SELECT * FROM {} {} LIMIT ? OFFSET ?", from, order_by);
+
+        let mut q = sqlx::query(&query);
+        for param in &query_params {
+            q = q.bind(param);
+        }
+        q = q.bind(limit).bind(offset);
+
+        let rows = q.fetch_all(&self.pool).await.map_err(DatabaseError::from)?;
+
+        let columns = if let Some(first_row) = rows.first() {
+            first_row
+                .columns()
+                .iter()
+                .map(|col| col.name().to_string())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let result_rows: Vec<Vec<serde_json::Value>> =
+            rows.into_iter().map(row_to_values).collect();
+
+        Ok(QueryResult {
+            columns,
+            rows: result_rows,
+            total_rows: Some(total_rows),
+            ..Default::default()
+        })
+    }
+
+    async fn stream_table_data(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        filters: Vec<Filter>,
+        sorts: Vec<Sort>,
+        _query_id: Option<String>,
+        on_event: &(dyn Fn(QueryStreamEvent) -> bool + Send + Sync),
+    ) -> Result<(), DatabaseError> {
+        let TableQuery {
+            from,
+            order_by,
+            params,
+        } = self.table_query(schema.as_deref(), &table_name, &filters, &sorts);
+        let query = format!("SELECT * FROM {} {}", from, order_by);
+
+        let mut conn = self.pool.acquire().await.map_err(DatabaseError::from)?;
+
+        let statement = (&mut *conn)
+            .prepare(&query)
+            .await
+            .map_err(DatabaseError::from)?;
+        let mut q = statement.query();
+        for param in &params {
+            q = q.bind(param);
+        }
+        let rows = q.fetch(&mut *conn);
+        stream_rows(
+            rows,
+            statement.columns(),
+            row_to_values,
+            DEFAULT_STREAM_BATCH_SIZE,
+            usize::MAX,
+            on_event,
+        )
+        .await
+    }
+
+    async fn get_table_structure(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+    ) -> Result<Vec<ColumnDefinition>, DatabaseError> {
+        // A NULL schema makes the pragma functions search like an unqualified name
+        let column_rows = sqlx::query(
+            "SELECT name, type, \"notnull\", dflt_value FROM pragma_table_info(?, ?) ORDER BY cid;",
+        )
+        .bind(&table_name)
+        .bind(&schema)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            DatabaseError::with_context(e, format!("Failed to get structure for {}", table_name))
+        })?;
+
+        let fk_rows = sqlx::query(
+            "SELECT \"from\", \"table\", \"to\" FROM pragma_foreign_key_list(?, ?) ORDER BY id, seq;",
+        )
+        .bind(&table_name)
+        .bind(&schema)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            DatabaseError::with_context(e, format!("Failed to get foreign keys for {}", table_name))
+        })?;
+
+        let mut foreign_keys: HashMap<String, String> = HashMap::new();
+        for row in fk_rows {
+            let from: String = row.get("from");
+            let table: String = row.get("table");
+            // "to" is NULL when the key references the parent's primary key implicitly
+            let to: Option<String> = row.get("to");
+            let reference = match to {
+                Some(to) => format!("{}({})", table, to),
+                None => table,
+            };
+            foreign_keys.entry(from).or_insert(reference);
+        }
+
+        let mut results = Vec::new();
+        for row in column_rows {
+            let column_name: String = row.get("name");
+            let not_null: i64 = row.get("notnull");
+            results.push(ColumnDefinition {
+                foreign_key: foreign_keys.get(&column_name).cloned(),
+                column_name,
+                data_type: row.get("type"),
+                is_nullable: if not_null != 0 { "NO" } else { "YES" }.to_string(),
+                column_default: row.get("dflt_value"),
+                comment: None,
+            });
+        }
+
+        Ok(results)
+    }
+
+    async fn get_table_indexes(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+    ) -> Result<Vec<IndexDefinition>, DatabaseError> {
+        let query = format!(
+            "SELECT l.name AS index_name, l.\"unique\" AS is_unique, l.origin AS origin,
+                    l.partial AS partial, m.sql AS sql,
+                    (SELECT json_group_array(i.name)
+                     FROM (SELECT name FROM pragma_index_info(l.name, ?2) ORDER BY seqno) i
+                    ) AS column_names
+             FROM pragma_index_list(?1, ?2) l
+             LEFT JOIN {} m ON m.type = 'index' AND m.name = l.name
+             ORDER BY l.origin = 'pk' DESC, l.name;",
+            sqlite_master(schema.as_deref())
+        );
+        let rows = sqlx::query(&query)
+            .bind(&table_name)
+            .bind(&schema)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::with_context(e, format!("Failed to get indexes for {}", table_name))
+            })?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            let is_unique: i64 = row.get("is_unique");
+            let origin: String = row.get("origin");
+            let partial: i64 = row.get("partial");
+            let sql: Option<String> = row.get("sql");
+
+            // Partial indexes keep their predicate only in the CREATE INDEX text
+            let condition = if partial != 0 {
+                sql.as_deref().and_then(partial_index_condition)
+            } else {
+                None
+            };
+
+            results.push(IndexDefinition {
+                index_name: row.get("index_name"),
+                index_algorithm: "BTREE".to_string(),
+                is_unique: is_unique != 0,
+                is_primary: origin == "pk",
+                column_names: index_columns(row.get("column_names")),
+                condition,
+                comment: None,
+            });
+        }
+
+        // An INTEGER PRIMARY KEY aliases the rowid and has no backing index,
+        // so report it from the column metadata instead.
+        if !results.iter().any(|index| index.is_primary) {
+            let pk_rows =
+                sqlx::query("SELECT name FROM pragma_table_info(?, ?) WHERE pk > 0 ORDER BY pk;")
+                    .bind(&table_name)
+                    .bind(&schema)
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(DatabaseError::from)?;
+
+            if !pk_rows.is_empty() {
+                let column_names: Vec<String> = pk_rows.iter().map(|row| row.get("name")).collect();
+                results.insert(
+                    0,
+                    IndexDefinition {
+                        index_name: "PRIMARY".to_string(),
+                        index_algorithm: "BTREE".to_string(),
+                        is_unique: true,
+                        is_primary: true,
+                        column_names,
+                        condition: None,
+                        comment: None,
+                    },
+                );
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn get_table_constraints(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+    ) -> Result<Vec<ConstraintDefinition>, DatabaseError> {
+        // SQLite keeps neither constraint names nor CHECK expressions outside
+        // the CREATE TABLE text, so keys are named after their backing index
+        // (or numbered) and checks are not listed
+        let unique_rows = sqlx::query(
+            "SELECT l.name AS index_name, i.name AS column_name
+             FROM pragma_index_list(?1, ?2) l
+             JOIN pragma_index_info(l.name, ?2) i
+             WHERE l.origin = 'u'
+             ORDER BY l.name, i.seqno;",
+        )
+        .bind(&table_name)
+        .bind(&schema)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            DatabaseError::with_context(e, format!("Failed to get constraints for {}", table_name))
+        })?;
+
+        let pk_columns = self
+            .primary_key_columns(schema.as_deref(), &table_name)
+            .await?;
+
+        let fk_rows = sqlx::query(
+            "SELECT id, \"from\", \"table\", \"to\", on_update, on_delete
+             FROM pragma_foreign_key_list(?, ?) ORDER BY id, seq;",
+        )
+        .bind(&table_name)
+        .bind(&schema)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            DatabaseError::with_context(e, format!("Failed to get foreign keys for {}", table_name))
+        })?;
+
+        let empty = |constraint_name: String, kind: ConstraintKind| ConstraintDefinition {
+            constraint_name,
+            kind,
+            column_names: Vec::new(),
+            referenced_schema: None,
+            referenced_table: None,
+            referenced_columns: Vec::new(),
+            on_update: None,
+            on_delete: None,
+            is_deferrable: false,
+            initially_deferred: false,
+            check_clause: None,
+        };
+
+        let mut results: Vec<ConstraintDefinition> = Vec::new();
+        if !pk_columns.is_empty() {
+            let mut primary = empty("PRIMARY".to_string(), ConstraintKind::PrimaryKey);
+            primary.column_names = pk_columns;
+            results.push(primary);
+        }
+        for row in unique_rows {
+            let index_name: String = row.get("index_name");
+            let constraint = match results.last_mut() {
+                Some(last) if last.constraint_name == index_name => last,
+                _ => {
+                    results.push(empty(index_name, ConstraintKind::Unique));
+                    results.last_mut().unwrap()
+                }
+            };
+            constraint.column_names.push(row.get("column_name"));
+        }
+
+        let mut last_fk_id = None;
+        for row in fk_rows {
+            let id: i64 = row.get("id");
+            if last_fk_id != Some(id) {
+                last_fk_id = Some(id);
+                let mut foreign_key = empty(
+                    format!("{}_fkey{}", table_name, id),
+                    ConstraintKind::ForeignKey,
+                );
+                foreign_key.referenced_schema = schema.clone();
+                foreign_key.referenced_table = Some(row.get("table"));
+                foreign_key.on_update = Some(row.get("on_update"));
+                foreign_key.on_delete = Some(row.get("on_delete"));
+                results.push(foreign_key);
+            }
+            let foreign_key = results.last_mut().unwrap();
+            foreign_key.column_names.push(row.get("from"));
+            // "to" is NULL when the key references the parent's primary key implicitly
+            if let Some(to) = row.get::<Option<String>, _>("to") {
+                foreign_key.referenced_columns.push(to);
+            }
+        }
+        for foreign_key in &mut results {
+            if let (ConstraintKind::ForeignKey, Some(parent), true) = (
+                foreign_key.kind,
+                &foreign_key.referenced_table,
+                foreign_key.referenced_columns.is_empty(),
+            ) {
+                foreign_key.referenced_columns =
+                    self.primary_key_columns(schema.as_deref(), parent).await?;
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn get_incoming_foreign_keys(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+    ) -> Result<Vec<IncomingForeignKey>, DatabaseError> {
+        // Foreign keys cannot cross attached databases, so only this one's tables are checked
+        let query = format!(
+            "SELECT m.name AS table_name, f.id AS id, f.\"from\" AS column_name,
+                    f.\"to\" AS referenced_column, f.on_update AS on_update,
+                    f.on_delete AS on_delete
+             FROM {} m
+             JOIN pragma_foreign_key_list(m.name, ?1) f
+             WHERE m.type = 'table' AND f.\"table\" = ?2 COLLATE NOCASE
+             ORDER BY m.name, f.id, f.seq;",
+            sqlite_master(schema.as_deref())
+        );
+        let rows = sqlx::query(&query)
+            .bind(&schema)
+            .bind(&table_name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| {
+                DatabaseError::with_context(
+                    e,
+                    format!("Failed to get references to {}", table_name),
+                )
+            })?;
+
+        let mut results: Vec<IncomingForeignKey> = Vec::new();
+        let mut last_key = None;
+        for row in rows {
+            let child: String = row.get("table_name");
+            let id: i64 = row.get("id");
+            if last_key.as_ref() != Some(&(child.clone(), id)) {
+                last_key = Some((child.clone(), id));
+                results.push(IncomingForeignKey {
+                    constraint_name: format!("{}_fkey{}", child, id),
+                    schema: schema.clone(),
+                    table_name: child,
+                    column_names: Vec::new(),
+                    referenced_columns: Vec::new(),
+                    on_update: Some(row.get("on_update")),
+                    on_delete: Some(row.get("on_delete")),
+                });
+            }
+            let key = results.last_mut().unwrap();
+            key.column_names.push(row.get("column_name"));
+            if let Some(to) = row.get::<Option<String>, _>("referenced_column") {
+                key.referenced_columns.push(to);
+            }
+        }
+
+        if results.iter().any(|key| key.referenced_columns.is_empty()) {
+            let pk_columns = self
+                .primary_key_columns(schema.as_deref(), &table_name)
+                .await?;
+            for key in &mut results {
+                if key.referenced_columns.is_empty() {
+                    key.referenced_columns = pk_columns.clone();
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn execute_query(
+        &self,
+        query: String,
+        _query_id: Option<String>,
+        session_id: Option<String>,
+    ) -> Result<QueryResult, DatabaseError> {
+        let mut conn = self
+            .sessions
+            .acquire(&self.pool, session_id.as_deref())
+            .await?;
+        run_statement(&mut conn, &query).await
+    }
+
+    async fn execute_query_stream(
+        &self,
+        query: String,
+        _query_id: Option<String>,
+        session_id: Option<String>,
+        batch_size: usize,
+        max_rows: usize,
+        on_event: &(dyn Fn(QueryStreamEvent) -> bool + Send + Sync),
+    ) -> Result<(), DatabaseError> {
+        let mut conn = self
+            .sessions
+            .acquire(&self.pool, session_id.as_deref())
+            .await?;
+        let statement = (&mut *conn)
+            .prepare(&query)
+            .await
+            .map_err(DatabaseError::from)?;
+        let rows = statement.query().fetch(&mut *conn);
+        stream_rows(
+            rows,
+            statement.columns(),
+            row_to_values,
+            batch_size,
+            max_rows,
+            on_event,
+        )
+        .await
+    }
+
+    async fn execute_script(
+        &self,
+        statements: Vec<Statement>,
+        stop_on_error: bool,
+        _query_id: Option<String>,
+        session_id: Option<String>,
+    ) -> Result<Vec<StatementResult>, DatabaseError> {
+        let mut conn = self
+            .sessions
+            .acquire(&self.pool, session_id.as_deref())
+            .await?;
+
+        let mut results = Vec::new();
+        for statement in statements {
+            let started = Instant::now();
+            let outcome = run_statement(&mut conn, &statement.sql).await;
+
+            let failed = outcome.is_err();
+            results.push(StatementResult::new(statement, outcome, started.elapsed()));
+            if failed && stop_on_error {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn cancel_query(&self, _query_id: String) -> Result<bool, DatabaseError> {
+        // Statements run in-process on the connection's worker thread and
+        // cannot be interrupted from another connection.
+        Err(DatabaseError::new(
+            "Cancelling queries is not supported for SQLite",
+        ))
+    }
+
+    async fn begin_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.begin(&self.pool, session_id).await
+    }
+
+    async fn commit_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.commit(session_id).await
+    }
+
+    async fn rollback_transaction(
+        &self,
+        session_id: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.rollback(session_id).await
+    }
+
+    async fn create_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.savepoint(session_id, name).await
+    }
+
+    async fn rollback_to_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.rollback_to_savepoint(session_id, name).await
+    }
+
+    async fn release_savepoint(
+        &self,
+        session_id: String,
+        name: String,
+    ) -> Result<TransactionState, DatabaseError> {
+        self.sessions.release_savepoint(session_id, name).await
+    }
+
+    fn transaction_state(&self, session_id: &str) -> TransactionState {
+        self.sessions.state(session_id)
+    }
+
+    async fn execute_dml(&self, statements: Vec<DmlStatement>) -> Result<u64, DatabaseError> {
+        let mut tx = self.pool.begin().await.map_err(DatabaseError::from)?;
+        let mut rows_affected = 0;
+        for statement in &statements {
+            let mut query = sqlx::query(&statement.sql);
+            for param in &statement.params {
+                query = bind_value(query, param);
+            }
+            let affected = query
+                .execute(&mut *tx)
+                .await
+                .map_err(DatabaseError::from)?
+                .rows_affected();
+            if let Some(err) = dml::row_count_error(statement, affected) {
+                return Err(err);
+            }
+            rows_affected += affected;
+        }
+        tx.commit().await.map_err(DatabaseError::from)?;
+        Ok(rows_affected)
+    }
+
+    async fn bulk_insert(
+        &self,
+        schema: Option<String>,
+        table_name: String,
+        columns: Vec<String>,
+        rows: Vec<Vec<Value>>,
+    ) -> Result<u64, DatabaseError> {
+        let table = qualified_name(SqlDialect::Sqlite, schema.as_deref(), &table_name);
+        let columns: Vec<String> = columns
+            .iter()
+            .map(|c| quote_identifier(SqlDialect::Sqlite, c))
+            .collect();
+        let row_sql = format!("({})", vec!["?"; columns.len()].join(", "));
+        let rows_per_statement = (MAX_BIND_PARAMS / columns.len().max(1)).max(1);
+
+        let mut tx = self.pool.begin().await.map_err(DatabaseError::from)?;
+        let mut rows_affected = 0;
+        for chunk in rows.chunks(rows_per_statement) {
+            let sql = format!(
+                "INSERT INTO {} ({}) VALUES {}",
+                table,
+                columns.join(", "),
+                vec![row_sql.as_str(); chunk.len()].join(", ")
+            );
+            let mut query = sqlx::query(&sql);
+            for value in chunk.iter().flatten() {
+                query = bind_value(query, value);
+            }
+            rows_affected += query
+                .execute(&mut *tx)
+                .await
+                .map_err(DatabaseError::from)?
+                .rows_affected();
+        }
+        tx.commit().await.map_err(DatabaseError::from)?;
+        Ok(rows_affected)
+    }
+
+    async fn switch_database(&self, _database: String) -> Result<String, DatabaseError> {
+        // Each SQLite database is its own file, opened as its own connection
+        Err(DatabaseError::new(
+            "Open the other database file as a separate SQLite connection",
+        ))
+    }
+
+    async fn current_database(&self) -> Result<String, DatabaseError> {
+        Ok("main".to_string())
+    }
+
+    async fn current_schema(&self) -> Result<Option<String>, DatabaseError> {
+        // Foreign keys into the main database carry no schema
+        Ok(None)
+    }
+
+    async fn get_database_schema(&self) -> Result<HashMap<String, Vec<String>>, DatabaseError> {
+        let rows = sqlx::query(
+            "SELECT m.name AS table_name, p.name AS column_name
+             FROM sqlite_master m
+             JOIN pragma_table_info(m.name) p
+             WHERE m.type IN ('table', 'view') AND m.name NOT LIKE 'sqlite_%'
+             ORDER BY m.name, p.cid;",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(DatabaseError::from)?;
+
+        let mut schema: HashMap<String, Vec<String>> = HashMap::new();
+
+        for row in rows {
+            let table_name: String = row.get("table_name");
+            let column_name: String = row.get("column_name");
+
+            schema.entry(table_name).or_default().push(column_name);
+        }
+
+        Ok(schema)
+    }
+
+    async fn close(&self) -> Result<(), DatabaseError> {
+        let rolled_back = self.sessions.rollback_all().await;
+        self.pool.close().await;
+        rolled_back
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_partial_index_predicate() {
+        let condition = partial_index_condition;
+        assert_eq!(
+            condition("CREATE UNIQUE INDEX i ON t (a) WHERE deleted = 0").as_deref(),
+            Some("deleted = 0")
+        );
+        assert_eq!(
+            condition("CREATE UNIQUE INDEX i ON t(a) WHERE(deleted = 0)").as_deref(),
+            Some("(deleted = 0)")
+        );
+        assert_eq!(
+            condition("create unique index i on t(a)\nwhere\"gone\" IS NULL").as_deref(),
+            Some("\"gone\" IS NULL")
+        );
+        assert_eq!(
+            condition("CREATE UNIQUE INDEX \"x WHERE y\" ON t(\"where\", [a where]) WHERE a > 1")
+                .as_deref(),
+            Some("a > 1")
+        );
+    }
+
+    #[test]
+    fn ignores_where_inside_names_strings_and_comments() {
+        for sql in [
+            "CREATE UNIQUE INDEX nowhere ON t(somewhere)",
+            "CREATE UNIQUE INDEX i ON t(a COLLATE \"WHERE\")",
+            "CREATE UNIQUE INDEX i ON t(a) -- WHERE b",
+            "CREATE UNIQUE INDEX i /* WHERE */ ON t(a)",
+            "CREATE UNIQUE INDEX i ON t(a, 'WHERE')",
+        ] {
+            assert_eq!(partial_index_condition(sql), None, "{}", sql);
+        }
+    }
+}
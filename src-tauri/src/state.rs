@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::{AppHandle, Manager};
 
-use crate::connection_manager::SavedConnection;
+use crate::connection_manager::{self, SavedConnection};
+use crate::credentials::{self, CredentialStore, Secrets};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppStateData {
@@ -60,6 +61,15 @@ fn get_state_file_path(app: &AppHandle) -> Result<PathBuf, String> {
 pub async fn save_app_state(app: AppHandle, state: AppStateData) -> Result<(), String> {
     let state_file = get_state_file_path(&app)?;
 
+    // Passwords are kept in the credential store, not with the open spaces,
+    // once there is one to keep them in
+    let mut state = state;
+    if state_file.parent().is_some_and(credentials::is_initialized) {
+        for space in &mut state.spaces {
+            Secrets::take(&mut space.config);
+        }
+    }
+
     let json = serde_json::to_string_pretty(&state)
         .map_err(|e| format!("Failed to serialize state: {}", e))?;
 
@@ -85,3 +95,36 @@ pub async fn load_app_state(app: AppHandle) -> Result<Option<AppStateData>, Stri
 
     Ok(Some(state))
 }
+
+/// Moves passwords saved with the open spaces by earlier versions into the
+/// unlocked credential store, unless it already has some for that connection,
+/// and removes them from the state file.
+pub fn strip_plaintext_secrets(dir: &Path, credentials: &CredentialStore) -> Result<(), String> {
+    let state_file = dir.join("app_state.json");
+    if !state_file.exists() {
+        return Ok(());
+    }
+
+    let json =
+        fs::read_to_string(&state_file).map_err(|e| format!("Failed to read state file: {}", e))?;
+    let mut state: AppStateData =
+        serde_json::from_str(&json).map_err(|e| format!("Failed to deserialize state: {}", e))?;
+    if !state
+        .spaces
+        .iter()
+        .any(|space| connection_manager::has_secrets(&space.config))
+    {
+        return Ok(());
+    }
+
+    for space in &mut state.spaces {
+        let secrets = Secrets::take(&mut space.config);
+        if !secrets.is_empty() && credentials.get(dir, &space.config.id)?.is_none() {
+            credentials.set(dir, &space.config.id, &secrets)?;
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&state)
+        .map_err(|e| format!("Failed to serialize state: {}", e))?;
+    fs::write(&state_file, json).map_err(|e| format!("Failed to write state file: {}", e))
+}
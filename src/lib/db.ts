@@ -1,7 +1,29 @@
-import { invoke } from "@tauri-apps/api/core";
+import { Channel, invoke } from "@tauri-apps/api/core";
+import { open, save } from "@tauri-apps/plugin-dialog";
+
+export type DatabaseErrorKind =
+  | "connection"
+  | "syntax"
+  | "undefined_object"
+  | "constraint"
+  | "permission"
+  | "data"
+  | "transaction"
+  | "timeout"
+  | "cancelled"
+  | "other";
 
 export interface DatabaseError {
+  kind: DatabaseErrorKind;
   message: string;
+  code?: string;
+  position?: number;
+  detail?: string;
+  hint?: string;
+  schema?: string;
+  table?: string;
+  column?: string;
+  constraint?: string;
 }
 
 export interface SavedConnection {
@@ -12,15 +34,41 @@ export interface SavedConnection {
   username: string;
   password?: string;
   database: string;
-  db_type: "postgres" | "mysql";
+  db_type: "postgres" | "mysql" | "sqlite";
+  file_path?: string;
   ssh_enabled: boolean;
   ssh_host?: string;
   ssh_port?: number;
   ssh_user?: string;
   ssh_password?: string;
   ssh_key_path?: string;
+  ssl_mode?: SslMode;
+  /** CA certificate file (PEM) the server's certificate must chain to. */
+  ssl_root_cert?: string;
+  ssl_client_cert?: string;
+  ssl_client_key?: string;
+  /**
+   * The name verify-full checks the certificate against, when it isn't
+   * `host`. Behind an SSH tunnel `host` is checked rather than the tunnel's
+   * local address.
+   */
+  ssl_server_name?: string;
+  /**
+   * Driver and session settings such as `application_name`,
+   * `statement_timeout` or MySQL's `charset` and `max_execution_time`.
+   */
+  extra_params?: Record<string, string>;
 }
 
+/** libpq's sslmode values; MySQL's VERIFY_IDENTITY is "verify-full". */
+export type SslMode =
+  | "disable"
+  | "allow"
+  | "prefer"
+  | "require"
+  | "verify-ca"
+  | "verify-full";
+
 export async function connectDb(
   connectionId: string,
   connectionConfig: SavedConnection
@@ -36,14 +84,72 @@ export async function listDatabases(connectionId: string) {
   return await invoke<string[]>("list_databases", { connectionId });
 }
 
-export async function listTables(connectionId: string) {
-  return await invoke<string[]>("list_tables", { connectionId });
+/** Moves the connection to another database and returns the one now in use. */
+export async function switchDatabase(connectionId: string, database: string) {
+  return await invoke<string>("switch_database", { connectionId, database });
+}
+
+export async function getCurrentDatabase(connectionId: string) {
+  return await invoke<string>("get_current_database", { connectionId });
+}
+
+export async function listSchemas(connectionId: string) {
+  return await invoke<string[]>("list_schemas", { connectionId });
+}
+
+/** Without a schema, lists the tables of the server's default schema. */
+export async function listTables(connectionId: string, schema?: string) {
+  return await invoke<string[]>("list_tables", { connectionId, schema });
+}
+
+export type ObjectKind =
+  | "table"
+  | "view"
+  | "materialized_view"
+  | "sequence"
+  | "function"
+  | "procedure"
+  | "trigger"
+  | "type"
+  | "extension";
+
+export interface DatabaseObject {
+  name: string;
+  kind: ObjectKind;
+  /** Table a trigger is defined on. */
+  table_name?: string;
+  /** Argument list of a function or procedure (Postgres only). */
+  arguments?: string;
+}
+
+export async function listObjects(connectionId: string, schema?: string) {
+  return await invoke<DatabaseObject[]>("list_objects", {
+    connectionId,
+    schema,
+  });
+}
+
+/** The statements that would recreate an object from `listObjects`. */
+export async function getObjectDdl(
+  connectionId: string,
+  object: DatabaseObject,
+  schema?: string
+) {
+  return await invoke<string>("get_object_ddl", {
+    connectionId,
+    schema,
+    object,
+  });
 }
 
 export interface QueryResult {
   columns: string[];
   rows: any[][];
   total_rows?: number;
+  rows_affected?: number;
+  last_insert_id?: number;
+  notices: string[];
+  execution_time_ms?: number;
 }
 
 export interface Filter {
@@ -63,10 +169,12 @@ export async function getTableData(
   limit: number,
   offset: number,
   filters: Filter[] = [],
-  sorts: Sort[] = []
+  sorts: Sort[] = [],
+  schema?: string
 ): Promise<QueryResult> {
   return await invoke("get_table_data", {
     connectionId,
+    schema,
     tableName,
     limit,
     offset,
@@ -89,30 +197,713 @@ export interface IndexDefinition {
   index_algorithm: string;
   is_unique: boolean;
   is_primary: boolean;
-  column_names: string;
+  column_names: string[];
   condition?: string;
   comment?: string;
 }
 
 export async function getTableStructure(
   connectionId: string,
-  tableName: string
+  tableName: string,
+  schema?: string
 ) {
   return await invoke<ColumnDefinition[]>("get_table_structure", {
     connectionId,
+    schema,
     tableName,
   });
 }
 
-export async function getTableIndexes(connectionId: string, tableName: string) {
+export async function getTableIndexes(
+  connectionId: string,
+  tableName: string,
+  schema?: string
+) {
   return await invoke<IndexDefinition[]>("get_table_indexes", {
     connectionId,
+    schema,
+    tableName,
+  });
+}
+
+export type ConstraintKind =
+  | "primary_key"
+  | "unique"
+  | "foreign_key"
+  | "check"
+  | "exclusion";
+
+export interface ConstraintDefinition {
+  constraint_name: string;
+  kind: ConstraintKind;
+  column_names: string[];
+  referenced_schema?: string;
+  referenced_table?: string;
+  referenced_columns: string[];
+  on_update?: string;
+  on_delete?: string;
+  is_deferrable: boolean;
+  initially_deferred: boolean;
+  check_clause?: string;
+}
+
+export async function getTableConstraints(
+  connectionId: string,
+  tableName: string,
+  schema?: string
+) {
+  return await invoke<ConstraintDefinition[]>("get_table_constraints", {
+    connectionId,
+    schema,
+    tableName,
+  });
+}
+
+export interface IncomingForeignKey {
+  constraint_name: string;
+  schema?: string;
+  table_name: string;
+  column_names: string[];
+  referenced_columns: string[];
+  on_update?: string;
+  on_delete?: string;
+}
+
+export async function getIncomingForeignKeys(
+  connectionId: string,
+  tableName: string,
+  schema?: string
+) {
+  return await invoke<IncomingForeignKey[]>("get_incoming_foreign_keys", {
+    connectionId,
+    schema,
     tableName,
   });
 }
 
-export async function executeQuery(connectionId: string, query: string) {
-  return await invoke<QueryResult>("execute_query", { connectionId, query });
+export type RelationDirection = "parent" | "child";
+
+/** The `getTableData` arguments that select rows related to a given row. */
+export interface RelatedRows {
+  direction: RelationDirection;
+  constraint_name: string;
+  schema?: string;
+  table_name: string;
+  filters: Filter[];
+}
+
+export async function getRelatedRows(
+  connectionId: string,
+  tableName: string,
+  row: Record<string, any>,
+  schema?: string
+) {
+  return await invoke<RelatedRows[]>("get_related_rows", {
+    connectionId,
+    schema,
+    tableName,
+    row,
+  });
+}
+
+export interface GraphColumn {
+  name: string;
+  data_type: string;
+  is_nullable: boolean;
+  is_primary_key: boolean;
+  is_foreign_key: boolean;
+}
+
+export interface GraphTable {
+  name: string;
+  columns: GraphColumn[];
+}
+
+export interface GraphRelationship {
+  constraint_name: string;
+  from_table: string;
+  from_columns: string[];
+  to_schema?: string;
+  to_table: string;
+  to_columns: string[];
+  optional: boolean;
+  one_to_one: boolean;
+}
+
+export interface SchemaGraph {
+  schema?: string;
+  tables: GraphTable[];
+  relationships: GraphRelationship[];
+}
+
+export type DiagramFormat = "mermaid" | "dot" | "plantuml";
+
+export async function getSchemaGraph(connectionId: string, schema?: string) {
+  return await invoke<SchemaGraph>("get_schema_graph", {
+    connectionId,
+    schema,
+  });
+}
+
+export async function exportSchemaDiagram(
+  connectionId: string,
+  format: DiagramFormat,
+  schema?: string
+) {
+  return await invoke<string>("export_schema_diagram", {
+    connectionId,
+    schema,
+    format,
+  });
+}
+
+export type ExportSource =
+  | {
+      kind: "table";
+      schema?: string;
+      table_name: string;
+      filters?: Filter[];
+      sorts?: Sort[];
+    }
+  | { kind: "query"; query: string; session_id?: string };
+
+export interface CsvOptions {
+  delimiter?: string;
+  quote?: string;
+  header?: boolean;
+  quote_all?: boolean;
+  null_value?: string;
+}
+
+export type ExportFormat =
+  | ({ format: "csv" } & CsvOptions)
+  | { format: "json" }
+  | { format: "ndjson" }
+  | { format: "sql_insert"; table_name?: string; rows_per_statement?: number }
+  | { format: "xlsx" }
+  | { format: "parquet" };
+
+export type ExportEvent =
+  | { event: "progress"; data: { rows_written: number } }
+  | { event: "finished"; data: { rows_written: number } };
+
+const EXPORT_EXTENSIONS: Record<ExportFormat["format"], string> = {
+  csv: "csv",
+  json: "json",
+  ndjson: "ndjson",
+  sql_insert: "sql",
+  xlsx: "xlsx",
+  parquet: "parquet",
+};
+
+/**
+ * Asks for a destination file and streams the rows into it. Resolves to the
+ * number of rows written, or null when the save dialog is dismissed.
+ */
+export async function exportData(
+  connectionId: string,
+  source: ExportSource,
+  format: ExportFormat,
+  onProgress: (event: ExportEvent) => void,
+  queryId?: string
+): Promise<number | null> {
+  const extension = EXPORT_EXTENSIONS[format.format];
+  const path = await save({
+    defaultPath: `${source.kind === "table" ? source.table_name : "query"}.${extension}`,
+    filters: [{ name: extension.toUpperCase(), extensions: [extension] }],
+  });
+  if (!path) return null;
+
+  const channel = new Channel<ExportEvent>();
+  channel.onmessage = onProgress;
+  return await invoke<number>("export_data", {
+    connectionId,
+    source,
+    format,
+    path,
+    queryId,
+    onProgress: channel,
+  });
+}
+
+export type ImportFormat =
+  | ({ format: "csv" } & CsvOptions)
+  | { format: "ndjson" };
+
+export interface ColumnMapping {
+  source: string;
+  target: string;
+}
+
+export interface RowError {
+  line: number;
+  message: string;
+}
+
+export interface ImportPreview {
+  columns: string[];
+  rows: any[][];
+  errors: RowError[];
+}
+
+export interface ImportSummary {
+  rows_read: number;
+  rows_imported: number;
+  error_count: number;
+  errors: RowError[];
+}
+
+export type ImportEvent = {
+  event: "progress";
+  data: { rows_read: number; rows_imported: number; error_count: number };
+};
+
+/** Asks for a CSV or NDJSON file to import; null when the dialog is dismissed. */
+export async function chooseImportFile(): Promise<string | null> {
+  return await open({
+    multiple: false,
+    directory: false,
+    filters: [
+      {
+        name: "CSV or NDJSON",
+        extensions: ["csv", "tsv", "txt", "ndjson", "jsonl"],
+      },
+    ],
+  });
+}
+
+export async function previewImport(
+  path: string,
+  format: ImportFormat,
+  limit?: number
+) {
+  return await invoke<ImportPreview>("preview_import", { path, format, limit });
+}
+
+export async function importData(
+  connectionId: string,
+  tableName: string,
+  path: string,
+  format: ImportFormat,
+  mapping: ColumnMapping[],
+  onProgress: (event: ImportEvent) => void,
+  schema?: string
+): Promise<ImportSummary> {
+  const channel = new Channel<ImportEvent>();
+  channel.onmessage = onProgress;
+  return await invoke<ImportSummary>("import_data", {
+    connectionId,
+    schema,
+    tableName,
+    path,
+    format,
+    mapping,
+    onProgress: channel,
+  });
+}
+
+export interface DumpOptions {
+  /** Tables to dump; every table of the schema when omitted. */
+  tables?: string[];
+  include_schema?: boolean;
+  include_data?: boolean;
+  drop_existing?: boolean;
+  rows_per_statement?: number;
+}
+
+export type DumpEvent = {
+  event: "progress";
+  data: {
+    table: string;
+    tables_done: number;
+    table_count: number;
+    rows_written: number;
+  };
+};
+
+export interface DumpSummary {
+  tables: string[];
+  rows_written: number;
+}
+
+export type RestoreEvent = {
+  event: "progress";
+  data: { statements_run: number; bytes_read: number; total_bytes: number };
+};
+
+export interface RestoreSummary {
+  statements_run: number;
+}
+
+/**
+ * Asks for a destination file and writes the schema's DDL and data to it as
+ * SQL. Resolves to null when the save dialog is dismissed.
+ */
+export async function dumpDatabase(
+  connectionId: string,
+  options: DumpOptions,
+  onProgress: (event: DumpEvent) => void,
+  queryId?: string,
+  schema?: string
+): Promise<DumpSummary | null> {
+  const path = await save({
+    defaultPath: `${schema ?? "dump"}.sql`,
+    filters: [{ name: "SQL", extensions: ["sql"] }],
+  });
+  if (!path) return null;
+
+  const channel = new Channel<DumpEvent>();
+  channel.onmessage = onProgress;
+  return await invoke<DumpSummary>("dump_database", {
+    connectionId,
+    schema,
+    path,
+    options,
+    queryId,
+    onProgress: channel,
+  });
+}
+
+/**
+ * Asks for a SQL file and runs it in a single transaction. Resolves to null
+ * when the dialog is dismissed.
+ */
+export async function restoreDatabase(
+  connectionId: string,
+  onProgress: (event: RestoreEvent) => void,
+  queryId?: string
+): Promise<RestoreSummary | null> {
+  const path = await open({
+    multiple: false,
+    directory: false,
+    filters: [{ name: "SQL", extensions: ["sql"] }],
+  });
+  if (!path) return null;
+
+  const channel = new Channel<RestoreEvent>();
+  channel.onmessage = onProgress;
+  return await invoke<RestoreSummary>("restore_database", {
+    connectionId,
+    path,
+    queryId,
+    onProgress: channel,
+  });
+}
+
+export interface RowUpdate {
+  /** Original values identifying the row; must cover a primary or unique key. */
+  key: Record<string, any>;
+  values: Record<string, any>;
+}
+
+export async function updateRows(
+  connectionId: string,
+  tableName: string,
+  updates: RowUpdate[],
+  schema?: string
+) {
+  return await invoke<number>("update_rows", {
+    connectionId,
+    schema,
+    tableName,
+    updates,
+  });
+}
+
+export async function insertRows(
+  connectionId: string,
+  tableName: string,
+  rows: Record<string, any>[],
+  schema?: string
+) {
+  return await invoke<number>("insert_rows", {
+    connectionId,
+    schema,
+    tableName,
+    rows,
+  });
+}
+
+export async function deleteRows(
+  connectionId: string,
+  tableName: string,
+  keys: Record<string, any>[],
+  schema?: string
+) {
+  return await invoke<number>("delete_rows", {
+    connectionId,
+    schema,
+    tableName,
+    keys,
+  });
+}
+
+export interface TableChanges {
+  updates: RowUpdate[];
+  inserts: Record<string, any>[];
+  deletes: Record<string, any>[];
+}
+
+export type ChangeKind = "update" | "insert" | "delete";
+
+export async function stageRowUpdate(
+  connectionId: string,
+  tableName: string,
+  update: RowUpdate,
+  schema?: string
+) {
+  return await invoke<TableChanges>("stage_row_update", {
+    connectionId,
+    schema,
+    tableName,
+    update,
+  });
+}
+
+export async function stageRowInsert(
+  connectionId: string,
+  tableName: string,
+  row: Record<string, any>,
+  schema?: string
+) {
+  return await invoke<TableChanges>("stage_row_insert", {
+    connectionId,
+    schema,
+    tableName,
+    row,
+  });
+}
+
+export async function stageRowDelete(
+  connectionId: string,
+  tableName: string,
+  key: Record<string, any>,
+  schema?: string
+) {
+  return await invoke<TableChanges>("stage_row_delete", {
+    connectionId,
+    schema,
+    tableName,
+    key,
+  });
+}
+
+export async function unstageChange(
+  connectionId: string,
+  tableName: string,
+  kind: ChangeKind,
+  index: number,
+  schema?: string
+) {
+  return await invoke<TableChanges>("unstage_change", {
+    connectionId,
+    schema,
+    tableName,
+    kind,
+    index,
+  });
+}
+
+export async function getPendingChanges(
+  connectionId: string,
+  tableName: string,
+  schema?: string
+) {
+  return await invoke<TableChanges>("get_pending_changes", {
+    connectionId,
+    schema,
+    tableName,
+  });
+}
+
+export async function previewPendingChanges(
+  connectionId: string,
+  tableName: string,
+  schema?: string
+) {
+  return await invoke<string[]>("preview_pending_changes", {
+    connectionId,
+    schema,
+    tableName,
+  });
+}
+
+export async function applyPendingChanges(
+  connectionId: string,
+  tableName: string,
+  schema?: string
+) {
+  return await invoke<number>("apply_pending_changes", {
+    connectionId,
+    schema,
+    tableName,
+  });
+}
+
+export async function discardPendingChanges(
+  connectionId: string,
+  tableName: string,
+  schema?: string
+) {
+  return await invoke("discard_pending_changes", {
+    connectionId,
+    schema,
+    tableName,
+  });
+}
+
+export async function executeQuery(
+  connectionId: string,
+  query: string,
+  queryId?: string,
+  sessionId?: string
+) {
+  return await invoke<QueryResult>("execute_query", {
+    connectionId,
+    query,
+    queryId,
+    sessionId,
+  });
+}
+
+export interface StatementResult {
+  sql: string;
+  offset: number;
+  line: number;
+  column: number;
+  result?: QueryResult;
+  duration_ms: number;
+  error?: DatabaseError;
+  error_offset?: number;
+}
+
+export async function executeScript(
+  connectionId: string,
+  script: string,
+  stopOnError = true,
+  queryId?: string,
+  sessionId?: string
+) {
+  return await invoke<StatementResult[]>("execute_script", {
+    connectionId,
+    script,
+    stopOnError,
+    queryId,
+    sessionId,
+  });
+}
+
+export async function cancelQuery(connectionId: string, queryId: string) {
+  return await invoke<boolean>("cancel_query", { connectionId, queryId });
+}
+
+export type QueryStreamEvent =
+  | { event: "columns"; data: { columns: string[]; types: string[] } }
+  | { event: "rows"; data: { rows: any[][] } }
+  | { event: "finished"; data: { row_count: number; truncated: boolean } };
+
+export async function executeQueryStream(
+  connectionId: string,
+  query: string,
+  onEvent: (event: QueryStreamEvent) => void,
+  options: {
+    queryId?: string;
+    sessionId?: string;
+    batchSize?: number;
+    maxRows?: number;
+  } = {}
+): Promise<void> {
+  const channel = new Channel<QueryStreamEvent>();
+  channel.onmessage = onEvent;
+  return await invoke("execute_query_stream", {
+    connectionId,
+    query,
+    queryId: options.queryId,
+    sessionId: options.sessionId,
+    batchSize: options.batchSize,
+    maxRows: options.maxRows,
+    onEvent: channel,
+  });
+}
+
+export interface TransactionState {
+  session_id: string;
+  active: boolean;
+  savepoints: string[];
+  started_at?: string;
+}
+
+export async function beginTransaction(connectionId: string, sessionId: string) {
+  return await invoke<TransactionState>("begin_transaction", {
+    connectionId,
+    sessionId,
+  });
+}
+
+export async function commitTransaction(
+  connectionId: string,
+  sessionId: string
+) {
+  return await invoke<TransactionState>("commit_transaction", {
+    connectionId,
+    sessionId,
+  });
+}
+
+export async function rollbackTransaction(
+  connectionId: string,
+  sessionId: string
+) {
+  return await invoke<TransactionState>("rollback_transaction", {
+    connectionId,
+    sessionId,
+  });
+}
+
+export async function createSavepoint(
+  connectionId: string,
+  sessionId: string,
+  name: string
+) {
+  return await invoke<TransactionState>("create_savepoint", {
+    connectionId,
+    sessionId,
+    name,
+  });
+}
+
+export async function rollbackToSavepoint(
+  connectionId: string,
+  sessionId: string,
+  name: string
+) {
+  return await invoke<TransactionState>("rollback_to_savepoint", {
+    connectionId,
+    sessionId,
+    name,
+  });
+}
+
+export async function releaseSavepoint(
+  connectionId: string,
+  sessionId: string,
+  name: string
+) {
+  return await invoke<TransactionState>("release_savepoint", {
+    connectionId,
+    sessionId,
+    name,
+  });
+}
+
+export async function getTransactionState(
+  connectionId: string,
+  sessionId: string
+) {
+  return await invoke<TransactionState>("get_transaction_state", {
+    connectionId,
+    sessionId,
+  });
 }
 
 export async function getDatabaseSchema(connectionId: string) {
@@ -133,6 +924,133 @@ export async function deleteConnection(id: string) {
   return await invoke("delete_connection", { id });
 }
 
+export type ConnectionSource =
+  | { kind: "urls"; text: string }
+  | { kind: "pgpass"; path?: string }
+  | { kind: "my_cnf"; path?: string }
+  | { kind: "pg_service"; path?: string };
+
+export interface ImportedConnection {
+  /** The connection as it would be saved, without its passwords. */
+  connection: SavedConnection;
+  has_password: boolean;
+  origin: string;
+  /** Settings from the source that are not saved, such as connect_timeout. */
+  parameters: Record<string, string>;
+  /** Id of an already saved connection to the same target; not imported. */
+  duplicate_of?: string;
+}
+
+export interface ConnectionImport {
+  connections: ImportedConnection[];
+  issues: { origin: string; message: string }[];
+  created: number;
+}
+
+/**
+ * Reads connections from DSNs or another client's config files. With
+ * `dryRun` nothing is saved, so the result can be shown as a preview.
+ */
+export async function importConnections(
+  source: ConnectionSource,
+  dryRun: boolean
+) {
+  return await invoke<ConnectionImport>("import_connections", {
+    source,
+    dryRun,
+  });
+}
+
+/**
+ * Asks for a destination and writes the connections (all of them when `ids`
+ * is omitted) to a bundle. Passwords are only included with `includeSecrets`,
+ * which requires a bundle `password`. Resolves to null when the dialog is
+ * dismissed.
+ */
+export async function exportConnections(
+  includeSecrets: boolean,
+  password?: string,
+  ids?: string[]
+): Promise<number | null> {
+  const path = await save({
+    defaultPath: "connections-bundle.json",
+    filters: [{ name: "Connection bundle", extensions: ["json"] }],
+  });
+  if (!path) return null;
+
+  return await invoke<number>("export_connections", {
+    path,
+    ids,
+    includeSecrets,
+    password,
+  });
+}
+
+/** Asks for a connection bundle to import; null when the dialog is dismissed. */
+export async function chooseConnectionBundle(): Promise<string | null> {
+  return await open({
+    multiple: false,
+    directory: false,
+    filters: [{ name: "Connection bundle", extensions: ["json"] }],
+  });
+}
+
+export type ConflictResolution = "skip" | "overwrite" | "duplicate";
+
+export interface BundleEntry {
+  /** The connection as it would be saved, without its passwords. */
+  connection: SavedConnection;
+  has_password: boolean;
+  /** Whether a connection with the same id is already saved. */
+  conflict: boolean;
+  action: "create" | "overwrite" | "duplicate" | "skip";
+}
+
+export interface BundleImport {
+  entries: BundleEntry[];
+  imported: number;
+}
+
+export async function importConnectionBundle(
+  path: string,
+  onConflict: ConflictResolution,
+  dryRun: boolean,
+  password?: string
+) {
+  return await invoke<BundleImport>("import_connection_bundle", {
+    path,
+    password,
+    onConflict,
+    dryRun,
+  });
+}
+
+export interface CredentialStoreStatus {
+  /** Whether a master password has been set. */
+  initialized: boolean;
+  unlocked: boolean;
+  /** Saved connections whose passwords are still stored in plaintext. */
+  plaintext_connections: number;
+}
+
+export async function getCredentialStoreStatus() {
+  return await invoke<CredentialStoreStatus>("get_credential_store_status");
+}
+
+/**
+ * Unlocks the credential store, setting the master password if none is set
+ * yet. Passwords still stored in plaintext are moved into the store.
+ */
+export async function unlockCredentialStore(masterPassword: string) {
+  return await invoke<CredentialStoreStatus>("unlock_credential_store", {
+    masterPassword,
+  });
+}
+
+export async function lockCredentialStore() {
+  return await invoke<CredentialStoreStatus>("lock_credential_store");
+}
+
 // State persistence types
 export interface TabData {
   query?: string;